@@ -0,0 +1,84 @@
+use crate::ssh_client::{normalize_mac, parse_neighbors, Neighbor};
+use std::io;
+use std::net::IpAddr;
+use std::process::Command;
+use tracing::debug;
+
+/// The kernel's IPv4 ARP table on Linux.
+const PROC_NET_ARP: &str = "/proc/net/arp";
+
+/// Read this machine's ARP/neighbor table: `/proc/net/arp` on Linux,
+/// else the output of `ip neigh`, `arp -an` or, on Windows, `arp -a`.
+/// Entries without a resolved MAC are skipped; the rest may be stale.
+///
+/// This is a **blocking** function.
+pub fn read() -> io::Result<Vec<Neighbor>> {
+    match std::fs::read_to_string(PROC_NET_ARP) {
+        Ok(table) => return Ok(parse_proc_net_arp(&table)),
+        Err(e) => debug!("Cannot read {}: {}", PROC_NET_ARP, e),
+    }
+    let commands: &[(&str, &[&str])] = if cfg!(windows) {
+        &[("arp", &["-a"])]
+    } else {
+        &[("ip", &["neigh", "show"]), ("arp", &["-an"])]
+    };
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no neighbor table command");
+    for &(program, args) in commands {
+        match Command::new(program).args(args).output() {
+            Ok(output) if output.status.success() => {
+                let text = String::from_utf8_lossy(&output.stdout);
+                return Ok(if cfg!(windows) {
+                    parse_windows_arp(&text)
+                } else {
+                    parse_neighbors(&text)
+                });
+            }
+            Ok(output) => {
+                let message = format!("`{}` exited with {}", program, output.status);
+                last_error = io::Error::other(message);
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Parse `/proc/net/arp`: a header line, then `IP HW-type Flags MAC Mask
+/// Device` rows. Incomplete entries have flags `0x0` and a zero MAC.
+fn parse_proc_net_arp(table: &str) -> Vec<Neighbor> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [ip, _, flags, mac, ..] = fields.as_slice() else {
+                return None;
+            };
+            let mac = normalize_mac(mac).filter(|mac| mac != "00:00:00:00:00:00")?;
+            (*flags != "0x0" && ip.parse::<IpAddr>().is_ok()).then(|| Neighbor {
+                ip: ip.to_string(),
+                mac,
+                hostname: None,
+            })
+        })
+        .collect()
+}
+
+/// Parse Windows `arp -a` rows: `  10.8.0.42   aa-bb-cc-dd-ee-ff   dynamic`.
+fn parse_windows_arp(output: &str) -> Vec<Neighbor> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [ip, mac, _] = fields.as_slice() else {
+                return None;
+            };
+            let mac = normalize_mac(mac)?;
+            ip.parse::<IpAddr>().is_ok().then(|| Neighbor {
+                ip: ip.to_string(),
+                mac,
+                hostname: None,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,327 @@
+#[cfg(not(target_os = "linux"))]
+compile_error!("the `arp` feature needs Linux AF_PACKET sockets");
+
+use crate::errors::RadarError;
+use crate::ssh_client::Neighbor;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::net::Ipv4Addr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Ethernet + ARP request/reply for IPv4 over Ethernet.
+const FRAME_LEN: usize = 42;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const BROADCAST: [u8; 6] = [0xff; 6];
+
+/// Raw ARP discovery configuration.
+#[derive(Debug, Clone)]
+pub struct ArpConfig {
+    /// Local interface to send requests on (e.g. `eth0`).
+    pub interface: String,
+    /// How long to keep listening for replies after the last request.
+    pub timeout: Duration,
+}
+
+impl ArpConfig {
+    /// Send an ARP request for every host in `hosts` on the local segment
+    /// and return the neighbors that replied, sorted by address. Returns
+    /// early once every MAC in `stop_at` (lowercase) has replied; an empty
+    /// `stop_at` waits out the full timeout.
+    ///
+    /// Needs `CAP_NET_RAW` (or root). This is a **blocking** function and is
+    /// intended to be called from inside `tokio::task::spawn_blocking`.
+    pub fn sweep(
+        &self,
+        hosts: &[Ipv4Addr],
+        stop_at: &[String],
+    ) -> Result<Vec<Neighbor>, RadarError> {
+        let err = |e: io::Error| RadarError::Arp(self.interface.clone(), e.to_string());
+        let socket = Socket::open(&self.interface).map_err(err)?;
+        info!(
+            "ARP sweep of {} host(s) on {} from {} ({})",
+            hosts.len(),
+            self.interface,
+            socket.ip,
+            format_mac(&socket.mac)
+        );
+
+        let wanted: HashSet<Ipv4Addr> = hosts.iter().copied().collect();
+        let mut replies = BTreeMap::new();
+        let mut buf = [0u8; 1500];
+        let done = |replies: &BTreeMap<Ipv4Addr, String>| {
+            !stop_at.is_empty() && stop_at.iter().all(|mac| replies.values().any(|m| m == mac))
+        };
+
+        // Drain replies between requests so a large range cannot overflow
+        // the socket's receive buffer.
+        for ip in hosts {
+            socket.send_request(*ip).map_err(err)?;
+            while let Some(reply) = socket.recv(&mut buf, Duration::ZERO).map_err(err)? {
+                record(&mut replies, &wanted, reply);
+            }
+            if done(&replies) {
+                return Ok(neighbors(replies));
+            }
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        while !done(&replies) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            if let Some(reply) = socket.recv(&mut buf, left).map_err(err)? {
+                record(&mut replies, &wanted, reply);
+            }
+        }
+
+        Ok(neighbors(replies))
+    }
+}
+
+/// Keep a reply from a requested host; the first answer per IP wins.
+fn record(
+    replies: &mut BTreeMap<Ipv4Addr, String>,
+    wanted: &HashSet<Ipv4Addr>,
+    (ip, mac): (Ipv4Addr, [u8; 6]),
+) {
+    if wanted.contains(&ip) {
+        replies.entry(ip).or_insert_with(|| format_mac(&mac));
+    }
+}
+
+fn neighbors(replies: BTreeMap<Ipv4Addr, String>) -> Vec<Neighbor> {
+    replies
+        .into_iter()
+        .map(|(ip, mac)| Neighbor {
+            ip: ip.to_string(),
+            mac,
+            hostname: None,
+        })
+        .collect()
+}
+
+/// Lowercase, colon-separated.
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// A non-blocking `AF_PACKET` socket bound to one interface for ARP.
+struct Socket {
+    fd: OwnedFd,
+    ifindex: i32,
+    mac: [u8; 6],
+    ip: Ipv4Addr,
+}
+
+impl Socket {
+    fn open(interface: &str) -> io::Result<Self> {
+        let protocol = ETHERTYPE_ARP.to_be();
+        // SAFETY: plain socket(2) call; the result is checked before use.
+        let raw = unsafe {
+            libc::socket(
+                libc::AF_PACKET,
+                libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                protocol as libc::c_int,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `raw` is a freshly opened descriptor owned by nobody else.
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let mut req = ifreq(interface)?;
+        ioctl(&fd, libc::SIOCGIFINDEX as _, &mut req)?;
+        // SAFETY: SIOCGIFINDEX fills `ifru_ifindex`.
+        let ifindex = unsafe { req.ifr_ifru.ifru_ifindex };
+
+        ioctl(&fd, libc::SIOCGIFHWADDR as _, &mut req)?;
+        // SAFETY: SIOCGIFHWADDR fills `ifru_hwaddr`.
+        let hw = unsafe { req.ifr_ifru.ifru_hwaddr.sa_data };
+        let mut mac = [0u8; 6];
+        for (dst, src) in mac.iter_mut().zip(hw) {
+            *dst = src as u8;
+        }
+
+        // The interface needs an IPv4 address to source requests from.
+        let mut req = ifreq(interface)?;
+        ioctl(&fd, libc::SIOCGIFADDR as _, &mut req)
+            .map_err(|e| io::Error::new(e.kind(), format!("no IPv4 address ({})", e)))?;
+        // SAFETY: SIOCGIFADDR fills `ifru_addr` with a `sockaddr_in`.
+        let ip = unsafe {
+            let addr = &req.ifr_ifru.ifru_addr as *const libc::sockaddr;
+            let sin = &*(addr as *const libc::sockaddr_in);
+            Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))
+        };
+
+        let addr = link_addr(ifindex, [0; 6]);
+        // SAFETY: `addr` is a valid `sockaddr_ll` of the given length.
+        let rc = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { fd, ifindex, mac, ip })
+    }
+
+    /// Broadcast "who has `target`?".
+    fn send_request(&self, target: Ipv4Addr) -> io::Result<()> {
+        let mut frame = [0u8; FRAME_LEN];
+        frame[0..6].copy_from_slice(&BROADCAST);
+        frame[6..12].copy_from_slice(&self.mac);
+        frame[12..14].copy_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+        frame[14..16].copy_from_slice(&1u16.to_be_bytes()); // Ethernet
+        frame[16..18].copy_from_slice(&0x0800u16.to_be_bytes()); // IPv4
+        frame[18] = 6;
+        frame[19] = 4;
+        frame[20..22].copy_from_slice(&1u16.to_be_bytes()); // request
+        frame[22..28].copy_from_slice(&self.mac);
+        frame[28..32].copy_from_slice(&self.ip.octets());
+        frame[38..42].copy_from_slice(&target.octets());
+
+        let addr = link_addr(self.ifindex, BROADCAST);
+        loop {
+            // SAFETY: `frame` and `addr` are valid for the lengths passed.
+            let rc = unsafe {
+                libc::sendto(
+                    self.fd.as_raw_fd(),
+                    frame.as_ptr().cast(),
+                    frame.len(),
+                    0,
+                    &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                    std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+                )
+            };
+            if rc >= 0 {
+                return Ok(());
+            }
+            let e = io::Error::last_os_error();
+            match e.kind() {
+                // The TX queue is full; give it a moment to drain.
+                io::ErrorKind::WouldBlock => {
+                    self.wait(libc::POLLOUT, Duration::from_millis(10))?;
+                }
+                io::ErrorKind::Interrupted => {}
+                _ => return Err(e),
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the next ARP reply, as `(sender IP, MAC)`.
+    /// Non-reply frames are skipped without waiting again.
+    fn recv(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> io::Result<Option<(Ipv4Addr, [u8; 6])>> {
+        if !timeout.is_zero() && !self.wait(libc::POLLIN, timeout)? {
+            return Ok(None);
+        }
+        loop {
+            // SAFETY: `buf` is valid for writes of `buf.len()` bytes.
+            let n = unsafe {
+                libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0)
+            };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                return match e.kind() {
+                    io::ErrorKind::WouldBlock => Ok(None),
+                    io::ErrorKind::Interrupted => continue,
+                    _ => Err(e),
+                };
+            }
+            if let Some(reply) = parse_reply(&buf[..n as usize]) {
+                return Ok(Some(reply));
+            }
+        }
+    }
+
+    /// Poll for `events`; `false` on timeout.
+    fn wait(&self, events: libc::c_short, timeout: Duration) -> io::Result<bool> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events,
+            revents: 0,
+        };
+        let ms = timeout.as_millis().clamp(1, i32::MAX as u128) as libc::c_int;
+        // SAFETY: `pfd` is a single valid pollfd.
+        match unsafe { libc::poll(&mut pfd, 1, ms) } {
+            n if n > 0 => Ok(true),
+            0 => Ok(false),
+            _ => {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    Ok(true)
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+}
+
+/// Sender of an IPv4-over-Ethernet ARP reply.
+fn parse_reply(frame: &[u8]) -> Option<(Ipv4Addr, [u8; 6])> {
+    if frame.len() < FRAME_LEN
+        || frame[12..14] != ETHERTYPE_ARP.to_be_bytes()
+        || frame[14..16] != [0, 1]
+        || frame[16..18] != [0x08, 0x00]
+        || frame[18] != 6
+        || frame[19] != 4
+        || frame[20..22] != [0, 2]
+    {
+        return None;
+    }
+    let mac: [u8; 6] = frame[22..28].try_into().ok()?;
+    let ip: [u8; 4] = frame[28..32].try_into().ok()?;
+    Some((Ipv4Addr::from(ip), mac))
+}
+
+fn link_addr(ifindex: i32, addr: [u8; 6]) -> libc::sockaddr_ll {
+    let mut sll_addr = [0u8; 8];
+    sll_addr[..6].copy_from_slice(&addr);
+    libc::sockaddr_ll {
+        sll_family: libc::AF_PACKET as libc::c_ushort,
+        sll_protocol: ETHERTYPE_ARP.to_be(),
+        sll_ifindex: ifindex,
+        sll_hatype: 0,
+        sll_pkttype: 0,
+        sll_halen: 6,
+        sll_addr,
+    }
+}
+
+fn ifreq(interface: &str) -> io::Result<libc::ifreq> {
+    if interface.is_empty() || interface.len() >= libc::IFNAMSIZ || interface.contains('\0') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid interface name",
+        ));
+    }
+    // SAFETY: `ifreq` is plain old data; all-zero is a valid value.
+    let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in req.ifr_name.iter_mut().zip(interface.bytes()) {
+        *dst = src as libc::c_char;
+    }
+    Ok(req)
+}
+
+fn ioctl(fd: &OwnedFd, request: libc::c_ulong, req: &mut libc::ifreq) -> io::Result<()> {
+    // SAFETY: every request used here reads and writes a single `ifreq`.
+    if unsafe { libc::ioctl(fd.as_raw_fd(), request as _, req as *mut libc::ifreq) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::errors::RadarError;
+use crate::ssh_client::Neighbor;
+use regex::Regex;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Sent to the broadcast address with [`BeaconConfig::solicit`]; devices
+/// answer it with a beacon right away instead of at their next interval.
+const SOLICITATION: &[u8] = b"RADAR-IP DISCOVER";
+
+/// UDP identification beacon discovery configuration.
+#[derive(Debug, Clone)]
+pub struct BeaconConfig {
+    /// UDP port the devices send their beacons to.
+    pub port: u16,
+    /// How long to listen for beacons.
+    pub timeout: Duration,
+    /// Broadcast a solicitation before listening.
+    pub solicit: bool,
+}
+
+impl BeaconConfig {
+    /// Listen for beacons from the hosts in `hosts` and return the
+    /// neighbors they identify, sorted by address. A beacon is any datagram
+    /// naming a MAC in text (`aa:bb:cc:dd:ee:ff` or `aa-bb-…`), or exactly
+    /// six raw bytes; its source address is taken as the device's IP.
+    /// Returns early once every MAC in `stop_at` (lowercase) has been
+    /// heard; an empty `stop_at` listens for the full timeout.
+    ///
+    /// This is a **blocking** function and is intended to be called from
+    /// inside `tokio::task::spawn_blocking`.
+    pub fn listen(
+        &self,
+        hosts: &[Ipv4Addr],
+        stop_at: &[String],
+    ) -> Result<Vec<Neighbor>, RadarError> {
+        let err = |e: io::Error| RadarError::Beacon(self.port, e.to_string());
+        let socket = self.open().map_err(err)?;
+        info!(
+            "Listening for beacons on UDP port {} for {}",
+            self.port,
+            humantime::format_duration(self.timeout)
+        );
+        if self.solicit {
+            socket
+                .send_to(SOLICITATION, (Ipv4Addr::BROADCAST, self.port))
+                .map_err(err)?;
+        }
+
+        let mac_re = Regex::new(r"(?i)\b[0-9a-f]{2}(?:[:-][0-9a-f]{2}){5}\b")
+            .expect("MAC regex is valid");
+        let wanted: HashSet<Ipv4Addr> = hosts.iter().copied().collect();
+        let mut heard = BTreeMap::new();
+        let mut buf = [0u8; 1500];
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            let done =
+                !stop_at.is_empty() && stop_at.iter().all(|mac| heard.values().any(|m| m == mac));
+            let left = deadline.saturating_duration_since(Instant::now());
+            if done || left.is_zero() {
+                break;
+            }
+            socket.set_read_timeout(Some(left)).map_err(err)?;
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    break
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(err(e)),
+            };
+            let SocketAddr::V4(from) = from else { continue };
+            if !wanted.contains(from.ip()) {
+                continue;
+            }
+            match parse_beacon(&buf[..len], &mac_re) {
+                Some(mac) => {
+                    debug!("Beacon from {}: {}", from.ip(), mac);
+                    heard.entry(*from.ip()).or_insert(mac);
+                }
+                None => debug!("Ignoring {}-byte datagram from {}", len, from.ip()),
+            }
+        }
+
+        Ok(heard
+            .into_iter()
+            .map(|(ip, mac)| Neighbor {
+                ip: ip.to_string(),
+                mac,
+                hostname: None,
+            })
+            .collect())
+    }
+
+    /// Bind the beacon port, shared with any other listener on this machine.
+    fn open(&self) -> io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.set_broadcast(true)?;
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, self.port)).into())?;
+        Ok(socket.into())
+    }
+}
+
+/// The MAC a beacon names (lowercase, colon-separated). Our own
+/// solicitation, echoed back by the broadcast, names none.
+fn parse_beacon(payload: &[u8], mac_re: &Regex) -> Option<String> {
+    if let Ok(raw) = <[u8; 6]>::try_from(payload) {
+        return (raw != [0; 6] && raw != [0xff; 6]).then(|| {
+            raw.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(":")
+        });
+    }
+    let text = String::from_utf8_lossy(payload);
+    mac_re
+        .find(&text)
+        .map(|m| m.as_str().to_lowercase().replace('-', ":"))
+}
@@ -0,0 +1,79 @@
+use thiserror::Error;
+
+/// All domain-level errors for the radar-ip tool.
+#[derive(Error, Debug)]
+pub enum RadarError {
+    #[error("SSH connection error to {0}: {1}")]
+    SshConnection(String, String),
+
+    #[error("SSH command execution error on {0}: {1}")]
+    CommandExecution(String, String),
+
+    #[error("Host key mismatch for {0}: {1}")]
+    HostKeyMismatch(String, String),
+
+    #[error("Invalid IP range: '{0}'")]
+    InvalidIpRange(String),
+
+    #[error("Cannot resolve host name '{0}': {1}")]
+    UnresolvedHost(String, String),
+
+    #[error(
+        "IP range '{0}' has more than 2^{} hosts; split it into smaller ranges",
+        crate::scanner::MAX_HOST_BITS
+    )]
+    RangeTooLarge(String),
+
+    #[error(
+        "Expanding IP range '{0}' took longer than {}; use a smaller range",
+        humantime::format_duration(*.1)
+    )]
+    RangeParseTimeout(String, std::time::Duration),
+
+    #[error("Private key authentication error: {0}")]
+    PrivateKey(String),
+
+    #[error("Password authentication error: {0}")]
+    Password(String),
+
+    #[error("SSH agent authentication error: {0}")]
+    Agent(String),
+
+    #[error("MAC address '{0}' not found on any host in the scanned range")]
+    MacNotFound(String),
+
+    #[error("MAC address '{0}' not found: no host in the scanned range could be probed ({1})")]
+    NoHostReachable(String, String),
+
+    #[error("Config error in {0}: {1}")]
+    Config(String, String),
+
+    #[error("Checkpoint error in {0}: {1}")]
+    Checkpoint(String, String),
+
+    #[error("Scan was cancelled")]
+    Cancelled,
+
+    #[error("MAC address '{0}' is owned by {n} hosts: {ips}", n = .1.len(), ips = .1.join(", "))]
+    MultipleMatches(String, Vec<String>),
+
+    #[error("UDP beacon error on port {0}: {1}")]
+    Beacon(u16, String),
+
+    #[error("mDNS error: {0}")]
+    Mdns(String),
+
+    #[error("SSDP error: {0}")]
+    Ssdp(String),
+
+    #[error("SFTP error on {0}: {1}")]
+    Sftp(String, String),
+
+    #[cfg(feature = "arp")]
+    #[error("ARP scan error on {0}: {1}")]
+    Arp(String, String),
+
+    #[cfg(feature = "snmp")]
+    #[error("SNMP error on {0}: {1}")]
+    Snmp(String, String),
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+//! Find which host in an IP range owns a given MAC address.
+//!
+//! This is the engine behind the `radar-ip` CLI and GUI. A [`Scanner`]
+//! probes every host of a range, by default over SSH (see [`SshConfig`]
+//! and [`AuthenticationMethod`]), and returns a [`ScanReport`] of the
+//! matches, per-host results and counters. Options are set with the
+//! `with_*` builder methods:
+//!
+//! ```no_run
+//! use radar_ip_core::{
+//!     AuthenticationMethod, HostKeyCheck, Platform, ScanMode, ScanRequest, Scanner, SshConfig,
+//!     SshTimeouts,
+//! };
+//! use std::time::Duration;
+//!
+//! # async fn locate() -> Result<(), radar_ip_core::RadarError> {
+//! let config = SshConfig {
+//!     user: "pi".into(),
+//!     port: 22,
+//!     auth: AuthenticationMethod::Agent,
+//!     timeouts: SshTimeouts::uniform(Duration::from_secs(3)),
+//!     pinned_host_keys: Default::default(),
+//!     host_key_check: HostKeyCheck::Disabled,
+//!     interface_filter: None,
+//!     probe: None,
+//!     platform: Platform::Auto,
+//!     via: None,
+//!     details: false,
+//!     pool: None,
+//! };
+//! let report = Scanner::new(config, vec!["b8:27:eb:12:34:56".into()])
+//!     .with_jitter(Duration::from_millis(50))
+//!     .run(ScanRequest {
+//!         range: "192.168.1.0/24".into(),
+//!         hosts: Vec::new(),
+//!         mode: ScanMode::FirstMatch,
+//!     })
+//!     .await?;
+//! for ip in report.into_ips("b8:27:eb:12:34:56")? {
+//!     println!("{}", ip);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Blocking work (SSH sessions, raw sockets) runs on tokio's blocking pool,
+//! so [`Scanner::run`] must be awaited inside a tokio runtime.
+//!
+//! # Features
+//!
+//! - `arp`: [`Scanner::new_arp`], raw ARP requests on the local segment
+//!   (Linux, needs `CAP_NET_RAW`).
+//! - `snmp`: [`snmp_client`], locating a MAC on a switch's forwarding table, and
+//!   reading the interface MACs of hosts that refuse SSH.
+
+pub mod arp_cache;
+#[cfg(feature = "arp")]
+pub mod arp_scan;
+pub mod beacon;
+pub mod errors;
+pub mod mdns;
+pub mod oui;
+pub mod ping;
+pub mod rdns;
+pub mod report;
+pub mod scanner;
+pub mod ssdp;
+#[cfg(feature = "snmp")]
+pub mod snmp_client;
+pub mod ssh_client;
+
+pub use errors::RadarError;
+pub use report::{
+    Discovered, HostResult, HostStatus, NotFoundReason, ScanMatch, ScanReport, ScanStats,
+};
+pub use scanner::{CancelToken, ScanMode, ScanProgress, ScanRequest, ScanStream, Scanner};
+pub use ssh_client::{
+    AuthenticationMethod, CommandOutput, HostKeyCheck, JumpHost, Platform, ProbeAbort, SessionPool,
+    SshConfig, SshTimeouts,
+};
@@ -0,0 +1,263 @@
+use crate::errors::RadarError;
+use crate::ssh_client::normalize_mac;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// The mDNS group and port (RFC 6762).
+const MDNS_GROUP: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
+
+/// Service types browsed unless [`MdnsConfig::services`] says otherwise.
+pub const DEFAULT_SERVICES: [&str; 1] = ["_ssh._tcp"];
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+
+/// mDNS/DNS-SD browsing configuration.
+#[derive(Debug, Clone)]
+pub struct MdnsConfig {
+    /// Service types to browse, e.g. `_ssh._tcp`; `.local` is implied.
+    pub services: Vec<String>,
+    /// How long to collect answers.
+    pub timeout: Duration,
+}
+
+/// A host that answered for one of the browsed services.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advertiser {
+    pub ip: IpAddr,
+    /// The SRV target, e.g. `raspberrypi.local`.
+    pub hostname: Option<String>,
+    /// The service instance it advertises, e.g. `raspberrypi._ssh._tcp.local`.
+    pub instance: String,
+    /// A MAC named in the instance's TXT record (`mac=…`, `deviceid=…`),
+    /// lowercase and colon-separated.
+    pub mac: Option<String>,
+}
+
+impl MdnsConfig {
+    /// Ask the local segment who offers [`MdnsConfig::services`] and return
+    /// every advertiser that answered within the timeout, one per IP,
+    /// sorted by address. Returns early once TXT records have named every
+    /// MAC in `stop_at` (lowercase); an empty `stop_at` listens for the full
+    /// timeout.
+    ///
+    /// The query is a one-shot (legacy unicast) query from an ephemeral
+    /// port, so it works next to a running Avahi or Bonjour daemon; only
+    /// IPv4 is asked. This is a **blocking** function and is intended to be
+    /// called from inside `tokio::task::spawn_blocking`.
+    pub fn browse(&self, stop_at: &[String]) -> Result<Vec<Advertiser>, RadarError> {
+        let err = |e: io::Error| RadarError::Mdns(e.to_string());
+        let services: Vec<String> = self.services.iter().map(|s| service_name(s)).collect();
+        let socket = open().map_err(err)?;
+        socket.send_to(&query(&services), MDNS_GROUP).map_err(err)?;
+        info!(
+            "Browsing mDNS for {} for {}",
+            services.join(", "),
+            humantime::format_duration(self.timeout)
+        );
+
+        let mut answers = Answers::default();
+        let mut buf = [0u8; 9000];
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let done = !stop_at.is_empty() && {
+                let named: Vec<_> = answers.advertisers(&services);
+                stop_at.iter().all(|mac| named.iter().any(|a| a.mac.as_ref() == Some(mac)))
+            };
+            let left = deadline.saturating_duration_since(Instant::now());
+            if done || left.is_zero() {
+                break;
+            }
+            socket.set_read_timeout(Some(left)).map_err(err)?;
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    break
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(err(e)),
+            };
+            if answers.add(&buf[..len], from.ip()).is_none() {
+                debug!("Ignoring malformed {}-byte mDNS answer from {}", len, from.ip());
+            }
+        }
+        Ok(answers.advertisers(&services))
+    }
+}
+
+/// `service` as a fully qualified, lowercase name without the final dot.
+fn service_name(service: &str) -> String {
+    let service = service.trim().trim_end_matches('.').to_lowercase();
+    if service.ends_with(".local") {
+        service
+    } else {
+        format!("{}.local", service)
+    }
+}
+
+/// A UDP socket on an ephemeral port that may send to the mDNS group.
+fn open() -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_multicast_ttl_v4(255)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)).into())?;
+    Ok(socket.into())
+}
+
+/// A DNS query with one PTR question per service.
+fn query(services: &[String]) -> Vec<u8> {
+    let mut packet = vec![0, 0, 0, 0];
+    packet.extend_from_slice(&(services.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[0; 6]);
+    for service in services {
+        for label in service.split('.') {
+            packet.push(label.len().min(63) as u8);
+            packet.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+        }
+        packet.push(0);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&1u16.to_be_bytes()); // IN
+    }
+    packet
+}
+
+/// The records of every answer received, by owner name (lowercase).
+#[derive(Debug, Default)]
+struct Answers {
+    /// Service → its instances.
+    ptr: BTreeMap<String, Vec<String>>,
+    /// Instance → the host it runs on.
+    srv: HashMap<String, String>,
+    /// Instance → its TXT strings.
+    txt: HashMap<String, Vec<String>>,
+    /// Host → its IPv4 address.
+    a: HashMap<String, Ipv4Addr>,
+    /// Instance → who answered for it, if its host has no A record.
+    source: HashMap<String, IpAddr>,
+}
+
+impl Answers {
+    /// Add the records of the DNS message `packet` sent by `from`; `None`
+    /// if it is malformed.
+    fn add(&mut self, packet: &[u8], from: IpAddr) -> Option<()> {
+        if u16_at(packet, 2)? & 0x8000 == 0 {
+            return Some(()); // a query, e.g. our own
+        }
+        let questions = u16_at(packet, 4)?;
+        let records = (6..=10).step_by(2).map(|at| u16_at(packet, at).map(usize::from));
+        let records: usize = records.sum::<Option<usize>>()?;
+        let mut pos = 12;
+        for _ in 0..questions {
+            pos = read_name(packet, pos)?.1 + 4;
+        }
+        for _ in 0..records {
+            let (name, next) = read_name(packet, pos)?;
+            let rtype = u16_at(packet, next)?;
+            let len = u16_at(packet, next + 8)? as usize;
+            let data = next + 10;
+            let rdata = packet.get(data..data + len)?;
+            match rtype {
+                TYPE_PTR => {
+                    let (instance, _) = read_name(packet, data)?;
+                    self.source.entry(instance.clone()).or_insert(from);
+                    let instances = self.ptr.entry(name).or_default();
+                    if !instances.contains(&instance) {
+                        instances.push(instance);
+                    }
+                }
+                TYPE_SRV if len > 6 => {
+                    let (target, _) = read_name(packet, data + 6)?;
+                    self.source.entry(name.clone()).or_insert(from);
+                    self.srv.insert(name, target);
+                }
+                TYPE_TXT => {
+                    self.txt.insert(name, txt_strings(rdata));
+                }
+                TYPE_A if len == 4 => {
+                    self.a.insert(name, Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]));
+                }
+                _ => {}
+            }
+            pos = data + len;
+        }
+        Some(())
+    }
+
+    /// One advertiser per IP among the instances of `services`.
+    fn advertisers(&self, services: &[String]) -> Vec<Advertiser> {
+        let mut found = BTreeMap::new();
+        let instances = services.iter().filter_map(|s| self.ptr.get(s)).flatten();
+        for instance in instances {
+            let hostname = self.srv.get(instance);
+            let ip = hostname
+                .and_then(|host| self.a.get(host))
+                .map(|&v4| IpAddr::V4(v4))
+                .or_else(|| self.source.get(instance).copied());
+            let Some(ip) = ip else { continue };
+            let mac = self.txt.get(instance).and_then(|txt| {
+                txt.iter()
+                    .filter_map(|entry| entry.split_once('='))
+                    .find_map(|(_, value)| normalize_mac(value))
+            });
+            let advertiser = found.entry(ip).or_insert_with(|| Advertiser {
+                ip,
+                hostname: hostname.cloned(),
+                instance: instance.clone(),
+                mac: None,
+            });
+            if advertiser.mac.is_none() {
+                advertiser.mac = mac;
+            }
+        }
+        found.into_values().collect()
+    }
+}
+
+/// The big-endian `u16` at `at` of `packet`.
+fn u16_at(packet: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]))
+}
+
+/// The (possibly compressed) name at `pos` of `packet`, lowercase without
+/// the final dot, and the position after it.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bounds the pointer chain, which a hostile packet could loop.
+    for _ in 0..128 {
+        let len = *packet.get(pos)? as usize;
+        match len {
+            0 => {
+                let name = labels.join(".").to_lowercase();
+                return Some((name, end.unwrap_or(pos + 1)));
+            }
+            len if len & 0xC0 == 0xC0 => {
+                let target = (len & 0x3F) << 8 | *packet.get(pos + 1)? as usize;
+                end.get_or_insert(pos + 2);
+                pos = target;
+            }
+            len => {
+                let label = packet.get(pos + 1..pos + 1 + len)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + len;
+            }
+        }
+    }
+    None
+}
+
+/// The length-prefixed strings of a TXT record.
+fn txt_strings(mut rdata: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    while let Some((&len, rest)) = rdata.split_first() {
+        let len = (len as usize).min(rest.len());
+        strings.push(String::from_utf8_lossy(&rest[..len]).into_owned());
+        rdata = &rest[len..];
+    }
+    strings
+}
@@ -0,0 +1,97 @@
+use crate::ssh_client::DeviceIdentity;
+use std::collections::{BTreeSet, HashMap};
+
+/// Label for MACs whose OUI is not in the built-in OUI table.
+pub const UNKNOWN_VENDOR: &str = "unknown";
+
+/// `(OUI, vendor)` pairs for hardware commonly seen on our networks. The
+/// OUI is the first three octets, lowercase and colon-separated.
+///
+/// This is a curated subset of the IEEE registry, not the full list.
+const OUI_TABLE: &[(&str, &str)] = &[
+    ("00:00:0c", "Cisco"),
+    ("00:03:93", "Apple"),
+    ("00:04:4b", "NVIDIA"),
+    ("00:05:69", "VMware"),
+    ("00:05:85", "Juniper"),
+    ("00:0a:95", "Apple"),
+    ("00:0c:29", "VMware"),
+    ("00:11:32", "Synology"),
+    ("00:14:22", "Dell"),
+    ("00:15:5d", "Microsoft Hyper-V"),
+    ("00:1b:21", "Intel"),
+    ("00:25:90", "Supermicro"),
+    ("00:40:96", "Cisco"),
+    ("00:50:56", "VMware"),
+    ("00:e0:4c", "Realtek"),
+    ("00:e0:fc", "Huawei"),
+    ("08:00:27", "VirtualBox"),
+    ("24:0a:c4", "Espressif"),
+    ("24:6f:28", "Espressif"),
+    ("24:a4:3c", "Ubiquiti"),
+    ("28:cd:c1", "Raspberry Pi"),
+    ("2c:cf:67", "Raspberry Pi"),
+    ("30:ae:a4", "Espressif"),
+    ("44:d9:e7", "Ubiquiti"),
+    ("48:b0:2d", "NVIDIA"),
+    ("4c:5e:0c", "MikroTik"),
+    ("50:c7:bf", "TP-Link"),
+    ("52:54:00", "QEMU/KVM"),
+    ("54:ef:44", "Lumi United"),
+    ("6c:3b:6b", "MikroTik"),
+    ("80:2a:a8", "Ubiquiti"),
+    ("a4:cf:12", "Espressif"),
+    ("b8:27:eb", "Raspberry Pi"),
+    ("d8:3a:dd", "Raspberry Pi"),
+    ("dc:a6:32", "Raspberry Pi"),
+    ("e4:5f:01", "Raspberry Pi"),
+    ("ec:08:6b", "TP-Link"),
+    ("f0:9f:c2", "Ubiquiti"),
+];
+
+/// Vendor owning the OUI of `mac`, if known. Accepts any letter case.
+pub fn vendor(mac: &str) -> Option<&'static str> {
+    let oui = mac.get(..8)?.to_lowercase();
+    OUI_TABLE
+        .binary_search_by(|(prefix, _)| prefix.cmp(&oui.as_str()))
+        .ok()
+        .map(|i| OUI_TABLE[i].1)
+}
+
+/// Whether `target` is an OUI prefix such as `b8:27:eb:*` rather than a
+/// full MAC address.
+pub fn is_prefix(target: &str) -> bool {
+    target.ends_with('*')
+}
+
+/// Whether `mac` is the MAC `target`, or falls under it if `target` is an
+/// OUI prefix. Both must be lowercase.
+pub fn target_matches(target: &str, mac: &str) -> bool {
+    match target.strip_suffix('*') {
+        Some(prefix) => mac.starts_with(prefix),
+        None => mac == target,
+    }
+}
+
+/// Count the distinct MACs in `inventory` per vendor, most common first.
+/// Unrecognised OUIs are grouped under [`UNKNOWN_VENDOR`], always last.
+pub fn vendor_counts(inventory: &[DeviceIdentity]) -> Vec<(&'static str, usize)> {
+    let macs: BTreeSet<&str> = inventory
+        .iter()
+        .flat_map(|host| host.mac_list.iter().map(String::as_str))
+        .collect();
+
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    for mac in macs {
+        *counts.entry(vendor(mac).unwrap_or(UNKNOWN_VENDOR)).or_default() += 1;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| {
+        (a.0 == UNKNOWN_VENDOR)
+            .cmp(&(b.0 == UNKNOWN_VENDOR))
+            .then(b.1.cmp(&a.1))
+            .then(a.0.cmp(b.0))
+    });
+    counts
+}
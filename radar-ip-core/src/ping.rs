@@ -0,0 +1,111 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::BTreeSet;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+const ECHO_REQUEST: u8 = 8;
+const ECHO_REPLY: u8 = 0;
+
+/// Send one ICMP echo request to every host in `hosts` and return those
+/// that answered within `timeout` after the last request, sorted.
+///
+/// Uses a raw ICMP socket when privileged (`CAP_NET_RAW`), else the
+/// unprivileged ping socket Linux and macOS offer; fails if neither can be
+/// opened. This is a **blocking** function and is intended to be called
+/// from inside `tokio::task::spawn_blocking`.
+pub fn sweep(hosts: &[Ipv4Addr], timeout: Duration) -> io::Result<Vec<Ipv4Addr>> {
+    let (socket, raw) = open()?;
+    let id = std::process::id() as u16;
+    info!(
+        "Ping sweep of {} host(s) over a {} ICMP socket",
+        hosts.len(),
+        if raw { "raw" } else { "datagram" }
+    );
+
+    for (seq, ip) in hosts.iter().enumerate() {
+        let request = echo_request(id, seq as u16);
+        if let Err(e) = socket.send_to(&request, SocketAddr::from((*ip, 0))) {
+            debug!("Ping to {} failed: {}", ip, e);
+        }
+    }
+
+    let wanted: BTreeSet<Ipv4Addr> = hosts.iter().copied().collect();
+    let mut alive = BTreeSet::new();
+    let mut buf = [0u8; 1500];
+    let deadline = Instant::now() + timeout;
+
+    while alive.len() < wanted.len() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(left))?;
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                break
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        // The kernel already matches replies to a datagram socket's requests.
+        let SocketAddr::V4(from) = from else { continue };
+        if is_reply(&buf[..len], raw.then_some(id)) && wanted.contains(from.ip()) {
+            alive.insert(*from.ip());
+        }
+    }
+
+    Ok(alive.into_iter().collect())
+}
+
+/// Open a raw ICMP socket, or a datagram one without privileges. The flag
+/// tells which.
+fn open() -> io::Result<(UdpSocket, bool)> {
+    let ipv4 = |ty| Socket::new(Domain::IPV4, ty, Some(Protocol::ICMPV4));
+    let (socket, raw) = match ipv4(Type::RAW) {
+        Ok(socket) => (socket, true),
+        Err(e) => {
+            debug!("No raw ICMP socket ({}); trying a ping socket", e);
+            (ipv4(Type::DGRAM)?, false)
+        }
+    };
+    // Only for its safe send_to/recv_from; nothing here is UDP-specific.
+    Ok((UdpSocket::from(socket), raw))
+}
+
+/// An ICMP echo request with an 8-byte payload.
+fn echo_request(id: u16, seq: u16) -> [u8; 16] {
+    let mut packet = [0u8; 16];
+    packet[0] = ECHO_REQUEST;
+    packet[4..6].copy_from_slice(&id.to_be_bytes());
+    packet[6..8].copy_from_slice(&seq.to_be_bytes());
+    packet[8..].copy_from_slice(b"radar-ip");
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    packet
+}
+
+/// Whether `packet` is an echo reply (to `id`, when given). Raw sockets
+/// deliver the IPv4 header too, as do macOS datagram sockets.
+fn is_reply(packet: &[u8], id: Option<u16>) -> bool {
+    let icmp = match packet.first() {
+        Some(b) if b >> 4 == 4 => packet.get(usize::from(b & 0x0f) * 4..).unwrap_or_default(),
+        _ => packet,
+    };
+    icmp.first() == Some(&ECHO_REPLY)
+        && id.is_none_or(|id| icmp.get(4..6) == Some(&id.to_be_bytes()[..]))
+}
+
+/// RFC 1071 Internet checksum.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
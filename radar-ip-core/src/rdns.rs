@@ -0,0 +1,97 @@
+use crate::report::ScanMatch;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tracing::debug;
+
+/// How long the CLI and GUI wait for each reverse lookup.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a scan waits for the address of a host name in its range;
+/// longer than [`DEFAULT_TIMEOUT`], as `.local` names go through mDNS.
+pub const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Fill in [`ScanMatch::dns_name`] for every match, looking the IPs up
+/// concurrently. A lookup that fails or takes longer than `timeout` leaves
+/// the name unset.
+pub async fn resolve_matches(matches: &mut [ScanMatch], timeout: Duration) {
+    let mut lookups = JoinSet::new();
+    for (i, m) in matches.iter().enumerate() {
+        if let Ok(ip) = m.ip.parse::<IpAddr>() {
+            lookups.spawn(async move { (i, lookup(ip, timeout).await) });
+        }
+    }
+    while let Some(done) = lookups.join_next().await {
+        if let Ok((i, name)) = done {
+            matches[i].dns_name = name;
+        }
+    }
+}
+
+/// The name the system resolver (DNS PTR record, `/etc/hosts`, ...) gives
+/// `ip`, or `None` if it has none or does not answer within `timeout`.
+pub async fn lookup(ip: IpAddr, timeout: Duration) -> Option<String> {
+    let query = tokio::task::spawn_blocking(move || name_of(ip));
+    match tokio::time::timeout(timeout, query).await {
+        Ok(Ok(name)) => name,
+        Ok(Err(_)) => None,
+        Err(_) => {
+            debug!("Reverse lookup of {} timed out", ip);
+            None
+        }
+    }
+}
+
+/// The first address the system resolver (DNS, `/etc/hosts`, mDNS through
+/// nss, ...) gives `name`, or why there is none within `timeout`.
+pub async fn resolve(name: &str, timeout: Duration) -> Result<IpAddr, String> {
+    let query = tokio::task::spawn_blocking({
+        let name = name.to_string();
+        move || (name.as_str(), 0).to_socket_addrs().map(|mut addrs| addrs.next())
+    });
+    match tokio::time::timeout(timeout, query).await {
+        Ok(Ok(Ok(Some(addr)))) => Ok(addr.ip()),
+        Ok(Ok(Ok(None))) => Err("no address".into()),
+        Ok(Ok(Err(e))) => Err(e.to_string()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("no answer within {}", humantime::format_duration(timeout))),
+    }
+}
+
+/// Blocking `getnameinfo` with `NI_NAMEREQD`, so a missing name is `None`
+/// rather than the address itself.
+#[cfg(unix)]
+fn name_of(ip: IpAddr) -> Option<String> {
+    use socket2::SockAddr;
+    use std::ffi::CStr;
+    use std::net::SocketAddr;
+
+    let addr = SockAddr::from(SocketAddr::new(ip, 0));
+    let mut host = [0 as libc::c_char; libc::NI_MAXHOST as usize];
+    // SAFETY: `addr` is a valid socket address of `addr.len()` bytes and
+    // `host` is writable for its whole length; no service is asked for.
+    let rc = unsafe {
+        libc::getnameinfo(
+            addr.as_ptr() as *const libc::sockaddr,
+            addr.len(),
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if rc != 0 {
+        debug!("{} has no reverse DNS name (getnameinfo error {})", ip, rc);
+        return None;
+    }
+    // SAFETY: on success `getnameinfo` wrote a NUL-terminated string.
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    Some(name.to_string_lossy().trim_end_matches('.').to_string())
+}
+
+/// Reverse lookups need the Unix resolver; elsewhere nothing is found.
+#[cfg(not(unix))]
+fn name_of(_ip: IpAddr) -> Option<String> {
+    None
+}
@@ -0,0 +1,249 @@
+use crate::errors::RadarError;
+use crate::ssh_client::{DeviceDetails, DeviceIdentity};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A host that owns one of the target MACs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScanMatch {
+    /// IP address of the host.
+    pub ip: String,
+    /// The matching MAC (lowercase, colon-separated).
+    pub mac: String,
+    /// Interface the MAC was found on.
+    pub interface: Option<String>,
+    /// The host's own name, when it was read over SSH.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// How long logging in and reading the interfaces took (serialized as
+    /// `latency_secs`); `None` for broadcast backends.
+    #[serde(
+        rename = "latency_secs",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "opt_secs_f64"
+    )]
+    pub latency: Option<Duration>,
+    /// Reverse DNS name of `ip`, with [`Scanner::with_reverse_dns`].
+    ///
+    /// [`Scanner::with_reverse_dns`]: crate::scanner::Scanner::with_reverse_dns
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_name: Option<String>,
+    /// What the host reported with [`SshConfig::details`].
+    ///
+    /// [`SshConfig::details`]: crate::ssh_client::SshConfig::details
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<DeviceDetails>,
+}
+
+/// A host an mDNS or SSDP discovery pass found before the probes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Discovered {
+    pub ip: String,
+    /// `mdns` or `ssdp`.
+    pub via: &'static str,
+    /// The mDNS service instance, or the SSDP `uuid:…` (else its USN).
+    pub id: String,
+    /// The mDNS SRV target, or the SSDP `SERVER` header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The SSDP device description URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// The MAC it advertised (mDNS TXT record, time-based SSDP UUID).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+}
+
+/// Counters describing how a scan went.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanStats {
+    /// Hosts in the requested range(s), after sampling.
+    pub hosts_total: usize,
+    /// Hosts whose probe finished (successfully or not).
+    pub hosts_probed: usize,
+    /// Probes that failed to connect, authenticate or run the command.
+    pub hosts_failed: usize,
+    /// Wall-clock duration of the scan (serialized as `elapsed_secs`).
+    #[serde(rename = "elapsed_secs", serialize_with = "as_secs_f64")]
+    pub elapsed: Duration,
+    /// Whether only a random sample of the range was probed.
+    pub sampled: bool,
+    /// Whether the scan was cancelled before every host was probed.
+    pub cancelled: bool,
+}
+
+/// How probing one host ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostStatus {
+    /// The host owns one of the target MACs.
+    Match,
+    /// The host answered but owns none of the target MACs.
+    Ok,
+    /// TCP connect failed, e.g. refused or no route.
+    Unreachable,
+    /// The host did not answer in time.
+    Timeout,
+    /// The presented host key was rejected.
+    HostKey,
+    /// The key or password was refused.
+    AuthFailed,
+    /// Logged in, but reading the interfaces failed.
+    CommandFailed,
+}
+
+impl HostStatus {
+    /// Classify the error a probe failed with.
+    pub fn of(err: &RadarError) -> Self {
+        match err {
+            _ if err.to_string().to_lowercase().contains("timed out") => HostStatus::Timeout,
+            RadarError::SshConnection(_, msg) if msg == "authentication failed" => {
+                HostStatus::AuthFailed
+            }
+            RadarError::PrivateKey(_) | RadarError::Password(_) | RadarError::Agent(_) => {
+                HostStatus::AuthFailed
+            }
+            RadarError::HostKeyMismatch(..) => HostStatus::HostKey,
+            RadarError::CommandExecution(..) => HostStatus::CommandFailed,
+            _ => HostStatus::Unreachable,
+        }
+    }
+
+    /// The name used in reports, e.g. `auth_failed`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HostStatus::Match => "match",
+            HostStatus::Ok => "ok",
+            HostStatus::Unreachable => "unreachable",
+            HostStatus::Timeout => "timeout",
+            HostStatus::HostKey => "host_key",
+            HostStatus::AuthFailed => "auth_failed",
+            HostStatus::CommandFailed => "command_failed",
+        }
+    }
+}
+
+/// What probing one host yielded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HostResult {
+    /// IP address of the host.
+    pub ip: String,
+    /// How the probe ended.
+    pub status: HostStatus,
+    /// Every MAC the host reported, if it answered.
+    pub macs: Vec<String>,
+    /// Why the probe failed.
+    pub error: Option<String>,
+    /// How long probing it took; `None` for hosts answered for by an ARP
+    /// table, broadcast or discovery pass instead (serialized as
+    /// `elapsed_secs`).
+    #[serde(
+        rename = "elapsed_secs",
+        serialize_with = "opt_secs_f64",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub elapsed: Option<Duration>,
+}
+
+/// Why a scan produced no match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotFoundReason {
+    /// The range contained no hosts to probe.
+    NoHosts,
+    /// Every probe failed; `first_error` is the first failure observed.
+    AllHostsFailed { first_error: String },
+    /// Some hosts answered, none owned the MAC.
+    NoMatch { first_error: Option<String> },
+    /// The scan was cancelled before any match.
+    Cancelled,
+}
+
+/// Everything a [`Scanner::run`](crate::scanner::Scanner::run) learned.
+///
+/// Serializes to JSON without any credentials: only addresses, MACs,
+/// interface names, counters and error messages.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanReport {
+    /// Matching hosts, sorted by address.
+    pub matches: Vec<ScanMatch>,
+    /// Scan counters.
+    pub stats: ScanStats,
+    /// Every reachable host's identity, in inventory mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<Vec<DeviceIdentity>>,
+    /// One entry per probed host, sorted by address.
+    pub hosts: Vec<HostResult>,
+    /// What the mDNS and SSDP passes found in the range, by address.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub discovered: Vec<Discovered>,
+    /// Set when `matches` is empty.
+    pub reason: Option<NotFoundReason>,
+}
+
+impl ScanReport {
+    /// IPs of all matching hosts.
+    pub fn ips(&self) -> Vec<String> {
+        self.matches.iter().map(|m| m.ip.clone()).collect()
+    }
+
+    /// Each matched MAC mapped to the lowest IP that owns it.
+    pub fn found(&self) -> BTreeMap<String, String> {
+        let mut found = BTreeMap::new();
+        for m in &self.matches {
+            found.entry(m.mac.clone()).or_insert_with(|| m.ip.clone());
+        }
+        found
+    }
+
+    /// The first error observed during the scan, if any.
+    pub fn first_error(&self) -> Option<&str> {
+        match &self.reason {
+            Some(NotFoundReason::AllHostsFailed { first_error }) => Some(first_error),
+            Some(NotFoundReason::NoMatch { first_error }) => first_error.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Build the [`RadarError::MacNotFound`] for this report, carrying the
+    /// first connection/auth error (if any) instead of a generic message;
+    /// [`RadarError::NoHostReachable`] if every probe failed, or
+    /// [`RadarError::Cancelled`] if the scan was cancelled.
+    pub fn not_found(&self, target_mac: &str) -> RadarError {
+        match &self.reason {
+            Some(NotFoundReason::Cancelled) => return RadarError::Cancelled,
+            Some(NotFoundReason::AllHostsFailed { first_error }) => {
+                return RadarError::NoHostReachable(target_mac.to_string(), first_error.clone())
+            }
+            _ => {}
+        }
+        match self.first_error() {
+            Some(err_msg) => RadarError::MacNotFound(format!(
+                "{}\n\nFirst error: {}",
+                target_mac, err_msg
+            )),
+            None => RadarError::MacNotFound(target_mac.to_string()),
+        }
+    }
+
+    /// Matching IPs, or [`ScanReport::not_found`] if there are none.
+    pub fn into_ips(self, target_mac: &str) -> Result<Vec<String>, RadarError> {
+        if self.matches.is_empty() {
+            Err(self.not_found(target_mac))
+        } else {
+            Ok(self.ips())
+        }
+    }
+}
+
+fn as_secs_f64<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+pub(crate) fn opt_secs_f64<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match d {
+        Some(d) => as_secs_f64(d, s),
+        None => s.serialize_none(),
+    }
+}
@@ -0,0 +1,2101 @@
+#[cfg(feature = "arp")]
+use crate::arp_scan::ArpConfig;
+use crate::arp_cache;
+use crate::beacon::BeaconConfig;
+use crate::errors::RadarError;
+use crate::mdns::MdnsConfig;
+use crate::ssdp::SsdpConfig;
+use crate::oui;
+use crate::ping;
+use crate::rdns;
+#[cfg(feature = "snmp")]
+use crate::snmp_client::SnmpFallback;
+use crate::report::{
+    Discovered, HostResult, HostStatus, NotFoundReason, ScanMatch, ScanReport, ScanStats,
+};
+use crate::ssh_client::{
+    DeviceIdentity, Neighbor, ProbeAbort, SessionPool, SshConfig, DEFAULT_LEASE_FILES,
+};
+use futures_core::Stream;
+use ipnet::{IpNet, Ipv4Subnets, Ipv6Subnets};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Notify, Semaphore};
+use tokio::task::{self, JoinHandle, JoinSet};
+use tracing::field::Empty;
+use tracing::{debug, info, info_span, warn, Instrument};
+
+/// Maximum number of concurrent SSH connections, unless
+/// [`Scanner::with_concurrency`] says otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 50;
+
+/// An adaptive scan halves its concurrency once this many of the last
+/// [`CONGESTION_WINDOW`] SSH attempts ended in a congestion signal.
+const CONGESTION_BURST: usize = 3;
+
+/// How many recent SSH attempts [`CONGESTION_BURST`] is counted over.
+const CONGESTION_WINDOW: usize = 10;
+
+/// Outcome of probing a single host: its identity, or why the probe failed.
+/// On success, also how long logging in and reading the interfaces took.
+type ProbeResult = Result<(DeviceIdentity, Duration), ProbeFailure>;
+
+/// A probe that failed, with a `"<ip>: <error>"` message.
+struct ProbeFailure {
+    ip: String,
+    status: HostStatus,
+    message: String,
+    /// From its first connection attempt, retries included.
+    elapsed: Duration,
+}
+
+/// A MAC → IP table read over one SSH connection, see
+/// [`Scanner::with_gateway`] and [`Scanner::with_dhcp_server`].
+#[derive(Clone, Copy)]
+enum Table<'a> {
+    /// The neighbor table of a gateway.
+    Neighbors(&'a str),
+    /// The leases in these files of a DHCP server.
+    Leases(&'a str, &'a [String]),
+}
+
+/// The concurrency limit shared by the probes of one scan.
+///
+/// With adaptive concurrency the limit starts at the configured maximum,
+/// is halved on a burst of connection resets or failed handshakes (an
+/// overwhelmed switch, or fail2ban starting to drop us) and grows by one
+/// after every `limit` clean attempts, never past the maximum.
+struct Throttle {
+    semaphore: Semaphore,
+    max: usize,
+    adaptive: bool,
+    state: Mutex<ThrottleState>,
+}
+
+struct ThrottleState {
+    limit: usize,
+    /// Slots to retire as probes finish, after the limit was lowered.
+    debt: usize,
+    /// Whether each recent attempt was a congestion signal, oldest first.
+    recent: VecDeque<bool>,
+    /// Clean attempts since the limit last changed.
+    healthy: usize,
+}
+
+/// A probe's place under the [`Throttle`], given back when dropped.
+struct Slot {
+    throttle: Arc<Throttle>,
+}
+
+impl Throttle {
+    fn new(max: usize, adaptive: bool) -> Arc<Self> {
+        Arc::new(Self {
+            semaphore: Semaphore::new(max),
+            max,
+            adaptive,
+            state: Mutex::new(ThrottleState {
+                limit: max,
+                debt: 0,
+                recent: VecDeque::with_capacity(CONGESTION_WINDOW),
+                healthy: 0,
+            }),
+        })
+    }
+
+    /// Wait until fewer than the current limit of probes hold a slot.
+    async fn acquire(self: &Arc<Self>) -> Result<Slot, tokio::sync::AcquireError> {
+        // The permit is handed back by `Slot::drop`, which may retire it
+        // instead.
+        self.semaphore.acquire().await?.forget();
+        Ok(Slot {
+            throttle: self.clone(),
+        })
+    }
+
+    /// Adjust the limit after an SSH attempt that did (`congested`) or did
+    /// not end in a congestion signal.
+    fn observe(&self, congested: bool) {
+        if !self.adaptive {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.recent.len() == CONGESTION_WINDOW {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(congested);
+        state.healthy = if congested { 0 } else { state.healthy + 1 };
+
+        let burst = state.recent.iter().filter(|&&c| c).count() >= CONGESTION_BURST;
+        if burst && state.limit > 1 {
+            let limit = state.limit / 2;
+            state.debt += state.limit - limit;
+            state.limit = limit;
+            state.recent.clear();
+            warn!("Connection resets or failed handshakes; lowering concurrency to {}", limit);
+        } else if !congested && state.healthy >= state.limit && state.limit < self.max {
+            state.limit += 1;
+            state.healthy = 0;
+            if state.debt > 0 {
+                state.debt -= 1;
+            } else {
+                self.semaphore.add_permits(1);
+            }
+            debug!("Probes healthy; raising concurrency to {}", state.limit);
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut state = self.throttle.state.lock().unwrap();
+        if state.debt > 0 {
+            state.debt -= 1;
+        } else {
+            self.throttle.semaphore.add_permits(1);
+        }
+    }
+}
+
+/// Probe only a random fraction of the hosts in a range.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// Percentage of hosts to keep, in `(0, 100]`.
+    pub percent: f64,
+    /// RNG seed for a reproducible selection.
+    pub seed: Option<u64>,
+}
+
+/// How hosts are filtered before they are probed over SSH.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Prescan {
+    /// Probe every host.
+    #[default]
+    None,
+    /// Only probe hosts whose SSH port accepts a TCP connection within the
+    /// timeout.
+    Tcp(Duration),
+    /// Ping every IPv4 host first and drop those that stay silent for the
+    /// timeout, then check the rest like [`Prescan::Tcp`]. If no ICMP socket
+    /// can be opened, only the TCP check runs.
+    Ping(Duration),
+}
+
+/// How often a probe that fails transiently (timeout, connection reset,
+/// no SSH banner) is attempted. Auth, host-key and refused connections are
+/// never retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retry {
+    /// Total attempts per host, at least 1.
+    pub attempts: u32,
+    /// Wait before the second attempt; doubled before each further one.
+    pub backoff: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Live counters of a running scan, shared with e.g. a UI. Use a fresh
+/// instance per scan.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    done: AtomicUsize,
+    failed: AtomicUsize,
+    found: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl ScanProgress {
+    /// Hosts whose probe has finished.
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    /// Finished probes that failed.
+    pub fn failed(&self) -> usize {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Finished probes that matched a target.
+    pub fn found(&self) -> usize {
+        self.found.load(Ordering::Relaxed)
+    }
+
+    /// Hosts that will be probed; 0 until the range has been expanded.
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+}
+
+/// Stops a running scan early. Clones share the same state, so one can go
+/// to the scanner and another to e.g. a Ctrl+C handler.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    /// Ask every scan holding this token to stop probing.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    /// Whether [`CancelToken::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolve once the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking, so a cancel in between is not missed.
+            let notified = self.0.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// What a [`Scanner::run`] should collect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Stop once every target MAC has been found on a host. OUI prefix
+    /// targets are never done, so they scan the whole range.
+    #[default]
+    FirstMatch,
+    /// Probe every host and report all owners of the target MACs.
+    AllMatches,
+    /// Probe every host and keep every reachable host's identity.
+    Inventory,
+}
+
+/// Input to [`Scanner::run`].
+#[derive(Debug, Clone)]
+pub struct ScanRequest {
+    /// Range(s) to scan: one or more comma-separated IPv4/IPv6 CIDRs,
+    /// addresses or host names (resolved to their first address before the
+    /// scan); may be empty if `hosts` lists every host.
+    pub range: String,
+    /// Individual hosts to probe on top of `range`.
+    pub hosts: Vec<IpAddr>,
+    /// What to collect.
+    pub mode: ScanMode,
+}
+
+/// How hosts are probed.
+// One per scanner, so the size difference does not matter.
+#[allow(clippy::large_enum_variant)]
+enum Backend {
+    /// SSH into every host and read its interfaces.
+    Ssh(SshConfig),
+    /// Broadcast ARP requests on the local segment.
+    #[cfg(feature = "arp")]
+    Arp(ArpConfig),
+    /// Listen for the UDP identification beacons devices broadcast.
+    Beacon(BeaconConfig),
+}
+
+/// Scans an IP range over SSH (or raw ARP) and looks for one or more MAC
+/// addresses.
+pub struct Scanner {
+    backend: Backend,
+    /// Full MACs or OUI prefixes (`b8:27:eb:*`), lowercase, without
+    /// duplicates.
+    target_macs: Vec<String>,
+    sample: Option<Sample>,
+    jitter: Duration,
+    concurrency: usize,
+    adaptive: bool,
+    prescan: Prescan,
+    retry: Retry,
+    arp_helper: Option<String>,
+    /// Try this machine's own ARP table before anything else.
+    local_arp: bool,
+    gateway: Option<String>,
+    /// DHCP server and the lease files to read on it.
+    dhcp_server: Option<(String, Vec<String>)>,
+    do_not_scan: Vec<IpNet>,
+    parse_budget: Option<Duration>,
+    quiet: bool,
+    progress: Option<Arc<ScanProgress>>,
+    cancel: Option<CancelToken>,
+    /// Receives each host's result as it completes, for
+    /// [`Scanner::scan_stream`].
+    stream: Option<mpsc::UnboundedSender<HostResult>>,
+    /// Look up the DNS name of every match, giving each lookup this long.
+    reverse_dns: Option<Duration>,
+    /// Probed on their own before the rest, in first-match scans.
+    priority: Vec<IpAddr>,
+    /// Browse mDNS first and probe only the advertisers.
+    mdns: Option<MdnsConfig>,
+    /// Search SSDP first and probe only the responders.
+    ssdp: Option<SsdpConfig>,
+    /// Ask hosts that refuse SSH for their interfaces over SNMP.
+    #[cfg(feature = "snmp")]
+    snmp: Option<SnmpFallback>,
+}
+
+/// The results of a [`Scanner::scan_stream`], one per host in completion
+/// order. Dropping it cancels the scan.
+pub struct ScanStream {
+    results: mpsc::UnboundedReceiver<HostResult>,
+    scan: JoinHandle<Result<ScanReport, RadarError>>,
+}
+
+impl ScanStream {
+    /// The next host's result, or `None` once the scan has ended.
+    pub async fn next(&mut self) -> Option<HostResult> {
+        self.results.recv().await
+    }
+
+    /// Wait for the scan to end and return its full report, as
+    /// [`Scanner::run`] would have. Results not yet taken from the stream
+    /// are in [`ScanReport::hosts`] too.
+    pub async fn report(mut self) -> Result<ScanReport, RadarError> {
+        (&mut self.scan).await.expect("scan task panicked")
+    }
+}
+
+impl Stream for ScanStream {
+    type Item = HostResult;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<HostResult>> {
+        self.get_mut().results.poll_recv(cx)
+    }
+}
+
+impl Drop for ScanStream {
+    fn drop(&mut self) {
+        self.scan.abort();
+    }
+}
+
+impl Scanner {
+    /// Create a new scanner that probes hosts over SSH for `target_macs`.
+    /// A target ending in `*` (e.g. `b8:27:eb:*`) matches every MAC with
+    /// that OUI prefix.
+    pub fn new(config: SshConfig, target_macs: Vec<String>) -> Self {
+        Self::with_backend(Backend::Ssh(config), target_macs)
+    }
+
+    /// Create a scanner that finds the MAC from raw ARP replies on the
+    /// local segment instead of SSHing into every host. IPv4 only; hosts
+    /// without SSH are found too.
+    #[cfg(feature = "arp")]
+    pub fn new_arp(config: ArpConfig, target_macs: Vec<String>) -> Self {
+        Self::with_backend(Backend::Arp(config), target_macs)
+    }
+
+    /// Create a scanner that resolves the MAC from the UDP beacons devices
+    /// broadcast, without SSH. IPv4 only.
+    pub fn new_beacon(config: BeaconConfig, target_macs: Vec<String>) -> Self {
+        Self::with_backend(Backend::Beacon(config), target_macs)
+    }
+
+    fn with_backend(backend: Backend, target_macs: Vec<String>) -> Self {
+        let mut targets: Vec<String> = Vec::new();
+        for mac in target_macs {
+            let mac = mac.to_lowercase();
+            if !targets.contains(&mac) {
+                targets.push(mac);
+            }
+        }
+        Self {
+            backend,
+            target_macs: targets,
+            sample: None,
+            jitter: Duration::ZERO,
+            concurrency: DEFAULT_CONCURRENCY,
+            adaptive: true,
+            prescan: Prescan::None,
+            retry: Retry::default(),
+            arp_helper: None,
+            local_arp: false,
+            gateway: None,
+            dhcp_server: None,
+            do_not_scan: Vec::new(),
+            parse_budget: None,
+            quiet: false,
+            progress: None,
+            cancel: None,
+            stream: None,
+            reverse_dns: None,
+            priority: Vec::new(),
+            mdns: None,
+            ssdp: None,
+            #[cfg(feature = "snmp")]
+            snmp: None,
+        }
+    }
+
+    /// Probe only a random sample of each range. Such scans are
+    /// non-exhaustive: a miss does not prove the MAC is absent.
+    pub fn with_sample(mut self, sample: Sample) -> Self {
+        self.sample = Some(sample);
+        self
+    }
+
+    /// Wait a random `0..max` before each probe connects, to spread out
+    /// connection bursts. `Duration::ZERO` disables jitter.
+    pub fn with_jitter(mut self, max: Duration) -> Self {
+        self.jitter = max;
+        self
+    }
+
+    /// Probe at most `limit` hosts at a time (at least one).
+    pub fn with_concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    /// Lower the concurrency while SSH attempts keep being reset or failing
+    /// their handshake, and raise it back towards
+    /// [`with_concurrency`](Self::with_concurrency)'s limit once they
+    /// succeed again (the default). `false` keeps the limit fixed.
+    pub fn with_adaptive_concurrency(mut self, adaptive: bool) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// Skip hosts that fail `prescan` before the SSH handshake, so dead
+    /// addresses cost the prescan timeout instead of the full SSH timeout.
+    pub fn with_prescan(mut self, prescan: Prescan) -> Self {
+        self.prescan = prescan;
+        self
+    }
+
+    /// Retry probes that fail with a transient error, see [`Retry`].
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Before sweeping, read the neighbor table of `helper` (typically the
+    /// gateway) and return immediately if it already maps every target MAC
+    /// to an IP. Falls back to the full scan otherwise. First-match, SSH
+    /// backend only.
+    pub fn with_arp_shortcut(mut self, helper: String) -> Self {
+        self.arp_helper = Some(helper);
+        self
+    }
+
+    /// Like [`with_arp_shortcut`](Self::with_arp_shortcut), with this
+    /// machine's own ARP/neighbor table (see [`arp_cache::read`]), without
+    /// connecting anywhere: tried first, so a device looked up moments ago
+    /// on the same segment is found at once. First-match, any backend.
+    pub fn with_local_arp_shortcut(mut self) -> Self {
+        self.local_arp = true;
+        self
+    }
+
+    /// Instead of sweeping, read the neighbor table of `gateway` alone and
+    /// resolve every target from it: one connection instead of one per
+    /// host, and devices that refuse our key are found too. Only entries
+    /// inside the range count, if one is given. SSH backend only.
+    pub fn with_gateway(mut self, gateway: String) -> Self {
+        self.gateway = Some(gateway);
+        self
+    }
+
+    /// Like [`with_gateway`](Self::with_gateway), from the DHCP leases in
+    /// `lease_files` on `server` (see [`SshConfig::fetch_leases`]; empty
+    /// for [`DEFAULT_LEASE_FILES`]): devices that are asleep are found too,
+    /// as long as their lease has not been released. SSH backend only.
+    pub fn with_dhcp_server(mut self, server: String, lease_files: Vec<String>) -> Self {
+        let lease_files = if lease_files.is_empty() {
+            DEFAULT_LEASE_FILES.map(String::from).to_vec()
+        } else {
+            lease_files
+        };
+        self.dhcp_server = Some((server, lease_files));
+        self
+    }
+
+    /// Never probe a host inside any of `nets`, even if it is in range.
+    pub fn with_do_not_scan(mut self, nets: Vec<IpNet>) -> Self {
+        self.do_not_scan = nets;
+        self
+    }
+
+    /// Abort with [`RadarError::RangeParseTimeout`] if expanding a range
+    /// into hosts takes longer than `budget`, instead of stalling startup.
+    pub fn with_range_parse_budget(mut self, budget: Duration) -> Self {
+        self.parse_budget = Some(budget);
+        self
+    }
+
+    /// Keep progress lines off stdout (they are still logged), e.g. when
+    /// stdout carries machine-readable output.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Count finished probes in `progress` while the scan runs.
+    pub fn with_progress(mut self, progress: Arc<ScanProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop probing once `cancel` is cancelled: in-flight probes are aborted
+    /// and [`Scanner::run`] returns what was found so far, with
+    /// [`ScanStats::cancelled`] set.
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Before probing over SSH, browse mDNS with `config` and probe only the
+    /// hosts that advertise one of its services (those of the range, or all
+    /// of them if the range is empty). An advertiser whose TXT record names
+    /// a target MAC is a match without logging in. Other backends ignore
+    /// it.
+    pub fn with_mdns(mut self, config: MdnsConfig) -> Self {
+        self.mdns = Some(config);
+        self
+    }
+
+    /// Like [`with_mdns`](Self::with_mdns), with an SSDP M-SEARCH: only the
+    /// devices that answer it are probed, and one whose time-based UUID
+    /// embeds a target MAC is a match. With both, the hosts either pass
+    /// finds are probed.
+    pub fn with_ssdp(mut self, config: SsdpConfig) -> Self {
+        self.ssdp = Some(config);
+        self
+    }
+
+    /// When a host refuses SSH (its port is closed, or the login or the
+    /// host key is rejected), read its interface MACs over SNMP with
+    /// `config` instead (see [`SnmpFallback::identify`]), so devices that
+    /// only speak SNMP can still be found. Hosts that did not answer at all
+    /// are not asked. Other backends ignore it.
+    #[cfg(feature = "snmp")]
+    pub fn with_snmp_fallback(mut self, config: SnmpFallback) -> Self {
+        self.snmp = Some(config);
+        self
+    }
+
+    /// Reuse the SSH sessions in `pool` and leave this scan's sessions there
+    /// (see [`SshConfig::pool`]), so the next scan of the same hosts skips
+    /// the connect, handshake and login. Other backends ignore it.
+    pub fn with_session_pool(mut self, pool: Arc<SessionPool>) -> Self {
+        if let Backend::Ssh(config) = &mut self.backend {
+            config.pool = Some(pool);
+        }
+        self
+    }
+
+    /// Once the scan is over, look up the reverse DNS name of every match
+    /// (see [`ScanMatch::dns_name`]), waiting at most `timeout` for each.
+    /// Names come from the system resolver, so on Unix only.
+    pub fn with_reverse_dns(mut self, timeout: Duration) -> Self {
+        self.reverse_dns = Some(timeout);
+        self
+    }
+
+    /// In first-match SSH scans, probe `hosts` (e.g. where the targets were
+    /// last found) on their own first, and sweep the rest of the range only
+    /// if they do not hold every target. Hosts outside the range are
+    /// ignored.
+    pub fn with_priority_hosts(mut self, hosts: Vec<IpAddr>) -> Self {
+        self.priority = hosts;
+        self
+    }
+
+    /// Scan every host in `cidr` (e.g. `"192.168.1.0/24"`) concurrently
+    /// until every target MAC is found or the range is exhausted.
+    ///
+    /// Returns each found MAC mapped to the IP that owns it (MACs that were
+    /// not found are absent), or [`ScanReport::not_found`] if none is.
+    pub async fn scan(&self, cidr: &str) -> Result<BTreeMap<String, String>, RadarError> {
+        let report = self
+            .run(ScanRequest {
+                range: cidr.to_string(),
+                hosts: Vec::new(),
+                mode: ScanMode::FirstMatch,
+            })
+            .await?;
+
+        if report.matches.is_empty() {
+            Err(report.not_found(&self.target_macs.join(", ")))
+        } else {
+            Ok(report.found())
+        }
+    }
+
+    /// Scan every host in `cidr` and return **all** IPs whose link table
+    /// contains one of the target MACs, sorted by address.
+    ///
+    /// Unlike [`Scanner::scan`] this never short-circuits, so duplicate
+    /// MACs (e.g. cloned images) are reported.
+    pub async fn scan_all(&self, cidr: &str) -> Result<Vec<String>, RadarError> {
+        let report = self
+            .run(ScanRequest {
+                range: cidr.to_string(),
+                hosts: Vec::new(),
+                mode: ScanMode::AllMatches,
+            })
+            .await?;
+
+        report.into_ips(&self.target_macs.join(", "))
+    }
+
+    /// Start the scan described by `request` on a new task and yield each
+    /// host's [`HostResult`] as its probe completes, instead of waiting for
+    /// the whole report. The stream ends when the scan does; an invalid
+    /// request ends it at once, with the error left for
+    /// [`ScanStream::report`].
+    ///
+    /// Broadcast backends (ARP, beacons, `--gateway`) learn every host at
+    /// once and yield all results together at the end.
+    pub fn scan_stream(mut self, request: ScanRequest) -> ScanStream {
+        let (tx, results) = mpsc::unbounded_channel();
+        self.stream = Some(tx);
+        ScanStream {
+            results,
+            scan: tokio::spawn(async move { self.run(request).await }),
+        }
+    }
+
+    /// Run a scan described by `request` and return everything learned.
+    ///
+    /// Only invalid input is an `Err`; a scan that finds nothing is an `Ok`
+    /// report with [`ScanReport::reason`] set.
+    pub async fn run(&self, request: ScanRequest) -> Result<ScanReport, RadarError> {
+        let mut report = self.collect(request).await?;
+        if let Some(timeout) = self.reverse_dns {
+            rdns::resolve_matches(&mut report.matches, timeout).await;
+        }
+        Ok(report)
+    }
+
+    /// [`Scanner::run`] without the enrichment of its matches.
+    async fn collect(&self, request: ScanRequest) -> Result<ScanReport, RadarError> {
+        let started = Instant::now();
+        let hosts = self.hosts(&request.range, &request.hosts).await?;
+
+        if request.mode == ScanMode::FirstMatch {
+            if let Some(found) = self.local_arp_shortcut(&hosts).await {
+                let results: Vec<HostResult> = found
+                    .iter()
+                    .map(|m| HostResult {
+                        ip: m.ip.clone(),
+                        status: HostStatus::Match,
+                        macs: vec![m.mac.clone()],
+                        error: None,
+                        elapsed: None,
+                    })
+                    .collect();
+                results.iter().for_each(|result| self.emit(result));
+                return Ok(ScanReport {
+                    matches: found,
+                    hosts: results,
+                    stats: ScanStats {
+                        hosts_total: hosts.len(),
+                        elapsed: started.elapsed(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+            }
+            if let Some(found) = self.arp_shortcut(&hosts).await {
+                let helper = HostResult {
+                    ip: self.arp_helper.clone().unwrap_or_default(),
+                    status: HostStatus::Match,
+                    macs: found.iter().map(|m| m.mac.clone()).collect(),
+                    error: None,
+                    elapsed: None,
+                };
+                self.emit(&helper);
+                return Ok(ScanReport {
+                    matches: found,
+                    hosts: vec![helper],
+                    stats: ScanStats {
+                        hosts_total: hosts.len(),
+                        hosts_probed: 1,
+                        elapsed: started.elapsed(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+            }
+        }
+
+        let mut stats = ScanStats {
+            hosts_total: hosts.len(),
+            sampled: self.sample.is_some(),
+            ..Default::default()
+        };
+        let mut matches: Vec<ScanMatch> = Vec::new();
+        let mut inventory = Vec::new();
+        let mut results = Vec::new();
+        let mut first_error: Option<String> = None;
+
+        if let Some(progress) = &self.progress {
+            progress.total.store(stats.hosts_total, Ordering::Relaxed);
+        }
+
+        let config = match &self.backend {
+            Backend::Ssh(config) => config,
+            #[cfg(feature = "arp")]
+            Backend::Arp(config) => {
+                return self.run_arp(config, hosts, request.mode, stats, started).await
+            }
+            Backend::Beacon(config) => {
+                return self.run_beacon(config, hosts, request.mode, stats, started).await
+            }
+        };
+        if let Some(gateway) = &self.gateway {
+            let table = Table::Neighbors(gateway);
+            return Ok(self.run_table(config, table, hosts, request.mode, stats, started).await);
+        }
+        if let Some((server, files)) = &self.dhcp_server {
+            let table = Table::Leases(server, files);
+            return Ok(self.run_table(config, table, hosts, request.mode, stats, started).await);
+        }
+
+        let mut hosts = hosts;
+        let mut discovered = Vec::new();
+        if self.mdns.is_some() || self.ssdp.is_some() {
+            discovered = self.discover(request.mode).await?;
+            discovered.retain(|d| d.ip.parse().is_ok_and(|ip| self.in_scope(&hosts, &ip)));
+            hosts = Vec::new();
+            for found in &discovered {
+                let Ok(ip) = found.ip.parse::<IpAddr>() else { continue };
+                if hosts.contains(&ip) || matches.iter().any(|m| m.ip == found.ip) {
+                    continue;
+                }
+                let mac = found.mac.clone().filter(|mac| self.is_target(mac));
+                let Some(mac) = mac.filter(|_| request.mode != ScanMode::Inventory) else {
+                    hosts.push(ip);
+                    continue;
+                };
+                info!("Found {} on {} from its {} advertisement", mac, ip, found.via);
+                matches.push(ScanMatch {
+                    ip: found.ip.clone(),
+                    mac: mac.clone(),
+                    interface: None,
+                    hostname: found.name.clone().filter(|_| found.via == "mdns"),
+                    latency: None,
+                    dns_name: None,
+                    details: None,
+                });
+                self.record(&mut results, HostResult {
+                    ip: found.ip.clone(),
+                    status: HostStatus::Match,
+                    macs: vec![mac],
+                    error: None,
+                    elapsed: None,
+                });
+                stats.hosts_probed += 1;
+            }
+            // A host named by both passes is probed (or matched) once.
+            hosts.retain(|ip| !matches.iter().any(|m| m.ip == ip.to_string()));
+            hosts.sort();
+            info!("{} discovered host(s) to probe", hosts.len());
+            if !self.quiet {
+                println!("Discovered {} host(s) ...", hosts.len() + stats.hosts_probed);
+            }
+            stats.hosts_total = hosts.len() + stats.hosts_probed;
+            if let Some(progress) = &self.progress {
+                progress.total.store(stats.hosts_total, Ordering::Relaxed);
+                progress.done.store(stats.hosts_probed, Ordering::Relaxed);
+            }
+        }
+
+        // An IPv6 address derived from a target's EUI-64 names its owner
+        // without logging in; inventories still probe every host.
+        if request.mode != ScanMode::Inventory {
+            hosts.retain(|ip| {
+                let Some(mac) = eui64_mac(ip).filter(|mac| self.is_target(mac)) else {
+                    return true;
+                };
+                info!("Found {} on {} from its EUI-64 address", mac, ip);
+                matches.push(ScanMatch {
+                    ip: ip.to_string(),
+                    mac: mac.clone(),
+                    interface: None,
+                    hostname: None,
+                    latency: None,
+                    dns_name: None,
+                    details: None,
+                });
+                self.record(&mut results, HostResult {
+                    ip: ip.to_string(),
+                    status: HostStatus::Match,
+                    macs: vec![mac],
+                    error: None,
+                    elapsed: None,
+                });
+                stats.hosts_probed += 1;
+                if let Some(progress) = &self.progress {
+                    progress.done.fetch_add(1, Ordering::Relaxed);
+                }
+                false
+            });
+        }
+
+        let done = request.mode == ScanMode::FirstMatch && self.all_found(&matches);
+        // Behind a jump host the devices are not reachable from here at all.
+        let prescan = match (&config.via, self.prescan) {
+            (Some(jump), prescan) if prescan != Prescan::None => {
+                info!("Probing via {}; skipping the prescan", jump);
+                Prescan::None
+            }
+            (_, prescan) => prescan,
+        };
+        let preprobe = match prescan {
+            Prescan::None => None,
+            Prescan::Tcp(timeout) | Prescan::Ping(timeout) => Some(timeout),
+        };
+        if let (Prescan::Ping(timeout), false) = (prescan, done) {
+            let v4: Vec<Ipv4Addr> = hosts
+                .iter()
+                .filter_map(|ip| match ip {
+                    IpAddr::V4(v4) => Some(*v4),
+                    IpAddr::V6(_) => None,
+                })
+                .collect();
+            let swept = task::spawn_blocking(move || ping::sweep(&v4, timeout))
+                .await
+                .expect("ping sweep task panicked");
+            match swept {
+                Ok(alive) => {
+                    hosts.retain(|ip| {
+                        let IpAddr::V4(v4) = ip else { return true };
+                        if alive.contains(v4) {
+                            return true;
+                        }
+                        let message = format!(
+                            "{}: no ping reply within {}",
+                            ip,
+                            humantime::format_duration(timeout)
+                        );
+                        self.record(&mut results, HostResult {
+                            ip: ip.to_string(),
+                            status: HostStatus::Timeout,
+                            macs: Vec::new(),
+                            error: Some(message.clone()),
+                            elapsed: None,
+                        });
+                        stats.hosts_probed += 1;
+                        stats.hosts_failed += 1;
+                        if let Some(progress) = &self.progress {
+                            progress.done.fetch_add(1, Ordering::Relaxed);
+                            progress.failed.fetch_add(1, Ordering::Relaxed);
+                        }
+                        first_error.get_or_insert(message);
+                        false
+                    });
+                }
+                Err(e) => warn!("Ping sweep unavailable ({}); using TCP pre-probes only", e),
+            }
+        }
+
+        // Likely owners go first, alone; the others wait for the second
+        // round, which only runs if they did not hold every target.
+        let mut rest = Vec::new();
+        if request.mode == ScanMode::FirstMatch && !done && !self.priority.is_empty() {
+            let (first, others): (Vec<IpAddr>, Vec<IpAddr>) =
+                hosts.into_iter().partition(|ip| self.priority.contains(ip));
+            if !first.is_empty() {
+                info!("Probing {} likely host(s) first", first.len());
+            }
+            (hosts, rest) = (first, others);
+        }
+        let throttle = Throttle::new(self.concurrency, self.adaptive);
+        let mut probes = if done {
+            JoinSet::new()
+        } else {
+            self.spawn_probes(config, hosts, preprobe, &throttle)
+        };
+
+        // Collect results as they complete; in first-match mode, return once
+        // every target is found instead of waiting out slower dead hosts.
+        loop {
+            let joined = tokio::select! {
+                joined = probes.join_next() => match joined {
+                    Some(joined) => joined,
+                    None if !rest.is_empty() => {
+                        let rest = std::mem::take(&mut rest);
+                        probes = self.spawn_probes(config, rest, preprobe, &throttle);
+                        continue;
+                    }
+                    None => break,
+                },
+                _ = cancelled(self.cancel.as_ref()) => {
+                    warn!("Scan cancelled after {} of {} host(s)", stats.hosts_probed, stats.hosts_total);
+                    probes.abort_all();
+                    stats.cancelled = true;
+                    break;
+                }
+            };
+            let Ok(result) = joined else { continue };
+            stats.hosts_probed += 1;
+            if let Some(progress) = &self.progress {
+                progress.done.fetch_add(1, Ordering::Relaxed);
+            }
+
+            match result {
+                Ok((identity, latency)) => {
+                    let before = matches.len();
+                    for mac in identity.mac_list.iter().filter(|m| self.is_target(m)) {
+                        info!("Found {} on {}", mac, identity.ip);
+                        let interface = identity
+                            .interfaces
+                            .iter()
+                            .find(|iface| &iface.mac == mac)
+                            .map(|iface| iface.name.clone());
+                        matches.push(ScanMatch {
+                            ip: identity.ip.clone(),
+                            mac: mac.clone(),
+                            interface,
+                            hostname: identity.hostname.clone(),
+                            latency: Some(latency),
+                            dns_name: None,
+                            details: identity.details.clone(),
+                        });
+                    }
+                    self.record(&mut results, HostResult {
+                        ip: identity.ip.clone(),
+                        status: if matches.len() > before {
+                            HostStatus::Match
+                        } else {
+                            HostStatus::Ok
+                        },
+                        macs: identity.mac_list.clone(),
+                        error: None,
+                        elapsed: Some(latency),
+                    });
+                    if request.mode == ScanMode::Inventory {
+                        inventory.push(identity);
+                    }
+                }
+                Err(failure) => {
+                    stats.hosts_failed += 1;
+                    if let Some(progress) = &self.progress {
+                        progress.failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    self.record(&mut results, HostResult {
+                        ip: failure.ip,
+                        status: failure.status,
+                        macs: Vec::new(),
+                        error: Some(failure.message.clone()),
+                        elapsed: Some(failure.elapsed),
+                    });
+                    first_error.get_or_insert(failure.message);
+                }
+            }
+
+            if request.mode == ScanMode::FirstMatch && self.all_found(&matches) {
+                probes.abort_all();
+                break;
+            }
+        }
+
+        matches.sort_by_key(|m| m.ip.parse::<IpAddr>().ok());
+        inventory.sort_by_key(|d| d.ip.parse::<IpAddr>().ok());
+        results.sort_by_key(|h| h.ip.parse::<IpAddr>().ok());
+        stats.elapsed = started.elapsed();
+
+        let reason = if !matches.is_empty() {
+            None
+        } else if stats.cancelled {
+            Some(NotFoundReason::Cancelled)
+        } else if stats.hosts_total == 0 {
+            Some(NotFoundReason::NoHosts)
+        } else if stats.hosts_failed == stats.hosts_probed && first_error.is_some() {
+            Some(NotFoundReason::AllHostsFailed {
+                first_error: first_error.unwrap_or_default(),
+            })
+        } else {
+            Some(NotFoundReason::NoMatch { first_error })
+        };
+
+        Ok(ScanReport {
+            matches,
+            stats,
+            inventory: (request.mode == ScanMode::Inventory).then_some(inventory),
+            hosts: results,
+            discovered,
+            reason,
+        })
+    }
+
+    /// Resolve `hosts` with a single raw ARP sweep. Every IPv4 host counts
+    /// as probed; hosts that stay silent are not failures, but are reported
+    /// as timed out.
+    #[cfg(feature = "arp")]
+    async fn run_arp(
+        &self,
+        config: &ArpConfig,
+        hosts: Vec<IpAddr>,
+        mode: ScanMode,
+        stats: ScanStats,
+        started: Instant,
+    ) -> Result<ScanReport, RadarError> {
+        let v4 = ipv4_only(&hosts, "ARP");
+        let neighbors = task::spawn_blocking({
+            let config = config.clone();
+            let stop_at = self.stop_at(mode);
+            let v4 = v4.clone();
+            move || config.sweep(&v4, &stop_at)
+        })
+        .await
+        .expect("ARP sweep task panicked")?;
+
+        Ok(self.neighbor_report(neighbors, &v4, mode, stats, started, "no ARP reply"))
+    }
+
+    /// Resolve `hosts` from the beacons they send; counted like an ARP
+    /// sweep.
+    async fn run_beacon(
+        &self,
+        config: &BeaconConfig,
+        hosts: Vec<IpAddr>,
+        mode: ScanMode,
+        stats: ScanStats,
+        started: Instant,
+    ) -> Result<ScanReport, RadarError> {
+        let v4 = ipv4_only(&hosts, "Beacon");
+        let neighbors = task::spawn_blocking({
+            let config = config.clone();
+            let stop_at = self.stop_at(mode);
+            let v4 = v4.clone();
+            move || config.listen(&v4, &stop_at)
+        })
+        .await
+        .expect("beacon listener task panicked")?;
+
+        Ok(self.neighbor_report(neighbors, &v4, mode, stats, started, "no beacon"))
+    }
+
+    /// Resolve the targets from the IPv4 entries of `table` that fall in
+    /// `hosts` (all of them if `hosts` is empty). Each entry counts as a
+    /// probed host; a table that cannot be read fails the whole scan.
+    async fn run_table(
+        &self,
+        config: &SshConfig,
+        table: Table<'_>,
+        hosts: Vec<IpAddr>,
+        mode: ScanMode,
+        mut stats: ScanStats,
+        started: Instant,
+    ) -> ScanReport {
+        let (gateway, what) = match table {
+            Table::Neighbors(gateway) => (gateway, "the neighbor table"),
+            Table::Leases(server, _) => (server, "the DHCP leases"),
+        };
+        let neighbors = task::spawn_blocking({
+            let config = config.clone();
+            let gateway = gateway.to_string();
+            let leases = match table {
+                Table::Neighbors(_) => None,
+                Table::Leases(_, files) => Some(files.to_vec()),
+            };
+            move || match leases {
+                None => config.fetch_neighbors(&gateway),
+                Some(files) => config.fetch_leases(&gateway, &files),
+            }
+        })
+        .await
+        .expect("gateway task panicked");
+
+        let mut neighbors = match neighbors {
+            Ok(neighbors) => neighbors,
+            Err(e) => {
+                let message = format!("{}: {}", gateway, e);
+                warn!("Cannot read {} of {}", what, message);
+                stats.hosts_probed = 1;
+                stats.hosts_failed = 1;
+                stats.elapsed = started.elapsed();
+                let failed = HostResult {
+                    ip: gateway.to_string(),
+                    status: HostStatus::of(&e),
+                    macs: Vec::new(),
+                    error: Some(message.clone()),
+                    elapsed: Some(started.elapsed()),
+                };
+                self.emit(&failed);
+                return ScanReport {
+                    stats,
+                    hosts: vec![failed],
+                    reason: Some(NotFoundReason::AllHostsFailed { first_error: message }),
+                    ..Default::default()
+                };
+            }
+        };
+        match table {
+            Table::Neighbors(_) => info!("{} knows {} neighbor(s)", gateway, neighbors.len()),
+            Table::Leases(..) => info!("{} holds {} lease(s)", gateway, neighbors.len()),
+        }
+
+        neighbors.retain(|n| n.ip.parse::<Ipv4Addr>().is_ok());
+        let v4 = if hosts.is_empty() {
+            let v4: Vec<Ipv4Addr> = neighbors.iter().filter_map(|n| n.ip.parse().ok()).collect();
+            stats.hosts_total = v4.len();
+            if let Some(progress) = &self.progress {
+                progress.total.store(v4.len(), Ordering::Relaxed);
+            }
+            v4
+        } else {
+            let v4 = ipv4_only(&hosts, "Gateway");
+            let wanted: HashSet<&Ipv4Addr> = v4.iter().collect();
+            neighbors.retain(|n| n.ip.parse::<Ipv4Addr>().is_ok_and(|ip| wanted.contains(&ip)));
+            v4
+        };
+
+        let silent = match table {
+            Table::Neighbors(_) => "not in the gateway's table",
+            Table::Leases(..) => "no DHCP lease",
+        };
+        self.neighbor_report(neighbors, &v4, mode, stats, started, silent)
+    }
+
+    /// Run the mDNS and SSDP passes that are configured, side by side.
+    async fn discover(&self, mode: ScanMode) -> Result<Vec<Discovered>, RadarError> {
+        let stop_at = self.stop_at(mode);
+        let mdns = self.mdns.clone().map(|config| {
+            let stop_at = stop_at.clone();
+            task::spawn_blocking(move || config.browse(&stop_at))
+        });
+        let ssdp = self.ssdp.clone().map(|config| {
+            let stop_at = stop_at.clone();
+            task::spawn_blocking(move || config.search(&stop_at))
+        });
+
+        let mut discovered = Vec::new();
+        if let Some(mdns) = mdns {
+            let advertisers = mdns.await.expect("mDNS browse task panicked")?;
+            discovered.extend(advertisers.into_iter().map(|a| Discovered {
+                ip: a.ip.to_string(),
+                via: "mdns",
+                id: a.instance,
+                name: a.hostname,
+                location: None,
+                mac: a.mac,
+            }));
+        }
+        if let Some(ssdp) = ssdp {
+            let responders = ssdp.await.expect("SSDP search task panicked")?;
+            discovered.extend(responders.into_iter().map(|r| Discovered {
+                ip: r.ip.to_string(),
+                via: "ssdp",
+                id: r.uuid.unwrap_or(r.usn),
+                name: r.server,
+                location: r.location,
+                mac: r.mac,
+            }));
+        }
+        discovered.sort_by_key(|d| d.ip.parse::<IpAddr>().ok());
+        Ok(discovered)
+    }
+
+    /// MACs after which a broadcast backend may stop listening early: every
+    /// target in first-match mode, unless one is a prefix.
+    fn stop_at(&self, mode: ScanMode) -> Vec<String> {
+        match mode {
+            ScanMode::FirstMatch if !self.has_prefix() => self.target_macs.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Report of a broadcast backend that heard `neighbors` out of `v4`;
+    /// silent hosts time out with `silent` as their error.
+    fn neighbor_report(
+        &self,
+        neighbors: Vec<Neighbor>,
+        v4: &[Ipv4Addr],
+        mode: ScanMode,
+        mut stats: ScanStats,
+        started: Instant,
+        silent: &str,
+    ) -> ScanReport {
+        stats.hosts_probed = v4.len();
+        stats.elapsed = started.elapsed();
+        if let Some(progress) = &self.progress {
+            progress.done.store(v4.len(), Ordering::Relaxed);
+        }
+
+        let matches: Vec<ScanMatch> = neighbors
+            .iter()
+            .filter(|n| self.is_target(&n.mac))
+            .map(|n| {
+                info!("Found {} on {}", n.mac, n.ip);
+                ScanMatch {
+                    ip: n.ip.clone(),
+                    mac: n.mac.clone(),
+                    interface: None,
+                    hostname: n.hostname.clone(),
+                    latency: None,
+                    dns_name: None,
+                    details: None,
+                }
+            })
+            .collect();
+        let results: Vec<HostResult> = v4
+            .iter()
+            .map(|ip| {
+                let ip = ip.to_string();
+                let replied = neighbors.iter().find(|n| n.ip == ip);
+                HostResult {
+                    status: match replied {
+                        Some(n) if self.is_target(&n.mac) => HostStatus::Match,
+                        Some(_) => HostStatus::Ok,
+                        None => HostStatus::Timeout,
+                    },
+                    macs: replied.map(|n| n.mac.clone()).into_iter().collect(),
+                    error: replied.is_none().then(|| silent.to_string()),
+                    elapsed: None,
+                    ip,
+                }
+            })
+            .collect();
+        results.iter().for_each(|result| self.emit(result));
+        let inventory = (mode == ScanMode::Inventory).then(|| {
+            neighbors
+                .into_iter()
+                .map(|n| DeviceIdentity {
+                    ip: n.ip,
+                    hostname: n.hostname,
+                    mac_list: vec![n.mac],
+                    interfaces: Vec::new(),
+                    mac_command: None,
+                    details: None,
+                })
+                .collect()
+        });
+
+        let reason = if !matches.is_empty() {
+            None
+        } else if stats.hosts_total == 0 {
+            Some(NotFoundReason::NoHosts)
+        } else {
+            Some(NotFoundReason::NoMatch { first_error: None })
+        };
+
+        ScanReport {
+            matches,
+            stats,
+            inventory,
+            hosts: results,
+            discovered: Vec::new(),
+            reason,
+        }
+    }
+
+    /// Keep `result` in `results`, and pass it on to a stream.
+    fn record(&self, results: &mut Vec<HostResult>, result: HostResult) {
+        self.emit(&result);
+        results.push(result);
+    }
+
+    /// Count a match in the progress, and pass `result` on to the
+    /// [`Scanner::scan_stream`] caller, if any.
+    fn emit(&self, result: &HostResult) {
+        if let (Some(progress), HostStatus::Match) = (&self.progress, result.status) {
+            progress.found.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(stream) = &self.stream {
+            // The caller may stop listening: the report still has it.
+            let _ = stream.send(result.clone());
+        }
+    }
+
+    /// Whether `mac` is, or falls under, one of the targets.
+    fn is_target(&self, mac: &str) -> bool {
+        self.target_macs.iter().any(|t| oui::target_matches(t, mac))
+    }
+
+    /// Whether any target is an OUI prefix.
+    fn has_prefix(&self) -> bool {
+        self.target_macs.iter().any(|t| oui::is_prefix(t))
+    }
+
+    /// Whether `matches` covers every target MAC. Never true with an OUI
+    /// prefix target, which wants every host it matches.
+    fn all_found(&self, matches: &[ScanMatch]) -> bool {
+        self.target_macs
+            .iter()
+            .all(|mac| !oui::is_prefix(mac) && matches.iter().any(|m| &m.mac == mac))
+    }
+
+    /// Whether `ip`, learned other than by probing, may be reported: it is
+    /// one of the `hosts` to scan (any host if there are none) and not in
+    /// the do-not-scan list.
+    fn in_scope(&self, hosts: &[IpAddr], ip: &IpAddr) -> bool {
+        (hosts.is_empty() || hosts.contains(ip))
+            && !self.do_not_scan.iter().any(|net| net.contains(ip))
+    }
+
+    /// Look the target MACs up in this machine's ARP table; `None` unless
+    /// it maps every one of them to one of the `hosts`.
+    async fn local_arp_shortcut(&self, hosts: &[IpAddr]) -> Option<Vec<ScanMatch>> {
+        if !self.local_arp || self.has_prefix() {
+            return None;
+        }
+        match task::spawn_blocking(arp_cache::read).await.ok()? {
+            Ok(neighbors) => self.shortcut_matches(&neighbors, "local ARP cache", hosts),
+            Err(e) => {
+                warn!("Cannot read the local ARP cache, falling back to the scan: {}", e);
+                None
+            }
+        }
+    }
+
+    /// The entries of `neighbors` (read from `source`) for every target
+    /// MAC, sorted by IP; `None` unless all of them are there. Entries for
+    /// IPs outside the `hosts` to scan, or not to be scanned, are ignored.
+    fn shortcut_matches(
+        &self,
+        neighbors: &[Neighbor],
+        source: &str,
+        hosts: &[IpAddr],
+    ) -> Option<Vec<ScanMatch>> {
+        let in_scope = |n: &&Neighbor| n.ip.parse().is_ok_and(|ip| self.in_scope(hosts, &ip));
+        let mut found = Vec::new();
+        for mac in &self.target_macs {
+            let mut entries = neighbors.iter().filter(|n| &n.mac == mac).peekable();
+            let listed = entries.peek().is_some();
+            match entries.find(in_scope) {
+                Some(n) => {
+                    info!("ARP shortcut: {} maps {} to {}", source, mac, n.ip);
+                    found.push(ScanMatch {
+                        ip: n.ip.clone(),
+                        mac: n.mac.clone(),
+                        interface: None,
+                        hostname: None,
+                        latency: None,
+                        dns_name: None,
+                        details: None,
+                    });
+                }
+                None if listed => {
+                    info!("ARP shortcut: {} maps {} to a host not being scanned", source, mac)
+                }
+                None => info!("ARP shortcut: {} has no entry for {}", source, mac),
+            }
+        }
+        found.sort_by_key(|m| m.ip.parse::<IpAddr>().ok());
+        self.all_found(&found).then_some(found)
+    }
+
+    /// Look the target MACs up in the ARP helper's neighbor table; `None`
+    /// unless it maps every one of them to one of the `hosts`.
+    async fn arp_shortcut(&self, hosts: &[IpAddr]) -> Option<Vec<ScanMatch>> {
+        let helper = self.arp_helper.clone().filter(|_| !self.has_prefix())?;
+        let config = match &self.backend {
+            Backend::Ssh(config) => config.clone(),
+            #[cfg(feature = "arp")]
+            Backend::Arp(_) => return None,
+            Backend::Beacon(_) => return None,
+        };
+        let neighbors = task::spawn_blocking({
+            let helper = helper.clone();
+            move || config.fetch_neighbors(&helper)
+        })
+        .await
+        .ok()?;
+
+        match neighbors {
+            Ok(neighbors) => self.shortcut_matches(&neighbors, &helper, hosts),
+            Err(e) => {
+                warn!("ARP shortcut via {} failed, falling back to full scan: {}", helper, e);
+                None
+            }
+        }
+    }
+
+    /// Expand the `cidr` list into the hosts to probe, add the `listed` ones,
+    /// drop do-not-scan hosts and apply sampling if set.
+    async fn hosts(&self, cidr: &str, listed: &[IpAddr]) -> Result<Vec<IpAddr>, RadarError> {
+        // Host names are resolved up front; the rest must be addresses or
+        // CIDRs.
+        let mut named = Vec::new();
+        let mut ranges = Vec::new();
+        for entry in cidr.split(',').map(str::trim) {
+            if !is_host_name(entry) {
+                ranges.push(entry);
+                continue;
+            }
+            let ip = rdns::resolve(entry, rdns::RESOLVE_TIMEOUT)
+                .await
+                .map_err(|e| RadarError::UnresolvedHost(entry.to_string(), e))?;
+            info!("Scanning {} ({})", entry, ip);
+            if !self.quiet {
+                println!("Scanning {} ({}) ...", entry, ip);
+            }
+            named.push(ip);
+        }
+        let ranges = ranges.join(",");
+
+        let deadline = self.parse_budget.map(|budget| (Instant::now() + budget, budget));
+        let mut hosts = if ranges.trim().is_empty() {
+            Vec::new()
+        } else {
+            parse_hosts(&ranges, deadline, self.quiet)?
+        };
+        if !named.is_empty() {
+            hosts.extend(named);
+            hosts.sort();
+            hosts.dedup();
+        }
+        if !listed.is_empty() {
+            info!("Scanning {} listed host(s)", listed.len());
+            if !self.quiet {
+                println!("Scanning {} listed host(s) ...", listed.len());
+            }
+            hosts.extend_from_slice(listed);
+            hosts.sort();
+            hosts.dedup();
+        }
+
+        if !self.do_not_scan.is_empty() {
+            let before = hosts.len();
+            hosts.retain(|ip| !self.do_not_scan.iter().any(|net| net.contains(ip)));
+            check_deadline(cidr, deadline)?;
+            if hosts.len() < before {
+                info!("Skipping {} do-not-scan host(s)", before - hosts.len());
+            }
+        }
+
+        if let Some(sample) = self.sample {
+            let total = hosts.len();
+            let keep = ((total as f64 * sample.percent / 100.0).ceil() as usize).min(total);
+            let mut rng = match sample.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+
+            hosts.shuffle(&mut rng);
+            hosts.truncate(keep);
+            hosts.sort();
+
+            warn!(
+                "Sampled scan: probing {} of {} host(s) ({}%); results are non-exhaustive",
+                keep, total, sample.percent
+            );
+            if !self.quiet {
+                println!("Sampling {} of {} host(s) — scan is NOT exhaustive", keep, total);
+            }
+        }
+
+        Ok(hosts)
+    }
+
+    /// Spawn one concurrent probe per host. Each task resolves to that
+    /// host's identity, or why its probe failed.
+    ///
+    /// Dropping the returned set aborts every probe that is still waiting or
+    /// running, so a cancelled or short-circuited scan stops touching the
+    /// network and releases its `throttle` slots; a probe already inside
+    /// `fetch_macs` has its socket shut down, which ends it at once.
+    /// Each probe as a whole fails after [`SshTimeouts::total`](crate::ssh_client::SshTimeouts::total).
+    fn spawn_probes(
+        &self,
+        config: &SshConfig,
+        hosts: Vec<IpAddr>,
+        preprobe: Option<Duration>,
+        throttle: &Arc<Throttle>,
+    ) -> JoinSet<ProbeResult> {
+        // One RNG shared by every probe for the jitter delays.
+        let rng = Arc::new(Mutex::new(StdRng::from_entropy()));
+        let max_jitter = self.jitter;
+        let retry = self.retry;
+        let deadline = config.fetch_timeout();
+
+        let targets: Arc<[String]> = self.target_macs.clone().into();
+
+        let mut probes = JoinSet::new();
+
+        for ip in hosts {
+            let ip_str = ip.to_string();
+            let config = config.clone();
+            let throttle = throttle.clone();
+            let rng = rng.clone();
+            #[cfg(feature = "snmp")]
+            let snmp = self.snmp.clone();
+
+            // One span per host, closed with how its probe ended.
+            let span = info_span!("probe", ip = %ip, outcome = Empty, duration_ms = Empty);
+            let probe_span = span.clone();
+            let probe = async move {
+                // Jitter before taking a slot, so a sleeping probe does not
+                // keep another one from starting.
+                if !max_jitter.is_zero() {
+                    let delay = rng.lock().unwrap().gen_range(Duration::ZERO..=max_jitter);
+                    tokio::time::sleep(delay).await;
+                }
+
+                // Acquire a slot before blocking the thread pool.
+                let _slot = throttle.acquire().await.map_err(|e| ProbeFailure {
+                    ip: ip_str.clone(),
+                    status: HostStatus::Unreachable,
+                    message: format!("{}: {}", ip_str, e),
+                    elapsed: Duration::ZERO,
+                })?;
+
+                // Timed from here: waiting for the slot is not the host's time.
+                let began = Instant::now();
+                let fail = |status, message: String| ProbeFailure {
+                    ip: ip_str.clone(),
+                    status,
+                    message,
+                    elapsed: began.elapsed(),
+                };
+
+                // The SSH probe; a host it fails on may still answer SNMP.
+                let probed: ProbeResult = async {
+                    if let Some(timeout) = preprobe {
+                        let addr = SocketAddr::new(ip, config.port);
+                        match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+                            Ok(Ok(_)) => {}
+                            Ok(Err(e)) => {
+                                throttle.observe(e.kind() == std::io::ErrorKind::ConnectionReset);
+                                let msg =
+                                    format!("{}: port {} closed: {}", ip_str, config.port, e);
+                                info!("{}", msg);
+                                return Err(fail(HostStatus::Unreachable, msg));
+                            }
+                            Err(_) => {
+                                let msg = format!(
+                                    "{}: port {} did not answer within {}",
+                                    ip_str,
+                                    config.port,
+                                    humantime::format_duration(timeout)
+                                );
+                                info!("{}", msg);
+                                return Err(fail(HostStatus::Timeout, msg));
+                            }
+                        }
+                    }
+
+                    let mut attempt = 1;
+                    let (status, msg) = loop {
+                        debug!("{}: probing over SSH (attempt {})", ip_str, attempt);
+                        let started = Instant::now();
+                        // Shuts the attempt's socket down once it is over, so
+                        // a host stalling past the deadline, or a probe the
+                        // scan drops, does not keep holding a blocking thread.
+                        let abort = AbortOnDrop(ProbeAbort::default());
+                        let probe = task::spawn_blocking({
+                            let (config, ip_str) = (config.clone(), ip_str.clone());
+                            let (span, abort) = (probe_span.clone(), abort.0.clone());
+                            move || span.in_scope(|| config.fetch_macs_abortable(&ip_str, &abort))
+                        });
+
+                        let (status, msg, transient) =
+                            match tokio::time::timeout(deadline, probe).await {
+                                Ok(Ok(Ok(identity))) => {
+                                    throttle.observe(false);
+                                    return Ok((identity, started.elapsed()));
+                                }
+                                Ok(Ok(Err(e))) => {
+                                    throttle.observe(is_congestion(&e));
+                                    let msg = format!("{}: {}", ip_str, e);
+                                    (HostStatus::of(&e), msg, is_transient(&e))
+                                }
+                                Ok(Err(e)) => {
+                                    let msg = format!("{}: {}", ip_str, e);
+                                    (HostStatus::CommandFailed, msg, false)
+                                }
+                                Err(_) => (
+                                    HostStatus::Timeout,
+                                    format!(
+                                        "{}: no answer within {}",
+                                        ip_str,
+                                        humantime::format_duration(deadline)
+                                    ),
+                                    true,
+                                ),
+                            };
+
+                        if !transient || attempt >= retry.attempts {
+                            break match attempt {
+                                1 => (status, msg),
+                                n => (status, format!("{} (after {} attempts)", msg, n)),
+                            };
+                        }
+                        let delay = retry.backoff * 2u32.saturating_pow(attempt - 1);
+                        info!("{}; retrying in {}", msg, humantime::format_duration(delay));
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    };
+                    warn!("{}", msg);
+                    Err(fail(status, msg))
+                }
+                .await;
+
+                #[cfg(feature = "snmp")]
+                if let (Err(failure), Some(snmp)) = (&probed, &snmp) {
+                    if failure.status != HostStatus::Timeout {
+                        let started = Instant::now();
+                        let identify = task::spawn_blocking({
+                            let (snmp, ip_str) = (snmp.clone(), ip_str.clone());
+                            move || snmp.identify(&ip_str)
+                        });
+                        match identify.await {
+                            Ok(Ok(identity)) => {
+                                let macs = identity.mac_list.len();
+                                info!("{}: read {} MAC(s) over SNMP", ip_str, macs);
+                                return Ok((identity, started.elapsed()));
+                            }
+                            Ok(Err(e)) => debug!("{}: SNMP fallback failed: {}", ip_str, e),
+                            Err(e) => debug!("{}: SNMP fallback failed: {}", ip_str, e),
+                        }
+                    }
+                }
+                probed
+            };
+
+            let targets = targets.clone();
+            let host_span = span.clone();
+            probes.spawn(
+                async move {
+                    let probed = probe.await;
+                    let (outcome, elapsed) = match &probed {
+                        Ok((identity, latency)) => {
+                            let owns = |mac: &String| {
+                                targets.iter().any(|t| oui::target_matches(t, mac))
+                            };
+                            match identity.mac_list.iter().any(owns) {
+                                true => (HostStatus::Match, *latency),
+                                false => (HostStatus::Ok, *latency),
+                            }
+                        }
+                        Err(failure) => (failure.status, failure.elapsed),
+                    };
+                    host_span.record("outcome", outcome.as_str());
+                    host_span.record("duration_ms", elapsed.as_millis() as u64);
+                    probed
+                }
+                .instrument(span),
+            );
+        }
+
+        probes
+    }
+
+    /// Run `request` on a background task. Dropping the returned handle
+    /// cancels the scan.
+    pub fn spawn(self, request: ScanRequest) -> ScanHandle {
+        ScanHandle {
+            task: task::spawn(async move { self.run(request).await }),
+        }
+    }
+}
+
+/// A scan running on a background task, created by [`Scanner::spawn`].
+///
+/// Dropping the handle aborts the scan and all of its probes.
+pub struct ScanHandle {
+    task: JoinHandle<Result<ScanReport, RadarError>>,
+}
+
+impl ScanHandle {
+    /// Wait for the scan to finish.
+    pub async fn wait(mut self) -> Result<ScanReport, RadarError> {
+        match (&mut self.task).await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => Err(RadarError::Cancelled),
+        }
+    }
+}
+
+impl Drop for ScanHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Aborts its probe when dropped.
+struct AbortOnDrop(ProbeAbort);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// The IPv4 hosts of `hosts`; the IPv6 ones are skipped with a warning
+/// naming `backend`.
+fn ipv4_only(hosts: &[IpAddr], backend: &str) -> Vec<Ipv4Addr> {
+    let v4: Vec<_> = hosts
+        .iter()
+        .filter_map(|ip| match ip {
+            IpAddr::V4(v4) => Some(*v4),
+            IpAddr::V6(_) => None,
+        })
+        .collect();
+    if v4.len() < hosts.len() {
+        warn!("{} backend skips {} IPv6 host(s)", backend, hosts.len() - v4.len());
+    }
+    v4
+}
+
+/// Whether a probe that failed with `err` may succeed if tried again.
+fn is_transient(err: &RadarError) -> bool {
+    let RadarError::SshConnection(_, msg) = err else {
+        return false;
+    };
+    let msg = msg.to_lowercase();
+    ["timed out", "reset", "broken pipe", "banner", "unexpected eof"]
+        .iter()
+        .any(|hint| msg.contains(hint))
+}
+
+/// Whether `err` suggests the network or the host is shedding our
+/// connections: a reset, or a handshake that broke off. Refusals and
+/// timeouts are what absent hosts look like, so they do not count.
+fn is_congestion(err: &RadarError) -> bool {
+    let RadarError::SshConnection(_, msg) = err else {
+        return false;
+    };
+    let msg = msg.to_lowercase();
+    ["reset", "broken pipe", "banner", "unexpected eof", "exchange encryption keys"]
+        .iter()
+        .any(|hint| msg.contains(hint))
+}
+
+/// The MAC embedded in an IPv6 address whose interface ID was built with
+/// modified EUI-64 (`xxxx:xxff:fexx:xxxx`, as in SLAAC and link-local
+/// addresses), lowercase and colon-separated.
+fn eui64_mac(ip: &IpAddr) -> Option<String> {
+    let IpAddr::V6(v6) = ip else { return None };
+    let b = v6.octets();
+    if b[11] != 0xff || b[12] != 0xfe {
+        return None;
+    }
+    let mac = [b[8] ^ 0x02, b[9], b[10], b[13], b[14], b[15]];
+    Some(mac.iter().map(|o| format!("{:02x}", o)).collect::<Vec<_>>().join(":"))
+}
+
+/// Resolve once `token` is cancelled; never without a token.
+async fn cancelled(token: Option<&CancelToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// How many hosts to enumerate between deadline checks.
+const DEADLINE_CHECK_EVERY: usize = 4096;
+
+/// Largest range (in address bits) that will be expanded: a /8 in IPv4, a
+/// /104 in IPv6. Anything bigger would exhaust memory before any deadline.
+pub const MAX_HOST_BITS: u8 = 24;
+
+/// Parse a comma-separated list of IPv4/IPv6 CIDRs and single addresses into
+/// the sorted, de-duplicated host addresses to probe, giving up once
+/// `deadline` (the instant and the budget it came from) has passed. Prints a
+/// line per range unless `quiet`.
+fn parse_hosts(
+    ranges: &str,
+    deadline: Option<(Instant, Duration)>,
+    quiet: bool,
+) -> Result<Vec<IpAddr>, RadarError> {
+    let mut hosts = Vec::new();
+
+    for cidr in ranges.split(',').map(str::trim) {
+        let net: IpNet = cidr
+            .parse()
+            .or_else(|_| cidr.parse::<IpAddr>().map(IpNet::from))
+            .map_err(|_| RadarError::InvalidIpRange(cidr.to_string()))?;
+        if net.max_prefix_len() - net.prefix_len() > MAX_HOST_BITS {
+            return Err(RadarError::RangeTooLarge(cidr.to_string()));
+        }
+
+        let before = hosts.len();
+        for (i, ip) in net.hosts().enumerate() {
+            if i % DEADLINE_CHECK_EVERY == 0 {
+                check_deadline(cidr, deadline)?;
+            }
+            hosts.push(ip);
+        }
+        info!("Scanning {} host(s) in {}", hosts.len() - before, cidr);
+        if !quiet {
+            println!("Scanning {} host(s) in {} ...", hosts.len() - before, cidr);
+        }
+    }
+
+    hosts.sort();
+    hosts.dedup();
+    Ok(hosts)
+}
+
+/// Whether a range entry is a host name (`device-042.lab.local`) rather
+/// than an address or CIDR: dot-separated labels of letters, digits, `-`
+/// and `_`, with at least one letter.
+fn is_host_name(entry: &str) -> bool {
+    let entry = entry.strip_suffix('.').unwrap_or(entry);
+    entry.parse::<IpAddr>().is_err()
+        && entry.bytes().any(|b| b.is_ascii_alphabetic())
+        && entry.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
+
+/// Parse a comma-separated list of hosts to skip: addresses, CIDRs and
+/// inclusive ranges (see [`parse_exclusion`]).
+pub fn parse_exclusions(list: &str) -> Result<Vec<IpNet>, RadarError> {
+    let mut nets = Vec::new();
+    for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let entry_nets =
+            parse_exclusion(entry).ok_or_else(|| RadarError::InvalidIpRange(entry.to_string()))?;
+        nets.extend(entry_nets);
+    }
+    Ok(nets)
+}
+
+/// Parse one address, CIDR or inclusive range into the networks it covers,
+/// or `None` if it is none of these. A range is `10.8.0.200-10.8.0.250`, or
+/// for IPv4 `10.8.0.200-250` with only the last octet after the dash.
+pub fn parse_exclusion(entry: &str) -> Option<Vec<IpNet>> {
+    if let Ok(net) = entry.parse::<IpNet>() {
+        return Some(vec![net]);
+    }
+    if let Ok(ip) = entry.parse::<IpAddr>() {
+        return Some(vec![ip.into()]);
+    }
+
+    let (start, end) = entry.split_once('-')?;
+    let start: IpAddr = start.trim().parse().ok()?;
+    let end: IpAddr = match (start, end.trim().parse::<u8>()) {
+        (IpAddr::V4(start), Ok(last)) => {
+            let [a, b, c, _] = start.octets();
+            Ipv4Addr::new(a, b, c, last).into()
+        }
+        _ => end.trim().parse().ok()?,
+    };
+    match (start, end) {
+        (IpAddr::V4(start), IpAddr::V4(end)) if start <= end => {
+            Some(Ipv4Subnets::new(start, end, 0).map(IpNet::V4).collect())
+        }
+        (IpAddr::V6(start), IpAddr::V6(end)) if start <= end => {
+            Some(Ipv6Subnets::new(start, end, 0).map(IpNet::V6).collect())
+        }
+        _ => None,
+    }
+}
+
+/// Fail with [`RadarError::RangeParseTimeout`] if `deadline` has passed.
+fn check_deadline(cidr: &str, deadline: Option<(Instant, Duration)>) -> Result<(), RadarError> {
+    match deadline {
+        Some((at, budget)) if Instant::now() > at => {
+            Err(RadarError::RangeParseTimeout(cidr.to_string(), budget))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh_client::{AuthenticationMethod, HostKeyCheck, Platform, SshTimeouts};
+    use std::io::Write;
+
+    /// A password login to `port` that waits up to 10s at every stage.
+    fn config(port: u16) -> SshConfig {
+        SshConfig {
+            user: "root".into(),
+            port,
+            auth: AuthenticationMethod::Password("x".into()),
+            timeouts: SshTimeouts::uniform(Duration::from_secs(10)),
+            pinned_host_keys: Default::default(),
+            host_key_check: HostKeyCheck::Disabled,
+            interface_filter: None,
+            probe: None,
+            platform: Platform::Auto,
+            via: None,
+            details: false,
+            pool: None,
+        }
+    }
+
+    fn ips(list: &[&str]) -> Vec<IpAddr> {
+        list.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn ranges_expand_cidrs_and_single_addresses() {
+        let hosts = parse_hosts("10.0.0.0/30, 10.0.0.9,10.0.0.1", None, true).unwrap();
+        assert_eq!(hosts, ips(&["10.0.0.1", "10.0.0.2", "10.0.0.9"]));
+
+        let hosts = parse_hosts("fd00::/126,fe80::1", None, true).unwrap();
+        assert_eq!(hosts, ips(&["fd00::", "fd00::1", "fd00::2", "fd00::3", "fe80::1"]));
+
+        let err = parse_hosts("10.0.0.0/30,10.0.0.256", None, true).unwrap_err();
+        assert!(matches!(err, RadarError::InvalidIpRange(entry) if entry == "10.0.0.256"));
+    }
+
+    #[test]
+    fn ranges_too_large_to_expand_are_rejected() {
+        for range in ["10.0.0.0/7", "fd00::/103", "10.0.0.0/30,0.0.0.0/0"] {
+            let err = parse_hosts(range, None, true).unwrap_err();
+            assert!(matches!(err, RadarError::RangeTooLarge(_)), "{}: {:?}", range, err);
+        }
+    }
+
+    #[test]
+    fn expanding_a_range_stops_at_the_deadline() {
+        let budget = Duration::from_millis(1);
+        let passed = Instant::now().checked_sub(budget).unwrap();
+        let err = parse_hosts("10.0.0.0/16", Some((passed, budget)), true).unwrap_err();
+        match err {
+            RadarError::RangeParseTimeout(cidr, b) => {
+                assert_eq!((cidr.as_str(), b), ("10.0.0.0/16", budget))
+            }
+            err => panic!("expected a timeout, got {:?}", err),
+        }
+
+        let later = Instant::now() + Duration::from_secs(60);
+        let hosts = parse_hosts("10.0.0.0/24", Some((later, budget)), true).unwrap();
+        assert_eq!(hosts.len(), 254);
+    }
+
+    #[test]
+    fn exclusions_are_addresses_cidrs_and_ranges() {
+        let excluded = parse_exclusions("10.0.0.5, 10.0.0.0/30,,10.0.0.200-203").unwrap();
+        assert_eq!(excluded, nets(&["10.0.0.5/32", "10.0.0.0/30", "10.0.0.200/30"]));
+
+        let excluded = parse_exclusions("10.0.0.7-10.0.0.9,fd00::1-fd00::3").unwrap();
+        assert_eq!(
+            excluded,
+            nets(&["10.0.0.7/32", "10.0.0.8/31", "fd00::1/128", "fd00::2/127"])
+        );
+
+        for entry in ["10.0.0.9-10.0.0.5", "10.0.0.1-fd00::1", "10.0.0.1-300", "lab"] {
+            let err = parse_exclusions(&format!("10.0.0.1,{}", entry)).unwrap_err();
+            assert!(matches!(&err, RadarError::InvalidIpRange(e) if e == entry), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn eui64_addresses_give_back_their_mac() {
+        let mac = |ip: &str| eui64_mac(&ip.parse().unwrap());
+        // The U/L bit is flipped back: 0xba is 0xb8 with the bit set.
+        assert_eq!(mac("fe80::ba27:ebff:fe12:3456").as_deref(), Some("b8:27:eb:12:34:56"));
+        assert_eq!(mac("2001:db8:1:2:ba27:ebff:fe12:3456").as_deref(), Some("b8:27:eb:12:34:56"));
+        // A locally administered MAC has it clear in the address.
+        assert_eq!(mac("fe80::ff:fe00:1").as_deref(), Some("02:00:00:00:00:01"));
+    }
+
+    #[test]
+    fn other_addresses_have_no_eui64_mac() {
+        let mac = |ip: &str| eui64_mac(&ip.parse().unwrap());
+        // Privacy and stable-privacy addresses, manual ones, and IDs with
+        // only half of the ff:fe marker.
+        for ip in ["fe80::1c2b:3a4d:5e6f:7081", "fd00::1", "fe80::ba27:ebff:fd12:3456"] {
+            assert_eq!(mac(ip), None, "{}", ip);
+        }
+        assert_eq!(mac("fe80::ba27:eb00:fe12:3456"), None);
+        assert_eq!(mac("10.0.0.1"), None);
+    }
+
+    #[tokio::test]
+    async fn overlapping_exclusions_each_remove_their_hosts_once() {
+        let excluded = parse_exclusions("10.0.0.0/29, 10.0.0.4-10, 10.0.0.6, fd00::/127").unwrap();
+        let scanner = Scanner::new(config(22), Vec::new())
+            .with_quiet(true)
+            .with_do_not_scan(excluded);
+        let hosts = scanner.hosts("10.0.0.0/28,fd00::/126", &[]).await.unwrap();
+        assert_eq!(
+            hosts,
+            ips(&["10.0.0.11", "10.0.0.12", "10.0.0.13", "10.0.0.14", "fd00::2", "fd00::3"])
+        );
+    }
+
+    /// Wait up to 2s for `count` to reach `n`, failing with `what`.
+    async fn wait_for(count: &AtomicUsize, n: usize, what: &str) {
+        let started = Instant::now();
+        while count.load(Ordering::SeqCst) != n {
+            assert!(started.elapsed() < Duration::from_secs(2), "{}", what);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn dropping_a_scan_handle_cancels_its_probes() {
+        // Answers on every loopback address with a banner, then stalls
+        // until the prober hangs up. Like sshd's MaxStartups, it serves two
+        // connections at a time and hangs up on any more at once.
+        let listener = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let served = Arc::new(AtomicUsize::new(0));
+        let refused = Arc::new(AtomicUsize::new(0));
+        let open = Arc::new(AtomicUsize::new(0));
+        std::thread::spawn({
+            let (served, refused, open) = (served.clone(), refused.clone(), open.clone());
+            move || {
+                for mut stream in listener.incoming().flatten() {
+                    if open.load(Ordering::SeqCst) >= 2 {
+                        refused.fetch_add(1, Ordering::SeqCst);
+                        continue;
+                    }
+                    open.fetch_add(1, Ordering::SeqCst);
+                    served.fetch_add(1, Ordering::SeqCst);
+                    let open = open.clone();
+                    std::thread::spawn(move || {
+                        let _ = stream.write_all(b"SSH-2.0-OpenSSH_9.6\r\n");
+                        let _ = std::io::copy(&mut stream, &mut std::io::sink());
+                        open.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+            }
+        });
+        let scan = || {
+            Scanner::new(config(port), vec!["02:00:00:00:00:01".into()])
+                .with_concurrency(2)
+                .with_quiet(true)
+                .spawn(ScanRequest {
+                    range: "127.0.0.0/28".into(),
+                    hosts: Vec::new(),
+                    mode: ScanMode::FirstMatch,
+                })
+        };
+
+        // Both slots are taken by probes stuck in the handshake.
+        let handle = scan();
+        wait_for(&served, 2, "no probe connected").await;
+
+        // The stalled handshakes hang up long before their 10s timeout, and
+        // no further host is probed.
+        drop(handle);
+        wait_for(&open, 0, "probes still connected").await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+
+        // So a second scan gets both of the server's places at once.
+        let _handle = scan();
+        wait_for(&served, 4, "the second scan cannot connect").await;
+        assert_eq!(refused.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::errors::RadarError;
+use crate::ssh_client::{DeviceIdentity, InterfaceMac};
+use serde::Serialize;
+use snmp::{SyncSession, Value};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// BRIDGE-MIB `dot1dTpFdbPort`, indexed by the 6 MAC octets.
+const DOT1D_TP_FDB_PORT: &[u32] = &[1, 3, 6, 1, 2, 1, 17, 4, 3, 1, 2];
+/// Q-BRIDGE-MIB `dot1qTpFdbPort`, indexed by VLAN id + the 6 MAC octets.
+const DOT1Q_TP_FDB_PORT: &[u32] = &[1, 3, 6, 1, 2, 1, 17, 7, 1, 2, 2, 1, 2];
+/// BRIDGE-MIB `dot1dBasePortIfIndex`, maps a bridge port to an ifIndex.
+const DOT1D_BASE_PORT_IF_INDEX: &[u32] = &[1, 3, 6, 1, 2, 1, 17, 1, 4, 1, 2];
+/// IF-MIB `ifName`, indexed by ifIndex.
+const IF_NAME: &[u32] = &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 1];
+/// IF-MIB `ifDescr`, indexed by ifIndex; for agents without `ifName`.
+const IF_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 2, 2, 1, 2];
+/// IF-MIB `ifPhysAddress`, indexed by ifIndex.
+const IF_PHYS_ADDRESS: &[u32] = &[1, 3, 6, 1, 2, 1, 2, 2, 1, 6];
+/// SNMPv2-MIB `sysName.0`.
+const SYS_NAME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 5, 0];
+
+/// Upper bound on GETNEXT requests when walking the Q-BRIDGE table.
+const MAX_WALK: usize = 100_000;
+
+/// Upper bound on interfaces read from one host's `ifPhysAddress` column.
+const MAX_INTERFACES: usize = 1024;
+
+/// What [`DeviceIdentity::mac_command`] says for hosts identified over SNMP.
+pub const SNMP_MAC_COMMAND: &str = "snmp ifPhysAddress";
+
+/// Where a switch has learned the target MAC.
+#[derive(Debug, Clone, Serialize)]
+pub struct FdbEntry {
+    /// Switch that was queried.
+    pub switch: String,
+    /// Bridge port number the MAC was learned on.
+    pub port: i64,
+    /// VLAN id, when found via the Q-BRIDGE table.
+    pub vlan: Option<u32>,
+    /// Interface name for the bridge port, if the switch exposes it.
+    pub if_name: Option<String>,
+}
+
+impl fmt::Display for FdbEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "port {}", self.port)?;
+        if let Some(name) = &self.if_name {
+            write!(f, " ({})", name)?;
+        }
+        if let Some(vlan) = self.vlan {
+            write!(f, ", VLAN {}", vlan)?;
+        }
+        write!(f, " on {}", self.switch)
+    }
+}
+
+/// SNMPv2c target parsed from `community@switch-ip`.
+#[derive(Debug, Clone)]
+pub struct SnmpTarget {
+    pub community: String,
+    pub switch: String,
+}
+
+impl SnmpTarget {
+    /// Parse a `community@switch-ip[:port]` specification.
+    pub fn parse(spec: &str) -> Result<Self, RadarError> {
+        match spec.rsplit_once('@') {
+            Some((community, switch)) if !community.is_empty() && !switch.is_empty() => Ok(Self {
+                community: community.to_string(),
+                switch: switch.to_string(),
+            }),
+            _ => Err(RadarError::Snmp(
+                spec.to_string(),
+                "expected <community>@<switch-ip>".into(),
+            )),
+        }
+    }
+
+    /// `host:port` address, defaulting to UDP 161.
+    fn address(&self) -> String {
+        if self.switch.contains(':') {
+            self.switch.clone()
+        } else {
+            format!("{}:161", self.switch)
+        }
+    }
+}
+
+/// SNMPv2c query made of hosts that refuse SSH, see
+/// `Scanner::with_snmp_fallback`.
+#[derive(Debug, Clone)]
+pub struct SnmpFallback {
+    pub community: String,
+    /// UDP port of the agents, usually 161.
+    pub port: u16,
+    /// How long to wait for each answer.
+    pub timeout: Duration,
+}
+
+impl SnmpFallback {
+    /// Read the interface MACs (IF-MIB `ifPhysAddress`) and `sysName` of
+    /// `ip`. Interfaces without a 6-byte address, such as loopbacks, are
+    /// left out; an agent that has none is an error.
+    ///
+    /// This is a **blocking** function.
+    pub fn identify(&self, ip: &str) -> Result<DeviceIdentity, RadarError> {
+        let err = |reason: String| RadarError::Snmp(ip.to_string(), reason);
+        let addr: IpAddr = ip.parse().map_err(|_| err("not an IP address".into()))?;
+        let mut session = SyncSession::new(
+            SocketAddr::new(addr, self.port),
+            self.community.as_bytes(),
+            Some(self.timeout),
+            0,
+        )
+        .map_err(|e| err(e.to_string()))?;
+
+        debug!("{}: reading ifPhysAddress over SNMP", ip);
+        let mut interfaces: Vec<InterfaceMac> = Vec::new();
+        let addresses = walk_phys_addresses(&mut session).map_err(|e| err(format!("{:?}", e)))?;
+        for (if_index, mac) in addresses {
+            let name = octet_string(&mut session, IF_NAME, if_index)
+                .or_else(|| octet_string(&mut session, IF_DESCR, if_index))
+                .unwrap_or_else(|| format!("ifIndex {}", if_index));
+            interfaces.push(InterfaceMac { name, mac });
+        }
+        if interfaces.is_empty() {
+            return Err(err("no interface with a MAC address".into()));
+        }
+        let mut mac_list: Vec<String> = Vec::new();
+        for interface in &interfaces {
+            if !mac_list.contains(&interface.mac) {
+                mac_list.push(interface.mac.clone());
+            }
+        }
+
+        let hostname = session.get(SYS_NAME).ok().and_then(|pdu| {
+            pdu.varbinds.into_iter().find_map(|(_, v)| match v {
+                Value::OctetString(s) if !s.is_empty() => {
+                    Some(String::from_utf8_lossy(s).into_owned())
+                }
+                _ => None,
+            })
+        });
+
+        Ok(DeviceIdentity {
+            ip: ip.to_string(),
+            hostname,
+            mac_list,
+            interfaces,
+            mac_command: Some(SNMP_MAC_COMMAND.to_string()),
+            details: None,
+        })
+    }
+}
+
+/// Walk `ifPhysAddress`: every `(ifIndex, MAC)` with a 6-byte address
+/// other than all zeros.
+fn walk_phys_addresses(
+    session: &mut SyncSession,
+) -> Result<Vec<(u32, String)>, snmp::SnmpError> {
+    let mut found = Vec::new();
+    let mut cursor: Vec<u32> = IF_PHYS_ADDRESS.to_vec();
+
+    for _ in 0..MAX_INTERFACES {
+        let mut pdu = session.getnext(&cursor)?;
+        let Some((name, value)) = pdu.varbinds.next() else {
+            break;
+        };
+
+        let mut buf = [0u32; 128];
+        let Ok(name) = name.read_name(&mut buf) else {
+            break;
+        };
+        if !name.starts_with(IF_PHYS_ADDRESS) || name.len() != IF_PHYS_ADDRESS.len() + 1 {
+            break;
+        }
+
+        if let Value::OctetString(octets) = value {
+            if octets.len() == 6 && octets.iter().any(|&b| b != 0) {
+                let mac: Vec<String> = octets.iter().map(|b| format!("{:02x}", b)).collect();
+                found.push((name[IF_PHYS_ADDRESS.len()], mac.join(":")));
+            }
+        }
+        cursor = name.to_vec();
+    }
+
+    Ok(found)
+}
+
+/// The non-empty string in column `column` of the row `if_index`, if any.
+fn octet_string(session: &mut SyncSession, column: &[u32], if_index: u32) -> Option<String> {
+    let oid: Vec<u32> = column.iter().copied().chain([if_index]).collect();
+    let pdu = session.get(&oid).ok()?;
+    pdu.varbinds.into_iter().find_map(|(_, v)| match v {
+        Value::OctetString(s) if !s.is_empty() => Some(String::from_utf8_lossy(s).into_owned()),
+        _ => None,
+    })
+}
+
+/// Query the switch forwarding database for `mac` (colon-separated hex).
+///
+/// Tries the plain BRIDGE-MIB table first, then walks the per-VLAN
+/// Q-BRIDGE table. This is a **blocking** function.
+pub fn locate(target: &SnmpTarget, mac: &str, timeout: Duration) -> Result<FdbEntry, RadarError> {
+    let err = |reason: String| RadarError::Snmp(target.switch.clone(), reason);
+
+    let octets = parse_mac(mac).ok_or_else(|| err(format!("invalid MAC address '{}'", mac)))?;
+
+    let mut session = SyncSession::new(
+        target.address(),
+        target.community.as_bytes(),
+        Some(timeout),
+        0,
+    )
+    .map_err(|e| err(e.to_string()))?;
+
+    info!("Querying FDB on {} for {}", target.switch, mac);
+
+    // ── 1. dot1dTpFdbTable: direct GET on the MAC-indexed row ────────────
+    let oid: Vec<u32> = DOT1D_TP_FDB_PORT.iter().chain(octets.iter()).copied().collect();
+    let pdu = session.get(&oid).map_err(|e| err(format!("{:?}", e)))?;
+    let dot1d_port = pdu.varbinds.into_iter().find_map(|(_, value)| integer(&value));
+
+    let (port, vlan) = match dot1d_port {
+        Some(port) => (port, None),
+        None => {
+            // ── 2. dot1qTpFdbTable: walk and match the MAC suffix ────────
+            debug!("{} has no dot1d entry, walking Q-BRIDGE table", target.switch);
+            walk_qbridge(&mut session, &octets)
+                .map_err(|e| err(format!("{:?}", e)))?
+                .ok_or_else(|| RadarError::MacNotFound(mac.to_string()))?
+        }
+    };
+
+    let if_name = port_if_name(&mut session, port);
+
+    Ok(FdbEntry {
+        switch: target.switch.clone(),
+        port,
+        vlan: vlan.map(|v| v as u32),
+        if_name,
+    })
+}
+
+/// Walk `dot1qTpFdbPort` looking for a row whose index ends in `octets`.
+/// Returns `(port, vlan)` on a match.
+fn walk_qbridge(
+    session: &mut SyncSession,
+    octets: &[u32; 6],
+) -> Result<Option<(i64, Option<i64>)>, snmp::SnmpError> {
+    let mut cursor: Vec<u32> = DOT1Q_TP_FDB_PORT.to_vec();
+
+    for _ in 0..MAX_WALK {
+        let mut pdu = session.getnext(&cursor)?;
+        let Some((name, value)) = pdu.varbinds.next() else {
+            return Ok(None);
+        };
+
+        let mut buf = [0u32; 128];
+        let Ok(name) = name.read_name(&mut buf) else {
+            return Ok(None);
+        };
+        if !name.starts_with(DOT1Q_TP_FDB_PORT) || name.len() != DOT1Q_TP_FDB_PORT.len() + 7 {
+            return Ok(None);
+        }
+
+        let index = &name[DOT1Q_TP_FDB_PORT.len()..];
+        if index[1..] == octets[..] {
+            return Ok(integer(&value).map(|port| (port, Some(index[0] as i64))));
+        }
+        cursor = name.to_vec();
+    }
+
+    Ok(None)
+}
+
+/// Resolve a bridge port to its `ifName`, if the switch exposes both tables.
+fn port_if_name(session: &mut SyncSession, port: i64) -> Option<String> {
+    let port = u32::try_from(port).ok()?;
+
+    let oid: Vec<u32> = DOT1D_BASE_PORT_IF_INDEX.iter().copied().chain([port]).collect();
+    let pdu = session.get(&oid).ok()?;
+    let if_index = pdu.varbinds.into_iter().find_map(|(_, v)| integer(&v))?;
+    let if_index = u32::try_from(if_index).ok()?;
+
+    octet_string(session, IF_NAME, if_index)
+}
+
+fn integer(value: &Value<'_>) -> Option<i64> {
+    match *value {
+        Value::Integer(n) => Some(n),
+        _ => None,
+    }
+}
+
+/// Parse `aa:bb:cc:dd:ee:ff` (or `-` separated) into six OID sub-ids.
+fn parse_mac(mac: &str) -> Option<[u32; 6]> {
+    let mut out = [0u32; 6];
+    let mut parts = mac.split([':', '-']);
+    for slot in out.iter_mut() {
+        *slot = u32::from_str_radix(parts.next()?, 16).ok().filter(|b| *b <= 0xff)?;
+    }
+    parts.next().is_none().then_some(out)
+}
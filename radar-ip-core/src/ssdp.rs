@@ -0,0 +1,162 @@
+use crate::errors::RadarError;
+use crate::ssh_client::normalize_mac;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// The SSDP group and port (UPnP Device Architecture 1.1).
+const SSDP_GROUP: (Ipv4Addr, u16) = (Ipv4Addr::new(239, 255, 255, 250), 1900);
+
+/// What is searched for unless [`SsdpConfig::search_target`] says
+/// otherwise: every device and service.
+pub const DEFAULT_SEARCH_TARGET: &str = "ssdp:all";
+
+/// SSDP search configuration.
+#[derive(Debug, Clone)]
+pub struct SsdpConfig {
+    /// The `ST` of the M-SEARCH, e.g. `ssdp:all` or `upnp:rootdevice`.
+    pub search_target: String,
+    /// How long to collect answers; also sent as `MX`, at least 1 s.
+    pub timeout: Duration,
+}
+
+/// A device that answered the M-SEARCH.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Responder {
+    pub ip: IpAddr,
+    /// Its `USN`, e.g. `uuid:2f402f80-da50-11e1-9b23-001788102201::upnp:rootdevice`.
+    pub usn: String,
+    /// The `uuid:…` part of the USN.
+    pub uuid: Option<String>,
+    /// Its `LOCATION`: the URL of its device description.
+    pub location: Option<String>,
+    /// Its `SERVER` header, e.g. `Linux/3.14 UPnP/1.0 IpBridge/1.26.0`.
+    pub server: Option<String>,
+    /// The MAC in the node field of a time-based (version 1) UUID,
+    /// lowercase and colon-separated.
+    pub mac: Option<String>,
+}
+
+impl SsdpConfig {
+    /// Multicast an M-SEARCH for [`SsdpConfig::search_target`] and return
+    /// every device that answered within the timeout, one per IP, sorted by
+    /// address. Returns early once the UUIDs have named every MAC in
+    /// `stop_at` (lowercase); an empty `stop_at` listens for the full
+    /// timeout.
+    ///
+    /// Only IPv4 is searched. This is a **blocking** function and is
+    /// intended to be called from inside `tokio::task::spawn_blocking`.
+    pub fn search(&self, stop_at: &[String]) -> Result<Vec<Responder>, RadarError> {
+        let err = |e: io::Error| RadarError::Ssdp(e.to_string());
+        let socket = open().map_err(err)?;
+        let mx = self.timeout.as_secs().clamp(1, 5);
+        let request = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}:{}\r\nMAN: \"ssdp:discover\"\r\n\
+             MX: {}\r\nST: {}\r\n\r\n",
+            SSDP_GROUP.0, SSDP_GROUP.1, mx, self.search_target
+        );
+        socket.send_to(request.as_bytes(), SSDP_GROUP).map_err(err)?;
+        info!(
+            "Searching SSDP for {} for {}",
+            self.search_target,
+            humantime::format_duration(self.timeout)
+        );
+
+        let mut found: BTreeMap<IpAddr, Responder> = BTreeMap::new();
+        let mut buf = [0u8; 4096];
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let done = !stop_at.is_empty()
+                && stop_at.iter().all(|mac| found.values().any(|r| r.mac.as_ref() == Some(mac)));
+            let left = deadline.saturating_duration_since(Instant::now());
+            if done || left.is_zero() {
+                break;
+            }
+            socket.set_read_timeout(Some(left)).map_err(err)?;
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    break
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(err(e)),
+            };
+            let Some(responder) = parse_response(&buf[..len], from.ip()) else {
+                debug!("Ignoring {}-byte SSDP datagram from {}", len, from.ip());
+                continue;
+            };
+            debug!("SSDP answer from {}: {}", responder.ip, responder.usn);
+            // A device answers once per embedded device and service; keep
+            // the first, completed by the others.
+            let known = found.entry(responder.ip).or_insert_with(|| responder.clone());
+            if known.mac.is_none() {
+                known.mac = responder.mac;
+            }
+            if known.location.is_none() {
+                known.location = responder.location;
+            }
+        }
+        Ok(found.into_values().collect())
+    }
+}
+
+/// A UDP socket on an ephemeral port that may send to the SSDP group.
+fn open() -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_multicast_ttl_v4(2)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)).into())?;
+    Ok(socket.into())
+}
+
+/// The responder an `HTTP/1.1 200 OK` search response from `from`
+/// describes; `None` for anything else, such as our own M-SEARCH.
+fn parse_response(payload: &[u8], from: IpAddr) -> Option<Responder> {
+    let text = String::from_utf8_lossy(payload);
+    let mut lines = text.lines();
+    if !lines.next()?.to_ascii_uppercase().starts_with("HTTP/1.1 200") {
+        return None;
+    }
+    let mut headers = BTreeMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_uppercase(), value.trim().to_string());
+        }
+    }
+    let usn = headers.remove("USN")?;
+    let uuid = usn
+        .split("::")
+        .next()
+        .filter(|id| id.to_ascii_lowercase().starts_with("uuid:"))
+        .map(str::to_string);
+    let mac = uuid.as_deref().and_then(uuid_mac);
+    Some(Responder {
+        ip: from,
+        usn,
+        uuid,
+        location: headers.remove("LOCATION"),
+        server: headers.remove("SERVER"),
+        mac,
+    })
+}
+
+/// The MAC in the node field of `uuid` (`uuid:xxxxxxxx-xxxx-1xxx-xxxx-<node>`)
+/// if it is time-based and its node is not a random one (multicast bit
+/// set, RFC 4122 §4.5).
+fn uuid_mac(uuid: &str) -> Option<String> {
+    let uuid = uuid.get(5..)?;
+    let groups: Vec<&str> = uuid.split('-').collect();
+    let [_, _, version, _, node] = groups.as_slice() else {
+        return None;
+    };
+    if !version.starts_with('1') || node.len() != 12 {
+        return None;
+    }
+    let first = u8::from_str_radix(node.get(..2)?, 16).ok()?;
+    if first & 1 == 1 {
+        return None;
+    }
+    normalize_mac(node)
+}
@@ -0,0 +1,2101 @@
+use crate::errors::RadarError;
+use crate::report::opt_secs_f64;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ssh2::{
+    Channel, CheckResult, HashType, KnownHostFileKind, KnownHosts, OpenFlags, OpenType, Session,
+    Sftp,
+};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use std::io::Write;
+
+/// Where [`SshConfig::fetch_leases`] looks unless told otherwise: OpenWrt,
+/// dnsmasq on Debian and Fedora, and ISC dhcpd on Debian, Fedora and the
+/// BSDs.
+pub const DEFAULT_LEASE_FILES: [&str; 6] = [
+    "/tmp/dhcp.leases",
+    "/var/lib/misc/dnsmasq.leases",
+    "/var/lib/dnsmasq/dnsmasq.leases",
+    "/var/lib/dhcp/dhcpd.leases",
+    "/var/lib/dhcpd/dhcpd.leases",
+    "/var/db/dhcpd.leases",
+];
+
+/// SSH authentication method.
+#[derive(Debug, Clone)]
+pub enum AuthenticationMethod {
+    /// Authenticate with a username/password pair.
+    Password(String),
+    /// Authenticate with a private key file and an optional passphrase.
+    PrivateKey {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+    /// Authenticate with a private key loaded from memory (e.g. env var).
+    PrivateKeyMemory {
+        key_data: String,
+        passphrase: Option<String>,
+    },
+    /// Authenticate with any identity held by the running ssh-agent.
+    Agent,
+}
+
+/// How server host keys are verified after the handshake. Hosts with an
+/// entry in [`SshConfig::pinned_host_keys`] are checked against the pin
+/// instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HostKeyCheck {
+    /// Accept any host key (the historical behavior).
+    #[default]
+    Disabled,
+    /// Trust on first use: record unknown hosts in the known_hosts file at
+    /// `path`, reject keys that differ from a recorded one.
+    Tofu { path: PathBuf },
+    /// Only accept hosts whose key is already recorded at `path`.
+    Strict { path: PathBuf },
+}
+
+/// How long each stage of an SSH probe may take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SshTimeouts {
+    /// TCP connect.
+    pub connect: Duration,
+    /// SSH banner exchange, key exchange and host-key check.
+    pub handshake: Duration,
+    /// Password or public-key authentication.
+    pub auth: Duration,
+    /// Each blocking libssh2 call while running a command and reading its
+    /// output.
+    pub command: Duration,
+}
+
+impl SshTimeouts {
+    /// The same limit for every stage.
+    pub fn uniform(timeout: Duration) -> Self {
+        Self {
+            connect: timeout,
+            handshake: timeout,
+            auth: timeout,
+            command: timeout,
+        }
+    }
+
+    /// Upper bound for a whole [`SshConfig::fetch_macs`] without details:
+    /// every stage once, plus a second command (`hostname`).
+    pub fn total(&self) -> Duration {
+        self.connect + self.handshake + self.auth + self.command * 2
+    }
+}
+
+/// How long an idle [`pump`] sleeps before polling both sides again.
+const PUMP_IDLE: Duration = Duration::from_millis(2);
+
+/// How often a [`SessionPool`] sends a keepalive on each idle session.
+pub const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// Most sessions a [`SessionPool`] keeps; further ones are closed.
+const POOL_CAPACITY: usize = 256;
+
+/// libssh2's blocking timeout for `d`, in milliseconds.
+fn millis(d: Duration) -> u32 {
+    d.as_millis().min(u32::MAX as u128) as u32
+}
+
+/// SSH connection configuration.
+#[derive(Debug, Clone)]
+pub struct SshConfig {
+    /// Remote username.
+    pub user: String,
+    /// Remote port (typically 22).
+    pub port: u16,
+    /// Authentication method.
+    pub auth: AuthenticationMethod,
+    /// Limits of each stage of a connection.
+    pub timeouts: SshTimeouts,
+    /// Exact SHA256 host-key fingerprints that specific IPs must present.
+    pub pinned_host_keys: HashMap<String, Vec<u8>>,
+    /// known_hosts policy for hosts without a pin.
+    pub host_key_check: HostKeyCheck,
+    /// Only consider interfaces whose name matches this pattern.
+    pub interface_filter: Option<Regex>,
+    /// List interfaces with this instead of [`MAC_COMMANDS`].
+    pub probe: Option<ProbeTemplate>,
+    /// Which commands list the interfaces, and how strictly they are run.
+    pub platform: Platform,
+    /// Tunnel every connection through this bastion.
+    pub via: Option<JumpHost>,
+    /// Also read [`DeviceDetails`] from every host ([`DETAIL_COMMANDS`]).
+    pub details: bool,
+    /// Reuse authenticated sessions from this pool, and return them to it.
+    pub pool: Option<Arc<SessionPool>>,
+}
+
+/// Matches MACs in colon, dash or Cisco dotted (`aabb.ccdd.eeff`) form.
+const ANY_MAC: &str =
+    r"(?i)\b[0-9a-f]{2}(?:[:-][0-9a-f]{2}){5}\b|\b[0-9a-f]{4}\.[0-9a-f]{4}\.[0-9a-f]{4}\b";
+
+/// A user-defined way to list a host's MACs, for devices where they only
+/// appear in a vendor command's output (`--probe-cmd`/`--probe-regex`).
+#[derive(Debug, Clone)]
+pub struct ProbeTemplate {
+    /// Command to run; it must exit with status 0.
+    pub command: String,
+    /// Finds the MACs in its output: the `mac` group of each match, else
+    /// its first group, else the whole match. An `iface` group names the
+    /// interface.
+    pub regex: Regex,
+}
+
+impl ProbeTemplate {
+    /// A template for `command`, matching any MAC when `regex` is `None`.
+    pub fn new(command: String, regex: Option<Regex>) -> Self {
+        Self {
+            command,
+            regex: regex.unwrap_or_else(|| Regex::new(ANY_MAC).expect("MAC regex is valid")),
+        }
+    }
+
+    /// Every MAC the regex finds in `output`, normalized; matches that are
+    /// not a MAC are skipped.
+    fn parse(&self, output: &str) -> Vec<InterfaceMac> {
+        self.regex
+            .captures_iter(output)
+            .filter_map(|cap| {
+                let mac = cap.name("mac").or_else(|| cap.get(1)).or_else(|| cap.get(0))?;
+                Some(InterfaceMac {
+                    name: cap.name("iface").map(|m| m.as_str().to_string()).unwrap_or_default(),
+                    mac: normalize_mac(mac.as_str())?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// `mac` in lowercase colon-separated form, whatever its separators; `None`
+/// unless it holds exactly twelve hex digits.
+pub(crate) fn normalize_mac(mac: &str) -> Option<String> {
+    let hex: Vec<char> = mac
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | '.'))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if hex.len() != 12 || !hex.iter().all(char::is_ascii_hexdigit) {
+        return None;
+    }
+    Some(
+        hex.chunks(2)
+            .map(|pair| pair.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join(":"),
+    )
+}
+
+/// A bastion that probe connections are tunnelled through (`--via`).
+///
+/// It is logged into with the probe's own credentials and host-key policy,
+/// then asked to open a `direct-tcpip` channel to each device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpHost {
+    /// Login on the bastion; the probe user if unset.
+    pub user: Option<String>,
+    /// Bastion host name or address.
+    pub host: String,
+    /// Bastion SSH port.
+    pub port: u16,
+}
+
+impl JumpHost {
+    /// Parse `[user@]host[:port]`; IPv6 addresses with a port must be
+    /// bracketed (`[fd00::1]:2222`).
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (user, rest) = match s.rsplit_once('@') {
+            Some((user, rest)) if !user.is_empty() => (Some(user.to_string()), rest),
+            Some(_) => return Err(format!("empty user in '{}'", s)),
+            None => (None, s),
+        };
+        let (host, port) = if let Some(bracketed) = rest.strip_prefix('[') {
+            let (host, tail) = bracketed
+                .split_once(']')
+                .ok_or_else(|| format!("unclosed '[' in '{}'", s))?;
+            match tail.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None if tail.is_empty() => (host, None),
+                None => return Err(format!("unexpected '{}' after ']' in '{}'", tail, s)),
+            }
+        } else {
+            match rest.split_once(':') {
+                // A bare IPv6 address: no port.
+                Some(_) if rest.matches(':').count() > 1 => (rest, None),
+                Some((host, port)) => (host, Some(port)),
+                None => (rest, None),
+            }
+        };
+        if host.is_empty() {
+            return Err(format!("missing host in '{}'", s));
+        }
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| format!("invalid port '{}'", port))?,
+            None => 22,
+        };
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl std::fmt::Display for JumpHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(user) = &self.user {
+            write!(f, "{}@", user)?;
+        }
+        if self.host.contains(':') {
+            write!(f, "[{}]", self.host)?;
+        } else {
+            f.write_str(&self.host)?;
+        }
+        if self.port != 22 {
+            write!(f, ":{}", self.port)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single interface and its hardware address.
+#[derive(Debug, Clone, Serialize)]
+pub struct InterfaceMac {
+    /// Interface name (e.g. `eth0`).
+    pub name: String,
+    /// MAC address (lowercase, colon-separated).
+    pub mac: String,
+}
+
+/// One entry of a host's ARP/neighbor table.
+#[derive(Debug, Clone)]
+pub struct Neighbor {
+    /// IP address the neighbor answered for.
+    pub ip: String,
+    /// Its MAC address (lowercase, colon-separated).
+    pub mac: String,
+    /// The name it asked for, when read from a DHCP lease.
+    pub hostname: Option<String>,
+}
+
+/// What a command run by [`SshConfig::run_command_output`] printed, and
+/// how it exited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Its exit status; 0 if the host never reported one.
+    pub status: i32,
+}
+
+/// Information gathered from a single device.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceIdentity {
+    /// The IP address that was probed.
+    pub ip: String,
+    /// The host's own name (`hostname`), if it reported one.
+    pub hostname: Option<String>,
+    /// All MAC addresses found on that host (lowercase, colon-separated).
+    pub mac_list: Vec<String>,
+    /// The interface each MAC was found on.
+    pub interfaces: Vec<InterfaceMac>,
+    /// The command the interfaces were read with (one of
+    /// [`MAC_COMMANDS`]); `None` when they were not read over SSH.
+    pub mac_command: Option<String>,
+    /// OS, kernel, uptime and addresses, with [`SshConfig::details`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<DeviceDetails>,
+}
+
+/// What [`DETAIL_COMMANDS`] report about a host. Each field is best
+/// effort: a command that fails or is missing leaves it empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DeviceDetails {
+    /// `PRETTY_NAME` from `/etc/os-release` (e.g. `Debian GNU/Linux 12`).
+    pub os_release: Option<String>,
+    /// Kernel release (`uname -r`).
+    pub kernel: Option<String>,
+    /// Time since boot, from `/proc/uptime` (serialized as `uptime_secs`).
+    #[serde(
+        rename = "uptime_secs",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "opt_secs_f64"
+    )]
+    pub uptime: Option<Duration>,
+    /// Every address of every interface (`ip -o addr`).
+    pub addresses: Vec<InterfaceAddress>,
+}
+
+/// One IP address of an interface, with the interface's MAC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InterfaceAddress {
+    /// Interface name (e.g. `eth0`).
+    pub name: String,
+    /// Address and prefix length (e.g. `192.168.1.20/24`, `fe80::1/64`).
+    pub address: String,
+    /// The interface's MAC, if it was among the listed interfaces.
+    pub mac: Option<String>,
+}
+
+/// What a probed host runs, which decides how its interfaces are listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Platform {
+    /// Windows commands if the SSH banner names Windows, else Linux ones
+    #[default]
+    Auto,
+    /// `ip link show`, `ifconfig -a`, then sysfs
+    Linux,
+    /// `ip -o link`, then sysfs; command timeout at most 2s, partial output
+    /// of stalled reads kept, channels closed without waiting
+    #[serde(rename = "busybox")]
+    BusyBox,
+    /// `ipconfig /all`, then PowerShell's `Get-NetAdapter`
+    Windows,
+}
+
+impl Platform {
+    /// The commands to try on a host whose SSH banner is `banner`.
+    fn commands(self, banner: Option<&str>) -> &'static [(&'static str, InterfaceParser)] {
+        match self {
+            Platform::Auto if banner.is_some_and(|b| b.contains("Windows")) => &WINDOWS_COMMANDS,
+            Platform::Auto | Platform::Linux => &MAC_COMMANDS,
+            Platform::BusyBox => &BUSYBOX_COMMANDS,
+            Platform::Windows => &WINDOWS_COMMANDS,
+        }
+    }
+}
+
+/// Parses the output of one of the [`MAC_COMMANDS`].
+type InterfaceParser = fn(&str) -> Vec<InterfaceMac>;
+
+/// Ways to list a host's interfaces, tried in order until one exits
+/// successfully: iproute2, then net-tools (also BusyBox), then sysfs.
+pub const MAC_COMMANDS: [(&str, InterfaceParser); 3] = [
+    ("ip link show", parse_ip_link),
+    ("ifconfig -a", parse_ifconfig),
+    ("grep -H . /sys/class/net/*/address", parse_sys_class_net),
+];
+
+/// [`MAC_COMMANDS`] for BusyBox targets: one-line `ip` output, then sysfs.
+pub const BUSYBOX_COMMANDS: [(&str, InterfaceParser); 2] = [
+    ("ip -o link", parse_ip_link),
+    ("grep -H . /sys/class/net/*/address", parse_sys_class_net),
+];
+
+/// [`MAC_COMMANDS`] for Windows hosts running OpenSSH.
+pub const WINDOWS_COMMANDS: [(&str, InterfaceParser); 2] = [
+    ("ipconfig /all", parse_ipconfig),
+    (
+        concat!(
+            "powershell -NoProfile -NonInteractive -Command ",
+            "\"Get-NetAdapter | Format-List Name,MacAddress\"",
+        ),
+        parse_format_list,
+    ),
+];
+
+/// What [`SshConfig::details`] runs, in order: OS release, kernel, uptime
+/// and interface addresses.
+pub const DETAIL_COMMANDS: [&str; 4] =
+    ["cat /etc/os-release", "uname -r", "cat /proc/uptime", "ip -o addr"];
+
+/// Upper bound of [`SshTimeouts::command`] in BusyBox mode, where reads
+/// that never see EOF are common.
+const BUSYBOX_COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl SshConfig {
+    /// Connect to `ip`, list its interfaces with [`SshConfig::probe`] or
+    /// the first of [`MAC_COMMANDS`] that works, parse every MAC address,
+    /// read the hostname (`hostname`, else `/etc/hostname`) and, with
+    /// [`SshConfig::details`], the [`DeviceDetails`], and return a
+    /// [`DeviceIdentity`].  This is a
+    /// **blocking** function and is intended to be called from inside
+    /// `tokio::task::spawn_blocking`.
+    pub fn fetch_macs(&self, ip: &str) -> Result<DeviceIdentity, RadarError> {
+        self.with_session(ip, |session| self.identify(session, ip))
+    }
+
+    /// [`SshConfig::fetch_macs`] that ends as soon as `abort` is
+    /// [aborted](ProbeAbort::abort), instead of at the next libssh2
+    /// timeout.
+    pub fn fetch_macs_abortable(
+        &self,
+        ip: &str,
+        abort: &ProbeAbort,
+    ) -> Result<DeviceIdentity, RadarError> {
+        self.with_session_abortable(ip, Some(abort), |session| self.identify(session, ip))
+    }
+
+    /// The body of [`SshConfig::fetch_macs`], on an open session.
+    fn identify(&self, session: &Session, ip: &str) -> Result<DeviceIdentity, RadarError> {
+        let (command, mut interfaces) = match &self.probe {
+            Some(probe) => match self.exec_status(session, ip, &probe.command)? {
+                (output, 0) => (probe.command.as_str(), probe.parse(&output)),
+                (_, status) => {
+                    return Err(RadarError::CommandExecution(
+                        ip.to_string(),
+                        format!("`{}` exited with {}", probe.command, status),
+                    ))
+                }
+            },
+            None => self.list_interfaces(session, ip)?,
+        };
+        // Best effort: a host without `hostname` (minimal images often
+        // lack it) may still have `/etc/hostname`, and is identified
+        // either way.
+        let hostname = ["hostname", "cat /etc/hostname"].iter().find_map(|command| {
+            self.exec(session, ip, command)
+                .ok()
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+        });
+
+        if let Some(filter) = &self.interface_filter {
+            interfaces.retain(|iface| filter.is_match(&iface.name));
+        }
+        let mac_list = interfaces.iter().map(|iface| iface.mac.clone()).collect();
+        let details = self.details.then(|| self.fetch_details(session, ip, &interfaces));
+
+        Ok(DeviceIdentity {
+            ip: ip.to_string(),
+            hostname,
+            mac_list,
+            interfaces,
+            mac_command: Some(command.to_string()),
+            details,
+        })
+    }
+
+    /// Upper bound for a whole [`SshConfig::fetch_macs`]: the
+    /// [`SshTimeouts::total`], plus one command per [`DETAIL_COMMANDS`]
+    /// with [`SshConfig::details`], plus one command spent on a pooled
+    /// session that turns out dead.
+    pub fn fetch_timeout(&self) -> Duration {
+        let details = if self.details { DETAIL_COMMANDS.len() as u32 } else { 0 };
+        let pooled = u32::from(self.pool.is_some());
+        self.timeouts.total() + self.timeouts.command * (details + pooled)
+    }
+
+    /// Run the [`DETAIL_COMMANDS`], keeping whatever they report. Addresses
+    /// of interfaces the [`SshConfig::interface_filter`] rejects are left
+    /// out, and each one is paired with its MAC from `interfaces`.
+    fn fetch_details(
+        &self,
+        session: &Session,
+        ip: &str,
+        interfaces: &[InterfaceMac],
+    ) -> DeviceDetails {
+        let [os_release, kernel, uptime, addresses] = DETAIL_COMMANDS.map(|command| {
+            match self.exec_status(session, ip, command) {
+                Ok((output, 0)) => Some(output),
+                Ok((_, status)) => {
+                    tracing::debug!("{}: `{}` exited with {}", ip, command, status);
+                    None
+                }
+                Err(e) => {
+                    tracing::debug!("{}: `{}` failed: {}", ip, command, e);
+                    None
+                }
+            }
+        });
+        let mut addresses = addresses.as_deref().map(parse_ip_addr).unwrap_or_default();
+        if let Some(filter) = &self.interface_filter {
+            addresses.retain(|addr| filter.is_match(&addr.name));
+        }
+        for addr in &mut addresses {
+            addr.mac = interfaces
+                .iter()
+                .find(|iface| iface.name == addr.name)
+                .map(|iface| iface.mac.clone());
+        }
+        DeviceDetails {
+            os_release: os_release.as_deref().and_then(parse_os_release),
+            kernel: kernel.map(|k| k.trim().to_string()).filter(|k| !k.is_empty()),
+            uptime: uptime.as_deref().and_then(parse_proc_uptime),
+            addresses,
+        }
+    }
+
+    /// Connect to `ip` and read its ARP/neighbor table (`ip neigh`, falling
+    /// back to `arp -an`). **Blocking**, like [`SshConfig::fetch_macs`].
+    pub fn fetch_neighbors(&self, ip: &str) -> Result<Vec<Neighbor>, RadarError> {
+        let output = self.with_session(ip, |session| {
+            self.exec(session, ip, "ip neigh show 2>/dev/null || arp -an")
+        })?;
+        Ok(parse_neighbors(&output))
+    }
+
+    /// Connect to `ip`, a DHCP server, and read the leases in `files`
+    /// (dnsmasq or ISC dhcpd format; missing files are skipped), one per
+    /// IP. **Blocking**, like [`SshConfig::fetch_macs`].
+    pub fn fetch_leases(&self, ip: &str, files: &[String]) -> Result<Vec<Neighbor>, RadarError> {
+        let quoted: Vec<String> =
+            files.iter().map(|f| format!("'{}'", f.replace('\'', "'\\''"))).collect();
+        let command = format!("cat {} 2>/dev/null", quoted.join(" "));
+        let output = self.with_session(ip, |session| self.exec(session, ip, &command))?;
+        if output.trim().is_empty() {
+            return Err(RadarError::CommandExecution(
+                ip.to_string(),
+                format!("no lease file found (tried {})", files.join(", ")),
+            ));
+        }
+        Ok(parse_leases(&output))
+    }
+
+    /// Connect to `ip`, run `command`, and return its stdout. Used for
+    /// follow-up actions on a host that has already been located.
+    /// **Blocking**, like [`SshConfig::fetch_macs`].
+    pub fn run_command(&self, ip: &str, command: &str) -> Result<String, RadarError> {
+        self.with_session(ip, |session| self.exec(session, ip, command))
+    }
+
+    /// Like [`SshConfig::run_command`], keeping stderr and the exit status:
+    /// `radar-ip exec`. The session of the scan that found `ip` is reused
+    /// when [`SshConfig::pool`] holds it. **Blocking**, like
+    /// [`SshConfig::fetch_macs`].
+    pub fn run_command_output(&self, ip: &str, command: &str) -> Result<CommandOutput, RadarError> {
+        self.with_session(ip, |session| self.exec_output(session, ip, command))
+    }
+
+    /// Connect to `ip` and copy the local file `local` to `remote` over
+    /// SFTP, replacing any file there. A `remote` that is a directory (or
+    /// ends in `/`, or is empty: the login directory) receives it under
+    /// its own name. Returns where it went and the bytes copied.
+    /// **Blocking**, like [`SshConfig::fetch_macs`].
+    pub fn upload(
+        &self,
+        ip: &str,
+        local: &Path,
+        remote: &str,
+    ) -> Result<(String, u64), RadarError> {
+        let err = |e: &dyn std::fmt::Display| RadarError::Sftp(ip.to_string(), e.to_string());
+        let local_err = |e: io::Error| err(&format!("{}: {}", local.display(), e));
+        let metadata = std::fs::metadata(local).map_err(local_err)?;
+        let name = match local.file_name() {
+            Some(name) if metadata.is_file() => name.to_string_lossy().into_owned(),
+            _ => return Err(err(&format!("{} is not a file", local.display()))),
+        };
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) as i32 & 0o777;
+        #[cfg(not(unix))]
+        let mode = 0o644;
+
+        self.with_session(ip, |session| {
+            let sftp = session.sftp().map_err(|e| err(&e))?;
+            let target = remote_target(&sftp, remote, &name);
+            let mut source = std::fs::File::open(local).map_err(local_err)?;
+            let flags = OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE;
+            let mut file = sftp
+                .open_mode(Path::new(&target), flags, mode, OpenType::File)
+                .map_err(|e| err(&format!("{}: {}", target, e)))?;
+            let copied = io::copy(&mut source, &mut file)
+                .map_err(|e| err(&format!("{}: {}", target, e)))?;
+            Ok((target, copied))
+        })
+    }
+
+    /// Connect to `ip` and copy the file `remote` to `local` over SFTP,
+    /// replacing any file there; a `local` that is a directory receives it
+    /// under its own name. Returns where it went and the bytes copied.
+    /// **Blocking**, like [`SshConfig::fetch_macs`].
+    pub fn download(
+        &self,
+        ip: &str,
+        remote: &str,
+        local: &Path,
+    ) -> Result<(PathBuf, u64), RadarError> {
+        let err = |e: &dyn std::fmt::Display| RadarError::Sftp(ip.to_string(), e.to_string());
+        let name = remote.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+        let target = if local.is_dir() && !name.is_empty() {
+            local.join(name)
+        } else {
+            local.to_path_buf()
+        };
+
+        self.with_session(ip, |session| {
+            let sftp = session.sftp().map_err(|e| err(&e))?;
+            let remote_err = |e: &dyn std::fmt::Display| err(&format!("{}: {}", remote, e));
+            let mut file = sftp.open(Path::new(remote)).map_err(|e| remote_err(&e))?;
+            if file.stat().map_err(|e| remote_err(&e))?.is_dir() {
+                return Err(remote_err(&"is a directory"));
+            }
+            let local_err = |e: io::Error| err(&format!("{}: {}", target.display(), e));
+            let mut destination = std::fs::File::create(&target).map_err(local_err)?;
+            let copied = io::copy(&mut file, &mut destination).map_err(local_err)?;
+            Ok((target.clone(), copied))
+        })
+    }
+
+    /// Run `work` on a session to `ip`: a pooled one if [`SshConfig::pool`]
+    /// has it, else a new one. A pooled session that fails is dropped and
+    /// `work` runs again on a new one; a session `work` succeeded on goes
+    /// back to the pool.
+    fn with_session<T>(
+        &self,
+        ip: &str,
+        work: impl Fn(&Session) -> Result<T, RadarError>,
+    ) -> Result<T, RadarError> {
+        self.with_session_abortable(ip, None, work)
+    }
+
+    /// [`SshConfig::with_session`] whose sockets `abort` shuts down while
+    /// `work` runs.
+    fn with_session_abortable<T>(
+        &self,
+        ip: &str,
+        abort: Option<&ProbeAbort>,
+        work: impl Fn(&Session) -> Result<T, RadarError>,
+    ) -> Result<T, RadarError> {
+        let key = format!("{}@{}:{}", self.user, ip, self.port);
+        let run = |session: &Session| {
+            let value = work(session);
+            if let Some(abort) = abort {
+                abort.forget();
+            }
+            value
+        };
+        if let Some((session, stream)) = self.pool.as_ref().and_then(|pool| pool.take(&key)) {
+            if let Some(abort) = abort {
+                abort.watch(&stream, ip)?;
+            }
+            match run(&session) {
+                Ok(value) => {
+                    self.release(key, session, stream);
+                    return Ok(value);
+                }
+                Err(e) => tracing::debug!("{}: pooled session failed ({}); reconnecting", ip, e),
+            }
+        }
+        let (session, stream) = self.connect(ip, abort)?;
+        let value = run(&session)?;
+        self.release(key, session, stream);
+        Ok(value)
+    }
+
+    /// Return `session`, running over `stream`, to [`SshConfig::pool`], if
+    /// any.
+    fn release(&self, key: String, session: Session, stream: TcpStream) {
+        if let Some(pool) = &self.pool {
+            pool.put(key, session, stream);
+        }
+    }
+
+    /// Run each command for [`SshConfig::platform`] until one exits with
+    /// status 0, and return it with the interfaces it lists. Fails naming
+    /// every attempt if none does.
+    fn list_interfaces(
+        &self,
+        session: &Session,
+        ip: &str,
+    ) -> Result<(&'static str, Vec<InterfaceMac>), RadarError> {
+        let commands = self.platform.commands(session.banner());
+        let mut attempts = Vec::new();
+        for &(command, parse) in commands {
+            match self.exec_status(session, ip, command)? {
+                (output, 0) => return Ok((command, parse(&output))),
+                (_, status) => {
+                    tracing::debug!("{}: `{}` exited with {}", ip, command, status);
+                    attempts.push(format!("`{}` exited with {}", command, status));
+                }
+            }
+        }
+        Err(RadarError::CommandExecution(
+            ip.to_string(),
+            format!("no command could list the interfaces ({})", attempts.join(", ")),
+        ))
+    }
+
+    /// Run `command` on an authenticated session and return its stdout.
+    fn exec(&self, session: &Session, ip: &str, command: &str) -> Result<String, RadarError> {
+        self.exec_status(session, ip, command).map(|(output, _)| output)
+    }
+
+    /// Like [`SshConfig::exec`], with the command's exit status.
+    fn exec_status(
+        &self,
+        session: &Session,
+        ip: &str,
+        command: &str,
+    ) -> Result<(String, i32), RadarError> {
+        let output = self.exec_output(session, ip, command)?;
+        Ok((String::from_utf8_lossy(&output.stdout).into_owned(), output.status))
+    }
+
+    /// Run `command` on an authenticated session and collect its stdout,
+    /// then its stderr, and its exit status.
+    ///
+    /// In BusyBox mode a read that stalls after some output keeps what
+    /// arrived (and skips stderr), and the channel is closed without
+    /// waiting for the host to confirm; a host that never reports an exit
+    /// status counts as 0.
+    fn exec_output(
+        &self,
+        session: &Session,
+        ip: &str,
+        command: &str,
+    ) -> Result<CommandOutput, RadarError> {
+        let err = |e: &dyn std::fmt::Display| {
+            RadarError::CommandExecution(ip.to_string(), e.to_string())
+        };
+        let mut channel = session.channel_session().map_err(|e| err(&e))?;
+        channel.exec(command).map_err(|e| err(&e))?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        match channel.read_to_end(&mut stdout) {
+            Ok(_) => {
+                if let Err(e) = channel.stderr().read_to_end(&mut stderr) {
+                    tracing::debug!("{}: cannot read the stderr of `{}`: {}", ip, command, e);
+                }
+            }
+            Err(e) if self.platform != Platform::BusyBox || stdout.is_empty() => {
+                return Err(err(&e));
+            }
+            Err(e) => {
+                tracing::debug!("{}: `{}` stalled ({}); using the partial output", ip, command, e)
+            }
+        }
+
+        if self.platform == Platform::BusyBox {
+            let _ = channel.close();
+        } else {
+            let _ = channel.wait_close();
+        }
+        let status = channel.exit_status().map_err(|e| err(&e))?;
+        Ok(CommandOutput {
+            stdout,
+            stderr,
+            status,
+        })
+    }
+
+    /// Open an authenticated SSH session to `ip`, directly or through
+    /// [`SshConfig::via`], and return it with a handle on its socket. Every
+    /// socket opened is watched by `abort`.
+    fn connect(
+        &self,
+        ip: &str,
+        abort: Option<&ProbeAbort>,
+    ) -> Result<(Session, TcpStream), RadarError> {
+        // ── 1. TCP connect with timeout ───────────────────────────────────
+        let stream = match &self.via {
+            Some(jump) => self.tunnel(jump, ip, abort)?,
+            None => tcp_connect(ip, self.port, self.timeouts.connect)?,
+        };
+        if let Some(abort) = abort {
+            abort.watch(&stream, ip)?;
+        }
+        let handle = stream
+            .try_clone()
+            .map_err(|e| RadarError::SshConnection(ip.to_string(), e.to_string()))?;
+
+        let session = self.login(stream, ip, self.port, &self.user)?;
+        let command = if self.platform == Platform::BusyBox {
+            self.timeouts.command.min(BUSYBOX_COMMAND_TIMEOUT)
+        } else {
+            self.timeouts.command
+        };
+        session.set_timeout(millis(command));
+        Ok((session, handle))
+    }
+
+    /// Handshake, verify the host key of `host` and authenticate as `user`
+    /// over `stream`.
+    fn login(
+        &self,
+        stream: TcpStream,
+        host: &str,
+        port: u16,
+        user: &str,
+    ) -> Result<Session, RadarError> {
+        stream
+            .set_read_timeout(Some(self.timeouts.handshake))
+            .map_err(|e| RadarError::SshConnection(host.to_string(), e.to_string()))?;
+
+        // ── 2. SSH handshake ─────────────────────────────────────────────
+        let mut session = Session::new()
+            .map_err(|e| RadarError::SshConnection(host.to_string(), e.to_string()))?;
+        session.set_tcp_stream(stream);
+
+        // Once libssh2 owns the socket, the TcpStream read timeout is not
+        // reliably observed; bound each stage with libssh2's own blocking
+        // timeout so a slow device (e.g. one that accepts TCP but never
+        // sends a banner) cannot hang a worker.
+        session.set_timeout(millis(self.timeouts.handshake));
+        session
+            .handshake()
+            .map_err(|e| RadarError::SshConnection(host.to_string(), e.to_string()))?;
+
+        match self.pinned_host_keys.get(host) {
+            Some(expected) => verify_pinned_host_key(&session, host, expected)?,
+            None => verify_known_host(&session, host, port, &self.host_key_check)?,
+        }
+
+        // ── 3. Authenticate ───────────────────────────────────────────────
+        session.set_timeout(millis(self.timeouts.auth));
+        match &self.auth {
+            AuthenticationMethod::Password(pwd) => {
+                session
+                    .userauth_password(user, pwd)
+                    .map_err(|e| RadarError::Password(e.to_string()))?;
+            }
+            AuthenticationMethod::PrivateKey { path, passphrase } => {
+                session
+                    .userauth_pubkey_file(
+                        user,
+                        None,
+                        path,
+                        passphrase.as_deref(),
+                    )
+                    .map_err(|e| RadarError::PrivateKey(e.to_string()))?;
+            }
+            AuthenticationMethod::PrivateKeyMemory { key_data, passphrase } => {
+                authenticate_key_memory(&session, user, key_data, passphrase.as_deref())?;
+            }
+            AuthenticationMethod::Agent => {
+                // Offers each agent identity in turn until one is accepted.
+                session
+                    .userauth_agent(user)
+                    .map_err(|e| RadarError::Agent(e.to_string()))?;
+            }
+        }
+
+        if !session.authenticated() {
+            return Err(RadarError::SshConnection(
+                host.to_string(),
+                "authentication failed".into(),
+            ));
+        }
+
+        Ok(session)
+    }
+
+    /// Log into `jump`, open a `direct-tcpip` channel to `ip` and return the
+    /// local end of a loopback connection relayed to it by a [`pump`]
+    /// thread, which ends with either side. libssh2 only runs a session
+    /// over a real socket, hence the detour.
+    fn tunnel(
+        &self,
+        jump: &JumpHost,
+        ip: &str,
+        abort: Option<&ProbeAbort>,
+    ) -> Result<TcpStream, RadarError> {
+        let stream = tcp_connect(&jump.host, jump.port, self.timeouts.connect)?;
+        if let Some(abort) = abort {
+            abort.watch(&stream, &jump.host)?;
+        }
+        let user = jump.user.as_deref().unwrap_or(&self.user);
+        let bastion = self.login(stream, &jump.host, jump.port, user)?;
+
+        let fail = |e: &dyn std::fmt::Display| {
+            RadarError::SshConnection(ip.to_string(), format!("via {}: {}", jump, e))
+        };
+        bastion.set_timeout(millis(self.timeouts.connect));
+        let channel = bastion
+            .channel_direct_tcpip(ip, self.port, None)
+            .map_err(|e| fail(&e))?;
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| fail(&e))?;
+        let near = listener
+            .local_addr()
+            .and_then(TcpStream::connect)
+            .map_err(|e| fail(&e))?;
+        let (far, _) = listener.accept().map_err(|e| fail(&e))?;
+
+        std::thread::spawn(move || pump(bastion, channel, far));
+        Ok(near)
+    }
+}
+
+/// Ends a blocking probe from another thread: [`ProbeAbort::abort`] shuts
+/// down the sockets of the [`SshConfig::fetch_macs_abortable`] it was given,
+/// so the libssh2 call that probe is blocked in fails at once and its
+/// thread is freed.
+#[derive(Debug, Clone, Default)]
+pub struct ProbeAbort {
+    state: Arc<Mutex<AbortState>>,
+}
+
+#[derive(Debug, Default)]
+struct AbortState {
+    aborted: bool,
+    /// Handles on the sockets the probe is using.
+    streams: Vec<TcpStream>,
+}
+
+impl ProbeAbort {
+    /// Shut the probe's sockets down. A probe that has not connected yet
+    /// fails once it does.
+    pub fn abort(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.aborted = true;
+        for stream in state.streams.drain(..) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Shut `stream`, connected to `host`, down on [`ProbeAbort::abort`],
+    /// or fail now if that already happened.
+    fn watch(&self, stream: &TcpStream, host: &str) -> Result<(), RadarError> {
+        let mut state = self.state.lock().unwrap();
+        if state.aborted {
+            let _ = stream.shutdown(Shutdown::Both);
+            return Err(RadarError::SshConnection(host.to_string(), "probe aborted".into()));
+        }
+        if let Ok(handle) = stream.try_clone() {
+            state.streams.push(handle);
+        }
+        Ok(())
+    }
+
+    /// Stop watching the probe's sockets, which may now go to the pool.
+    fn forget(&self) {
+        self.state.lock().unwrap().streams.clear();
+    }
+}
+
+/// Authenticated SSH sessions kept open between probes, for scans that
+/// contact the same hosts again and again (`watch`, follow-up commands).
+///
+/// Sessions are keyed by user, host and port and held by one probe at a
+/// time. A background thread sends a keepalive on every idle session each
+/// interval and drops those whose connection is gone; it ends with the
+/// pool.
+pub struct SessionPool {
+    /// Each session, with a handle on the socket it runs over.
+    sessions: Mutex<HashMap<String, (Session, TcpStream)>>,
+}
+
+impl SessionPool {
+    /// An empty pool whose idle sessions get a keepalive every `keepalive`.
+    pub fn new(keepalive: Duration) -> Arc<Self> {
+        let pool = Arc::new(Self {
+            sessions: Mutex::new(HashMap::new()),
+        });
+        let weak = Arc::downgrade(&pool);
+        std::thread::spawn(move || keep_alive(weak, keepalive));
+        pool
+    }
+
+    /// How many idle sessions the pool holds.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Whether the pool holds no idle session.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn take(&self, key: &str) -> Option<(Session, TcpStream)> {
+        let pooled = self.sessions.lock().unwrap().remove(key)?;
+        tracing::debug!("Reusing the SSH session to {}", key);
+        Some(pooled)
+    }
+
+    fn put(&self, key: String, session: Session, stream: TcpStream) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() < POOL_CAPACITY || sessions.contains_key(&key) {
+            sessions.insert(key, (session, stream));
+        }
+    }
+}
+
+impl std::fmt::Debug for SessionPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionPool").field("sessions", &self.len()).finish()
+    }
+}
+
+/// Every `interval`, send a keepalive on each idle session of `pool` and
+/// drop the sessions it fails on, until the pool is gone.
+fn keep_alive(pool: Weak<SessionPool>, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        let Some(pool) = pool.upgrade() else { return };
+        pool.sessions.lock().unwrap().retain(|key, (session, _)| {
+            session.set_keepalive(true, interval.as_secs().max(1) as u32);
+            match session.keepalive_send() {
+                Ok(_) => true,
+                Err(e) => {
+                    tracing::debug!("Dropping the SSH session to {}: {}", key, e);
+                    false
+                }
+            }
+        });
+    }
+}
+
+/// Where a file called `name` goes when uploaded to `remote`: into it if
+/// it is a directory, else `remote` itself.
+fn remote_target(sftp: &Sftp, remote: &str, name: &str) -> String {
+    if remote.is_empty() {
+        return name.to_string();
+    }
+    let is_dir = remote.ends_with('/')
+        || sftp.stat(Path::new(remote)).map(|stat| stat.is_dir()).unwrap_or(false);
+    if is_dir {
+        format!("{}/{}", remote.trim_end_matches('/'), name)
+    } else {
+        remote.to_string()
+    }
+}
+
+/// Open a TCP connection to `host:port` within `timeout`.
+fn tcp_connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, RadarError> {
+    // IPv6 literals must be bracketed before the port is appended.
+    let addr = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let socket_addr = addr
+        .to_socket_addrs()
+        .map_err(|e| RadarError::SshConnection(host.to_string(), e.to_string()))?
+        .next()
+        .ok_or_else(|| {
+            RadarError::SshConnection(host.to_string(), "could not resolve address".into())
+        })?;
+
+    TcpStream::connect_timeout(&socket_addr, timeout)
+        .map_err(|e| RadarError::SshConnection(host.to_string(), e.to_string()))
+}
+
+/// Relay bytes between `local` and `channel` until either side closes. Both
+/// run non-blocking so one thread can serve both directions; `session` is
+/// kept alive for as long as the channel is used.
+fn pump(session: Session, mut channel: Channel, mut local: TcpStream) {
+    session.set_blocking(false);
+    if local.set_nonblocking(true).is_err() {
+        return;
+    }
+
+    let mut buf = [0u8; 16 * 1024];
+    let mut up = Vec::new(); // local → channel, not yet written
+    let mut down = Vec::new(); // channel → local, not yet written
+    loop {
+        let mut moved = false;
+
+        if up.is_empty() {
+            match local.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => up.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+        }
+        if !up.is_empty() {
+            match channel.write(&up) {
+                Ok(n) => {
+                    up.drain(..n);
+                    moved = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+        }
+
+        if down.is_empty() {
+            match channel.read(&mut buf) {
+                Ok(0) if channel.eof() => break,
+                Ok(n) => down.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+        }
+        if !down.is_empty() {
+            match local.write(&down) {
+                Ok(n) => {
+                    down.drain(..n);
+                    moved = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+        }
+
+        if !moved {
+            std::thread::sleep(PUMP_IDLE);
+        }
+    }
+    let _ = channel.close();
+}
+
+/// Whether an ssh-agent can be reached through `SSH_AUTH_SOCK`.
+pub fn agent_available() -> bool {
+    std::env::var_os("SSH_AUTH_SOCK").is_some_and(|sock| Path::new(&sock).exists())
+}
+
+/// Parse `ip link show` output into interface/MAC pairs.
+///
+/// Interface lines look like `2: eth0: <BROADCAST,...> mtu 1500 ...` (or
+/// `eth0@if5` for veth/VLAN links) and are followed by an indented
+/// `link/ether aa:bb:cc:dd:ee:ff brd ...` line, or carry it themselves with
+/// `ip -o`.
+fn parse_ip_link(output: &str) -> Vec<InterfaceMac> {
+    let iface_re = Regex::new(r"^\d+:\s+([^:@\s]+)").expect("interface regex is valid");
+    let mac_re = Regex::new(r"(?i)link/ether\s+([0-9a-f]{2}(?::[0-9a-f]{2}){5})")
+        .expect("MAC regex is valid");
+
+    let mut current = String::new();
+    let mut interfaces = Vec::new();
+
+    for line in output.lines() {
+        if let Some(cap) = iface_re.captures(line) {
+            current = cap[1].to_string();
+        }
+        if let Some(cap) = mac_re.captures(line) {
+            interfaces.push(InterfaceMac {
+                name: current.clone(),
+                mac: cap[1].to_lowercase(),
+            });
+        }
+    }
+
+    interfaces
+}
+
+/// Parse `ip -o addr` output, one `2: eth0    inet 192.168.1.20/24 brd
+/// ...` line per address, into interface/address pairs (MACs unset).
+fn parse_ip_addr(output: &str) -> Vec<InterfaceAddress> {
+    let addr_re = Regex::new(r"^\d+:\s+([^:@\s]+)\S*\s+inet6?\s+(\S+)")
+        .expect("address regex is valid");
+    output
+        .lines()
+        .filter_map(|line| addr_re.captures(line))
+        .map(|cap| InterfaceAddress {
+            name: cap[1].to_string(),
+            address: cap[2].to_string(),
+            mac: None,
+        })
+        .collect()
+}
+
+/// The `PRETTY_NAME` of an `/etc/os-release` file, unquoted.
+fn parse_os_release(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|value| value.trim().trim_matches(['"', '\'']).to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// The first field of `/proc/uptime`: seconds since boot, with decimals.
+fn parse_proc_uptime(output: &str) -> Option<Duration> {
+    let secs: f64 = output.split_whitespace().next()?.parse().ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Parse `ifconfig -a` output, in either net-tools style: `eth0: flags=…`
+/// followed by `ether aa:bb:…`, or the older (and BusyBox) `eth0  Link
+/// encap:Ethernet  HWaddr AA:BB:…` on one line. BSD's `ether` and Solaris'
+/// `lladdr` lines are understood too.
+fn parse_ifconfig(output: &str) -> Vec<InterfaceMac> {
+    let mac_re = Regex::new(r"(?i)\b(?:ether|hwaddr|lladdr)\s+([0-9a-f]{2}(?::[0-9a-f]{2}){5})")
+        .expect("MAC regex is valid");
+
+    let mut current = String::new();
+    let mut interfaces = Vec::new();
+
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            if let Some(name) = line.split([':', ' ', '\t']).next() {
+                current = name.to_string();
+            }
+        }
+        if let Some(cap) = mac_re.captures(line) {
+            interfaces.push(InterfaceMac {
+                name: current.clone(),
+                mac: cap[1].to_lowercase(),
+            });
+        }
+    }
+
+    interfaces
+}
+
+/// Parse `ipconfig /all` output: an unindented `… adapter <name>:` line
+/// (`…-Adapter <name>:` in German) per adapter, then an indented `Physical
+/// Address. . . : AA-BB-…` (the label is localized, so any line ending in a
+/// six-byte dashed address counts). Tunnel adapters' eight-byte addresses
+/// and DHCPv6 DUIDs are skipped.
+fn parse_ipconfig(output: &str) -> Vec<InterfaceMac> {
+    let mac_re = Regex::new(r"(?i):\s*([0-9a-f]{2}(?:-[0-9a-f]{2}){5})\s*$")
+        .expect("MAC regex is valid");
+
+    let mut current = String::new();
+    let mut interfaces = Vec::new();
+
+    for line in output.lines() {
+        let line = line.trim_end();
+        if !line.starts_with(char::is_whitespace) {
+            if let Some(header) = line.strip_suffix(':') {
+                let header = header.trim_end();
+                current = match header.to_ascii_lowercase().find("adapter ") {
+                    Some(at) => header[at + "adapter ".len()..].to_string(),
+                    None => header.to_string(),
+                };
+            }
+        } else if let Some(cap) = mac_re.captures(line) {
+            interfaces.extend(normalize_mac(&cap[1]).map(|mac| InterfaceMac {
+                name: current.clone(),
+                mac,
+            }));
+        }
+    }
+
+    interfaces
+}
+
+/// Parse `Get-NetAdapter | Format-List Name,MacAddress` output: `Name :
+/// Ethernet` and `MacAddress : 00-15-5D-…` lines, one block per adapter.
+fn parse_format_list(output: &str) -> Vec<InterfaceMac> {
+    let mut current = String::new();
+    let mut interfaces = Vec::new();
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        match key.trim() {
+            "Name" => current = value.trim().to_string(),
+            "MacAddress" => interfaces.extend(normalize_mac(value.trim()).map(|mac| {
+                InterfaceMac {
+                    name: current.clone(),
+                    mac,
+                }
+            })),
+            _ => {}
+        }
+    }
+
+    interfaces
+}
+
+/// Parse `grep -H . /sys/class/net/*/address` output
+/// (`/sys/class/net/eth0/address:aa:bb:…`). Links without a hardware
+/// address, like `lo`, report all zeros and are skipped.
+fn parse_sys_class_net(output: &str) -> Vec<InterfaceMac> {
+    let re = Regex::new(r"(?i)^/sys/class/net/([^/]+)/address:([0-9a-f]{2}(?::[0-9a-f]{2}){5})$")
+        .expect("sysfs regex is valid");
+
+    output
+        .lines()
+        .filter_map(|line| re.captures(line.trim_end()))
+        .filter(|cap| &cap[2] != "00:00:00:00:00:00")
+        .map(|cap| InterfaceMac {
+            name: cap[1].to_string(),
+            mac: cap[2].to_lowercase(),
+        })
+        .collect()
+}
+
+/// Parse `ip neigh` (`10.8.0.42 dev eth0 lladdr aa:bb:.. REACHABLE`) or
+/// `arp -an` (`? (10.8.0.42) at aa:bb:.. [ether] on eth0`) output.
+/// Entries without a resolved MAC (INCOMPLETE/FAILED) are skipped.
+pub(crate) fn parse_neighbors(output: &str) -> Vec<Neighbor> {
+    let re = Regex::new(
+        r"(?i)^\(?([0-9a-f.:]+)\)?\s.*?\b(?:lladdr|at)\s+([0-9a-f]{2}(?::[0-9a-f]{2}){5})",
+    )
+    .expect("neighbor regex is valid");
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start_matches("? ");
+            re.captures(line).map(|cap| Neighbor {
+                ip: cap[1].to_string(),
+                mac: cap[2].to_lowercase(),
+                hostname: None,
+            })
+        })
+        .collect()
+}
+
+/// Parse DHCP lease files: dnsmasq's (`1718000000 aa:bb:.. 10.8.0.42 name
+/// *`, also OpenWrt's `/tmp/dhcp.leases`) and ISC dhcpd's `lease 10.8.0.42
+/// { … }` blocks, where the last block of an IP wins and only active ones
+/// count. IPv6 (DUID) leases are skipped.
+fn parse_leases(output: &str) -> Vec<Neighbor> {
+    let mut leases: Vec<Neighbor> = Vec::new();
+    let mut lines = output.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(ip) = line.strip_prefix("lease ").and_then(|l| l.strip_suffix('{')) {
+            let ip = ip.trim().to_string();
+            let (mut mac, mut hostname, mut active) = (None, None, true);
+            for line in lines.by_ref().take_while(|&line| line != "}") {
+                let line = line.trim_end_matches(';');
+                if let Some(hw) = line.strip_prefix("hardware ethernet ") {
+                    mac = normalize_mac(hw.trim());
+                } else if let Some(name) = line.strip_prefix("client-hostname ") {
+                    hostname = Some(name.trim_matches('"').to_string());
+                } else if let Some(state) = line.strip_prefix("binding state ") {
+                    active = state == "active";
+                }
+            }
+            match mac {
+                Some(mac) if active && ip.parse::<IpAddr>().is_ok() => {
+                    add_lease(&mut leases, Neighbor { ip, mac, hostname })
+                }
+                _ => leases.retain(|l| l.ip != ip),
+            }
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let [_, mac, ip, name, ..] = fields.as_slice() {
+            if let (Some(mac), Ok(_)) = (normalize_mac(mac), ip.parse::<IpAddr>()) {
+                let hostname = (*name != "*").then(|| name.to_string());
+                let lease = Neighbor {
+                    ip: ip.to_string(),
+                    mac,
+                    hostname,
+                };
+                add_lease(&mut leases, lease);
+            }
+        }
+    }
+    leases
+}
+
+/// Add `lease` to `leases`, replacing an older one for the same IP.
+fn add_lease(leases: &mut Vec<Neighbor>, lease: Neighbor) {
+    match leases.iter_mut().find(|l| l.ip == lease.ip) {
+        Some(known) => *known = lease,
+        None => leases.push(lease),
+    }
+}
+
+/// libssh2 could not read the key or lacks support for its format.
+const LIBSSH2_ERROR_FILE: i32 = -16;
+/// libssh2 was built without the requested method.
+const LIBSSH2_ERROR_METHOD_NOT_SUPPORTED: i32 = -33;
+
+/// Authenticate with a private key held in memory.
+///
+/// The key is first handed to libssh2 directly; only if that path is
+/// unsupported (older libssh2, unusual key format, no OpenSSL backend) is
+/// it written to a temporary file. If both fail, the error describes both.
+fn authenticate_key_memory(
+    session: &Session,
+    user: &str,
+    key_data: &str,
+    passphrase: Option<&str>,
+) -> Result<(), RadarError> {
+    // Normalize line endings (Windows CRLF → Unix LF) and ensure
+    // trailing newline — some key parsers are strict about this.
+    let clean_key = key_data.replace("\r\n", "\n");
+    let clean_key = if clean_key.ends_with('\n') {
+        clean_key
+    } else {
+        format!("{}\n", clean_key)
+    };
+
+    #[cfg(unix)]
+    let memory = || session.userauth_pubkey_memory(user, None, &clean_key, passphrase);
+    #[cfg(not(unix))]
+    let memory = || {
+        Err(ssh2::Error::new(
+            ssh2::ErrorCode::Session(LIBSSH2_ERROR_METHOD_NOT_SUPPORTED),
+            "in-memory keys are not supported on this platform",
+        ))
+    };
+    let temp_dir = std::env::temp_dir();
+    chain_key_auth(memory, || {
+        authenticate_key_temp_file(session, user, &clean_key, passphrase, &temp_dir)
+    })
+}
+
+/// The fallback order of [`authenticate_key_memory`]: `memory` first, then
+/// `file` only if `memory` reports the path unsupported.
+fn chain_key_auth(
+    memory: impl FnOnce() -> Result<(), ssh2::Error>,
+    file: impl FnOnce() -> Result<(), String>,
+) -> Result<(), RadarError> {
+    // ── 1. In-memory auth ──────────────────────────────────────────────
+    let memory_err = match memory() {
+        Ok(()) => return Ok(()),
+        Err(e) if is_unsupported(&e) => e.to_string(),
+        Err(e) => return Err(RadarError::PrivateKey(e.to_string())),
+    };
+
+    tracing::debug!("In-memory key auth unsupported ({}), using temp file", memory_err);
+
+    // ── 2. Temp-file fallback ──────────────────────────────────────────
+    file().map_err(|file_err| {
+        RadarError::PrivateKey(format!(
+            "in-memory auth failed ({}); temp-file fallback failed ({})",
+            memory_err, file_err
+        ))
+    })
+}
+
+/// Write the key to a temp file in `dir` and use file-based auth.
+fn authenticate_key_temp_file(
+    session: &Session,
+    user: &str,
+    clean_key: &str,
+    passphrase: Option<&str>,
+    dir: &Path,
+) -> Result<(), String> {
+    let mut tmp = NamedTempFile::new_in(dir).map_err(|e| format!("temp file: {}", e))?;
+    tmp.write_all(clean_key.as_bytes())
+        .map_err(|e| format!("write temp: {}", e))?;
+    tmp.flush().map_err(|e| format!("flush temp: {}", e))?;
+
+    session
+        .userauth_pubkey_file(user, None, tmp.path(), passphrase)
+        .map_err(|e| e.to_string())
+}
+
+/// Whether a libssh2 error means "this auth path is unavailable" rather
+/// than "the server rejected the key".
+fn is_unsupported(err: &ssh2::Error) -> bool {
+    matches!(
+        err.code(),
+        ssh2::ErrorCode::Session(LIBSSH2_ERROR_FILE | LIBSSH2_ERROR_METHOD_NOT_SUPPORTED)
+    )
+}
+
+/// Parse a SHA256 host-key fingerprint, either OpenSSH style
+/// (`SHA256:<base64>`) or as 64 hex digits (colons optional).
+pub fn parse_fingerprint(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    let s = s.strip_prefix("SHA256:").unwrap_or(s);
+
+    let hex: String = s.chars().filter(|c| *c != ':').collect();
+    let bytes = if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..64)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()?
+    } else {
+        STANDARD_NO_PAD.decode(s.trim_end_matches('=')).ok()?
+    };
+
+    (bytes.len() == 32).then_some(bytes)
+}
+
+/// Render a SHA256 digest the way OpenSSH prints it.
+fn format_fingerprint(hash: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(hash))
+}
+
+/// Fail with [`RadarError::HostKeyMismatch`] unless the server's host key
+/// hashes to `expected`.
+fn verify_pinned_host_key(session: &Session, ip: &str, expected: &[u8]) -> Result<(), RadarError> {
+    let actual = session.host_key_hash(HashType::Sha256).ok_or_else(|| {
+        RadarError::HostKeyMismatch(ip.to_string(), "server sent no host key".into())
+    })?;
+
+    if actual != expected {
+        return Err(RadarError::HostKeyMismatch(
+            ip.to_string(),
+            format!(
+                "expected {}, got {}",
+                format_fingerprint(expected),
+                format_fingerprint(actual)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Serializes known_hosts checks and TOFU appends across concurrent probes,
+/// so two probes of the same new host do not both record it. Other
+/// processes (such as `ssh`) only ever see whole lines appended.
+static KNOWN_HOSTS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Check the server's host key against the known_hosts file of `policy`,
+/// recording unknown hosts in TOFU mode.
+fn verify_known_host(
+    session: &Session,
+    ip: &str,
+    port: u16,
+    policy: &HostKeyCheck,
+) -> Result<(), RadarError> {
+    let (path, tofu) = match policy {
+        HostKeyCheck::Disabled => return Ok(()),
+        HostKeyCheck::Tofu { path } => (path, true),
+        HostKeyCheck::Strict { path } => (path, false),
+    };
+    let mismatch = |reason: String| RadarError::HostKeyMismatch(ip.to_string(), reason);
+
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| mismatch("server sent no host key".into()))?;
+    let fingerprint = session
+        .host_key_hash(HashType::Sha256)
+        .map(format_fingerprint)
+        .unwrap_or_default();
+
+    let _guard = KNOWN_HOSTS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut known_hosts = session
+        .known_hosts()
+        .map_err(|e| RadarError::SshConnection(ip.to_string(), e.to_string()))?;
+    let revoked = match read_known_hosts(&mut known_hosts, path) {
+        Ok(revoked) => revoked,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(mismatch(format!("cannot read {}: {}", path.display(), e))),
+    };
+    if revoked.iter().any(|k| k.as_slice() == key) {
+        return Err(mismatch(format!("key {} is revoked in {}", fingerprint, path.display())));
+    }
+
+    match known_hosts.check_port(ip, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(mismatch(format!(
+            "key {} differs from the one recorded in {}",
+            fingerprint,
+            path.display()
+        ))),
+        CheckResult::NotFound if tofu => {
+            let host = if port == 22 {
+                ip.to_string()
+            } else {
+                format!("[{}]:{}", ip, port)
+            };
+            record_host_key(path, &host, key)
+                .map_err(|e| mismatch(format!("cannot record key in {}: {}", path.display(), e)))?;
+            tracing::info!("Trusted new host key for {} ({})", ip, fingerprint);
+            Ok(())
+        }
+        CheckResult::NotFound => Err(mismatch(format!(
+            "unknown host (key {}) not in {}",
+            fingerprint,
+            path.display()
+        ))),
+        CheckResult::Failure => Err(mismatch("known_hosts check failed".into())),
+    }
+}
+
+/// Load the known_hosts file at `path` into `known_hosts` and return the
+/// keys of its `@revoked` lines. Lines libssh2 cannot parse, such as
+/// `@cert-authority` or newer key types, are skipped instead of failing
+/// the whole file.
+fn read_known_hosts(known_hosts: &mut KnownHosts, path: &Path) -> io::Result<Vec<Vec<u8>>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut revoked = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("@revoked") {
+            // `@revoked <hosts> <type> <key>`: refused for every host.
+            if let Some(key) = rest.split_whitespace().nth(2) {
+                revoked.extend(STANDARD.decode(key).ok());
+            }
+            continue;
+        }
+        if let Err(e) = known_hosts.read_str(line, KnownHostFileKind::OpenSSH) {
+            tracing::debug!("{}: skipping a line libssh2 cannot parse: {}", path.display(), e);
+        }
+    }
+    Ok(revoked)
+}
+
+/// Append a line for `host`'s `key` (an SSH wire-format public key) to the
+/// known_hosts file at `path`, creating it if missing. The rest of the file
+/// is never rewritten.
+fn record_host_key(path: &Path, host: &str, key: &[u8]) -> io::Result<()> {
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "malformed host key");
+    let len = key.get(..4).ok_or_else(malformed)?;
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    let key_type = key
+        .get(4..4 + len)
+        .and_then(|name| std::str::from_utf8(name).ok())
+        .ok_or_else(malformed)?;
+    let mut line = format!("{} {} {} added by radar-ip\n", host, key_type, STANDARD.encode(key));
+
+    let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+    // A last line without its newline would run into ours.
+    if file.seek(SeekFrom::End(-1)).is_ok() {
+        let mut last = [0];
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            line.insert(0, '\n');
+        }
+    }
+    file.write_all(line.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::time::Instant;
+
+    /// A config for 127.0.0.1:`port` with every stage limited to `timeout`.
+    fn local_config(port: u16, timeout: Duration) -> SshConfig {
+        SshConfig {
+            user: "root".into(),
+            port,
+            auth: AuthenticationMethod::Password("x".into()),
+            timeouts: SshTimeouts::uniform(timeout),
+            pinned_host_keys: HashMap::new(),
+            host_key_check: HostKeyCheck::Disabled,
+            interface_filter: None,
+            probe: None,
+            platform: Platform::Auto,
+            via: None,
+            details: false,
+            pool: None,
+        }
+    }
+
+    fn unsupported() -> ssh2::Error {
+        ssh2::Error::new(
+            ssh2::ErrorCode::Session(LIBSSH2_ERROR_METHOD_NOT_SUPPORTED),
+            "method not supported",
+        )
+    }
+
+    /// A temp-file auth that succeeds, noting that it ran.
+    fn file_ok(tried: &Cell<bool>) -> Result<(), String> {
+        tried.set(true);
+        Ok(())
+    }
+
+    #[test]
+    fn key_auth_tries_memory_first() {
+        let file_tried = Cell::new(false);
+        let result = chain_key_auth(|| Ok(()), || file_ok(&file_tried));
+        assert!(result.is_ok());
+        assert!(!file_tried.get());
+    }
+
+    #[test]
+    fn key_auth_uses_temp_file_only_when_memory_is_unsupported() {
+        let file_tried = Cell::new(false);
+        let result = chain_key_auth(|| Err(unsupported()), || file_ok(&file_tried));
+        assert!(result.is_ok());
+        assert!(file_tried.get());
+
+        let file_tried = Cell::new(false);
+        let rejected = || {
+            Err(ssh2::Error::new(ssh2::ErrorCode::Session(-18), "authentication failed"))
+        };
+        let result = chain_key_auth(rejected, || file_ok(&file_tried));
+        assert!(matches!(result, Err(RadarError::PrivateKey(_))));
+        assert!(!file_tried.get());
+    }
+
+    #[test]
+    fn key_auth_combines_both_failures() {
+        // A regular file stands in for a temp dir nobody can write into.
+        let not_a_dir = NamedTempFile::new().unwrap();
+        let session = Session::new().unwrap();
+        let result = chain_key_auth(
+            || Err(unsupported()),
+            || authenticate_key_temp_file(&session, "root", "key\n", None, not_a_dir.path()),
+        );
+        match result {
+            Err(RadarError::PrivateKey(msg)) => {
+                let memory = "in-memory auth failed ([Session(-33)] method not supported)";
+                assert!(msg.contains(memory), "{}", msg);
+                assert!(msg.contains("temp-file fallback failed (temp file:"), "{}", msg);
+            }
+            other => panic!("expected a combined PrivateKey error, got {:?}", other),
+        }
+    }
+
+    /// The port of a local server that sends a banner, then never answers
+    /// the key exchange.
+    fn stalled_host() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").unwrap();
+            std::thread::sleep(Duration::from_secs(30));
+        });
+        port
+    }
+
+    #[test]
+    fn probe_of_a_stalled_host_returns_within_its_timeout() {
+        let port = stalled_host();
+        let timeout = Duration::from_millis(500);
+        let config = local_config(port, timeout);
+        let started = Instant::now();
+        let result = config.fetch_macs("127.0.0.1");
+        let elapsed = started.elapsed();
+
+        assert!(matches!(result, Err(RadarError::SshConnection(..))), "{:?}", result);
+        // Only the handshake stage runs, so about one `timeout` passes.
+        assert!(elapsed < timeout * 2, "took {:?}", elapsed);
+    }
+
+    #[test]
+    fn aborted_probe_returns_at_once() {
+        let config = local_config(stalled_host(), Duration::from_secs(10));
+        let abort = ProbeAbort::default();
+        std::thread::spawn({
+            let abort = abort.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(200));
+                abort.abort();
+            }
+        });
+
+        let started = Instant::now();
+        let result = config.fetch_macs_abortable("127.0.0.1", &abort);
+        assert!(matches!(result, Err(RadarError::SshConnection(..))), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+
+        // An attempt after the abort fails without waiting either.
+        let started = Instant::now();
+        assert!(config.fetch_macs_abortable("127.0.0.1", &abort).is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    /// An `ssh-ed25519` public key in SSH wire format.
+    fn ed25519_key(fill: u8) -> Vec<u8> {
+        let mut key = vec![0, 0, 0, 11];
+        key.extend(b"ssh-ed25519");
+        key.extend([0, 0, 0, 32]);
+        key.extend([fill; 32]);
+        key
+    }
+
+    #[test]
+    fn tofu_appends_to_known_hosts_without_rewriting_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("known_hosts");
+        let existing = "@cert-authority *.lab ssh-ed25519 AAAAC3NzaC1lZDI1NTE5\n\
+                        10.0.0.1 ssh-foo AAAAB3NzaC1mb28= newer key type";
+        std::fs::write(&path, existing).unwrap();
+
+        record_host_key(&path, "[10.0.0.5]:2222", &ed25519_key(7)).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let (before, added) = text.split_at(existing.len() + 1);
+        assert_eq!(before, format!("{}\n", existing));
+        let key = STANDARD.encode(ed25519_key(7));
+        assert_eq!(added, format!("[10.0.0.5]:2222 ssh-ed25519 {} added by radar-ip\n", key));
+
+        let missing = dir.path().join("new_known_hosts");
+        record_host_key(&missing, "10.0.0.6", &ed25519_key(8)).unwrap();
+        assert!(std::fs::read_to_string(&missing).unwrap().starts_with("10.0.0.6 ssh-ed25519 "));
+
+        assert!(record_host_key(&path, "10.0.0.7", &[0, 0, 0, 99, b's']).is_err());
+    }
+
+    #[test]
+    fn known_hosts_lines_libssh2_cannot_parse_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("known_hosts");
+        let revoked = STANDARD.encode(ed25519_key(9));
+        std::fs::write(
+            &path,
+            format!(
+                "# comment\n\
+                 @cert-authority *.lab ssh-ed25519 AAAAC3NzaC1lZDI1NTE5\n\
+                 @revoked * ssh-ed25519 {}\n\
+                 10.0.0.1 ssh-foo AAAAB3NzaC1mb28=\n",
+                revoked
+            ),
+        )
+        .unwrap();
+        record_host_key(&path, "[10.0.0.5]:2222", &ed25519_key(7)).unwrap();
+
+        let session = Session::new().unwrap();
+        let mut known_hosts = session.known_hosts().unwrap();
+        let revoked = read_known_hosts(&mut known_hosts, &path).unwrap();
+        assert_eq!(revoked, vec![ed25519_key(9)]);
+        let check = |port, fill| known_hosts.check_port("10.0.0.5", port, &ed25519_key(fill));
+        assert!(matches!(check(2222, 7), CheckResult::Match));
+        assert!(matches!(check(2222, 8), CheckResult::Mismatch));
+        assert!(matches!(check(22, 7), CheckResult::NotFound));
+    }
+
+    fn pairs(interfaces: &[InterfaceMac]) -> Vec<(&str, &str)> {
+        interfaces.iter().map(|i| (i.name.as_str(), i.mac.as_str())).collect()
+    }
+
+    #[test]
+    fn ifconfig_of_current_net_tools() {
+        let output = "\
+eth0: flags=4163<UP,BROADCAST,RUNNING,MULTICAST>  mtu 1500
+        inet 192.168.1.20  netmask 255.255.255.0  broadcast 192.168.1.255
+        inet6 fe80::ba27:ebff:fe12:3456  prefixlen 64  scopeid 0x20<link>
+        ether b8:27:eb:12:34:56  txqueuelen 1000  (Ethernet)
+        RX packets 1234  bytes 567890 (567.8 KB)
+        TX errors 0  dropped 0 overruns 0  carrier 0  collisions 0
+
+lo: flags=73<UP,LOOPBACK,RUNNING>  mtu 65536
+        inet 127.0.0.1  netmask 255.0.0.0
+        loop  txqueuelen 1000  (Local Loopback)
+
+wlan0: flags=4099<UP,BROADCAST,MULTICAST>  mtu 1500
+        ether dc:a6:32:ab:cd:ef  txqueuelen 1000  (Ethernet)
+";
+        assert_eq!(
+            pairs(&parse_ifconfig(output)),
+            [("eth0", "b8:27:eb:12:34:56"), ("wlan0", "dc:a6:32:ab:cd:ef")]
+        );
+    }
+
+    #[test]
+    fn ifconfig_of_old_net_tools_and_busybox() {
+        let output = "\
+br-lan    Link encap:Ethernet  HWaddr 9C:5C:8E:01:02:03  
+          inet addr:192.168.1.1  Bcast:192.168.1.255  Mask:255.255.255.0
+          UP BROADCAST RUNNING MULTICAST  MTU:1500  Metric:1
+
+eth0.2    Link encap:Ethernet  HWaddr 9C:5C:8E:01:02:04  
+          UP BROADCAST RUNNING MULTICAST  MTU:1500  Metric:1
+
+lo        Link encap:Local Loopback  
+          inet addr:127.0.0.1  Mask:255.0.0.0
+";
+        assert_eq!(
+            pairs(&parse_ifconfig(output)),
+            [("br-lan", "9c:5c:8e:01:02:03"), ("eth0.2", "9c:5c:8e:01:02:04")]
+        );
+    }
+
+    #[test]
+    fn ifconfig_of_the_bsds() {
+        let freebsd = "\
+em0: flags=8843<UP,BROADCAST,RUNNING,SIMPLEX,MULTICAST> metric 0 mtu 1500
+\toptions=81009b<RXCSUM,TXCSUM,VLAN_MTU,VLAN_HWTAGGING,VLAN_HWCSUM,VLAN_HWFILTER>
+\tether 08:00:27:aa:bb:cc
+\tinet 10.0.2.15 netmask 0xffffff00 broadcast 10.0.2.255
+\tmedia: Ethernet autoselect (1000baseT <full-duplex>)
+\tstatus: active
+lo0: flags=8049<UP,LOOPBACK,RUNNING,MULTICAST> metric 0 mtu 16384
+\tinet 127.0.0.1 netmask 0xff000000
+";
+        assert_eq!(pairs(&parse_ifconfig(freebsd)), [("em0", "08:00:27:aa:bb:cc")]);
+
+        let openbsd = "\
+lo0: flags=8049<UP,LOOPBACK,RUNNING,MULTICAST> mtu 32768
+\tindex 3 priority 0 llprio 3
+\tinet 127.0.0.1 netmask 0xff000000
+vio0: flags=808843<UP,BROADCAST,RUNNING,SIMPLEX,MULTICAST,AUTOCONF4> mtu 1500
+\tlladdr 52:54:00:12:34:56
+\tindex 1 priority 0 llprio 3
+\tgroups: egress
+\tinet 10.0.2.15 netmask 0xffffff00 broadcast 10.0.2.255
+";
+        assert_eq!(pairs(&parse_ifconfig(openbsd)), [("vio0", "52:54:00:12:34:56")]);
+    }
+
+    #[test]
+    fn sys_class_net_skips_links_without_an_address() {
+        let output = "\
+/sys/class/net/eth0/address:b8:27:eb:12:34:56
+/sys/class/net/lo/address:00:00:00:00:00:00
+/sys/class/net/wlan0/address:DC:A6:32:AB:CD:EF
+/sys/class/net/wwan0/address:not-a-mac
+";
+        assert_eq!(
+            pairs(&parse_sys_class_net(output)),
+            [("eth0", "b8:27:eb:12:34:56"), ("wlan0", "dc:a6:32:ab:cd:ef")]
+        );
+    }
+
+    #[test]
+    fn ipconfig_skips_tunnel_adapters_and_duids() {
+        let output = "\r
+Windows IP Configuration\r
+\r
+   Host Name . . . . . . . . . . . . : DESKTOP-42\r
+   Node Type . . . . . . . . . . . . : Hybrid\r
+\r
+Ethernet adapter Ethernet:\r
+\r
+   Connection-specific DNS Suffix  . : lan\r
+   Description . . . . . . . . . . . : Intel(R) Ethernet Connection (7) I219-V\r
+   Physical Address. . . . . . . . . : 00-15-5D-01-02-03\r
+   DHCP Enabled. . . . . . . . . . . : Yes\r
+   Link-local IPv6 Address . . . . . : fe80::1c2b:3a4d:5e6f:7081%12(Preferred) \r
+   Lease Obtained. . . . . . . . . . : Wednesday, 14 October 2026 09:12:41\r
+   DHCPv6 Client DUID. . . . . . . . : 00-01-00-01-2A-3B-4C-5D-00-15-5D-01-02-03\r
+\r
+Wireless LAN adapter Wi-Fi:\r
+\r
+   Media State . . . . . . . . . . . : Media disconnected\r
+   Physical Address. . . . . . . . . : 3C-A9-F4-AA-BB-CC\r
+\r
+Tunnel adapter isatap.lan:\r
+\r
+   Media State . . . . . . . . . . . : Media disconnected\r
+   Physical Address. . . . . . . . . : 00-00-00-00-00-00-00-E0\r
+";
+        assert_eq!(
+            pairs(&parse_ipconfig(output)),
+            [("Ethernet", "00:15:5d:01:02:03"), ("Wi-Fi", "3c:a9:f4:aa:bb:cc")]
+        );
+    }
+
+    #[test]
+    fn ipconfig_with_localized_labels() {
+        let german = "\
+Windows-IP-Konfiguration
+
+   Hostname  . . . . . . . . . . . . : BUERO-PC
+
+Ethernet-Adapter Ethernet 2:
+
+   Verbindungsspezifisches DNS-Suffix:
+   Beschreibung. . . . . . . . . . . : Realtek PCIe GbE Family Controller
+   Physische Adresse . . . . . . . . : 00-E0-4C-68-01-02
+   DHCP aktiviert. . . . . . . . . . : Ja
+
+Tunneladapter Teredo Tunneling Pseudo-Interface:
+
+   Physische Adresse . . . . . . . . : 00-00-00-00-00-00-00-E0
+";
+        assert_eq!(pairs(&parse_ipconfig(german)), [("Ethernet 2", "00:e0:4c:68:01:02")]);
+
+        let french = "\
+Carte Ethernet Ethernet :
+
+   Adresse physique . . . . . . . . . . . : 00-15-5D-0A-0B-0C
+";
+        assert_eq!(
+            pairs(&parse_ipconfig(french)),
+            [("Carte Ethernet Ethernet", "00:15:5d:0a:0b:0c")]
+        );
+    }
+
+    #[test]
+    fn format_list_skips_adapters_without_a_mac() {
+        let output = "\r
+\r
+Name       : Ethernet\r
+MacAddress : 00-15-5D-01-02-03\r
+\r
+Name       : vEthernet (Default Switch)\r
+MacAddress : 00-15-5D-A0-B1-C2\r
+\r
+Name       : Bluetooth Network Connection\r
+MacAddress : \r
+\r
+";
+        assert_eq!(
+            pairs(&parse_format_list(output)),
+            [("Ethernet", "00:15:5d:01:02:03"), ("vEthernet (Default Switch)", "00:15:5d:a0:b1:c2")]
+        );
+    }
+
+    fn leases(output: &str) -> Vec<(String, String, Option<String>)> {
+        parse_leases(output).into_iter().map(|l| (l.ip, l.mac, l.hostname)).collect()
+    }
+
+    #[test]
+    fn dnsmasq_leases_skip_duids() {
+        let output = "\
+1760433600 b8:27:eb:12:34:56 10.8.0.42 raspberrypi 01:b8:27:eb:12:34:56
+1760433700 DC:A6:32:AB:CD:EF 10.8.0.43 * *
+duid 00:01:00:01:2a:3b:4c:5d:00:15:5d:01:02:03
+1760433800 218960451 fd00::43 sensor-43 00:01:00:01:2a:3b:4c:5d:b8:27:eb:00:00:43
+";
+        assert_eq!(
+            leases(output),
+            [
+                ("10.8.0.42".into(), "b8:27:eb:12:34:56".into(), Some("raspberrypi".into())),
+                ("10.8.0.43".into(), "dc:a6:32:ab:cd:ef".into(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn isc_dhcpd_last_block_of_an_ip_wins() {
+        let output = r#"
+# The format of this file is documented in the dhcpd.leases(5) manual page.
+lease 10.8.0.50 {
+  starts 3 2026/10/14 09:00:00;
+  ends 3 2026/10/14 21:00:00;
+  binding state active;
+  next binding state free;
+  hardware ethernet b8:27:eb:00:00:50;
+  client-hostname "sensor-50";
+}
+lease 10.8.0.50 {
+  starts 3 2026/10/14 10:00:00;
+  binding state active;
+  next binding state free;
+  hardware ethernet b8:27:eb:00:00:51;
+}
+"#;
+        assert_eq!(leases(output), [("10.8.0.50".into(), "b8:27:eb:00:00:51".into(), None)]);
+    }
+
+    #[test]
+    fn isc_dhcpd_leases_that_are_not_active_remove_the_ip() {
+        let output = "\
+lease 10.8.0.51 {
+  binding state active;
+  hardware ethernet b8:27:eb:00:00:52;
+  client-hostname \"sensor-51\";
+}
+lease 10.8.0.52 {
+  binding state active;
+  hardware ethernet b8:27:eb:00:00:53;
+}
+lease 10.8.0.51 {
+  binding state free;
+  hardware ethernet b8:27:eb:00:00:52;
+}
+lease 10.8.0.53 {
+  binding state backup;
+}
+";
+        assert_eq!(leases(output), [("10.8.0.52".into(), "b8:27:eb:00:00:53".into(), None)]);
+    }
+
+    fn jump(user: Option<&str>, host: &str, port: u16) -> JumpHost {
+        JumpHost {
+            user: user.map(str::to_string),
+            host: host.into(),
+            port,
+        }
+    }
+
+    #[test]
+    fn jump_hosts_with_user_and_port() {
+        let parse = |s| JumpHost::parse(s).unwrap();
+        assert_eq!(parse("bastion.lab"), jump(None, "bastion.lab", 22));
+        assert_eq!(parse("admin@bastion.lab:2222"), jump(Some("admin"), "bastion.lab", 2222));
+        assert_eq!(parse("10.0.0.1:2200"), jump(None, "10.0.0.1", 2200));
+        // Only the last `@` ends the user.
+        assert_eq!(parse("ops@corp@10.0.0.1"), jump(Some("ops@corp"), "10.0.0.1", 22));
+    }
+
+    #[test]
+    fn jump_hosts_on_ipv6() {
+        let parse = |s| JumpHost::parse(s).unwrap();
+        assert_eq!(parse("fd00::1"), jump(None, "fd00::1", 22));
+        assert_eq!(parse("[fd00::1]"), jump(None, "fd00::1", 22));
+        assert_eq!(parse("admin@[fd00::1]:2222"), jump(Some("admin"), "fd00::1", 2222));
+        // Display brackets IPv6 hosts again, so the two round-trip.
+        for s in ["admin@[fd00::1]:2222", "[fd00::1]", "admin@bastion.lab:2222", "bastion.lab"] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn malformed_jump_hosts_are_rejected() {
+        let err = |s| JumpHost::parse(s).unwrap_err();
+        assert_eq!(err("@bastion"), "empty user in '@bastion'");
+        assert_eq!(err("[fd00::1:2222"), "unclosed '[' in '[fd00::1:2222'");
+        assert_eq!(err("[fd00::1]2222"), "unexpected '2222' after ']' in '[fd00::1]2222'");
+        assert_eq!(err("admin@"), "missing host in 'admin@'");
+        assert_eq!(err(":2222"), "missing host in ':2222'");
+        assert_eq!(err("bastion:ssh"), "invalid port 'ssh'");
+        assert_eq!(err("[fd00::1]:65536"), "invalid port '65536'");
+    }
+}
@@ -0,0 +1,178 @@
+use radar_ip_core::oui;
+use radar_ip_core::report::ScanMatch;
+use radar_ip_core::ssh_client::DeviceIdentity;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The group every host is in unless `--profile` names one.
+pub const DEFAULT_GROUP: &str = "radar_ip";
+
+/// What every host of the inventory shares, written as vars of its group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vars {
+    /// The group: the `--profile` name, or [`DEFAULT_GROUP`].
+    pub group: String,
+    pub user: String,
+    /// The `--key`, made absolute.
+    pub key: Option<PathBuf>,
+}
+
+impl Default for Vars {
+    fn default() -> Self {
+        Self {
+            group: DEFAULT_GROUP.to_string(),
+            user: "root".to_string(),
+            key: None,
+        }
+    }
+}
+
+/// One inventory host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    pub ip: String,
+    pub mac: String,
+    pub hostname: Option<String>,
+}
+
+impl Host {
+    pub fn from_match(found: &ScanMatch) -> Self {
+        Self {
+            ip: found.ip.clone(),
+            mac: found.mac.clone(),
+            hostname: found.hostname.clone(),
+        }
+    }
+
+    /// A probed host, by its first MAC; `None` if it reported none.
+    pub fn from_identity(host: &DeviceIdentity) -> Option<Self> {
+        let mac = host.interfaces.first().map(|i| &i.mac).or(host.mac_list.first())?;
+        Some(Self {
+            ip: host.ip.clone(),
+            mac: mac.clone(),
+            hostname: host.hostname.clone(),
+        })
+    }
+}
+
+/// `hosts` as an INI inventory: each one in the group of `vars`, with
+/// `ansible_host` and `mac`, and in a group named after its vendor.
+pub fn render_ini(vars: &Vars, hosts: &[Host]) -> String {
+    let group = group_name(&vars.group);
+    let named = names(hosts);
+    let mut out = format!("[{}]\n", group);
+    for (name, host) in &named {
+        out.push_str(&format!("{} ansible_host={} mac={}\n", name, host.ip, host.mac));
+    }
+    out.push_str(&format!("\n[{}:vars]\nansible_user={}\n", group, ini_value(&vars.user)));
+    if let Some(key) = &vars.key {
+        let key = ini_value(&key.display().to_string());
+        out.push_str(&format!("ansible_ssh_private_key_file={}\n", key));
+    }
+    for (vendor, members) in vendor_groups(&named) {
+        out.push_str(&format!("\n[{}]\n", vendor));
+        for name in members {
+            out.push_str(&format!("{}\n", name));
+        }
+    }
+    out
+}
+
+/// `hosts` as a YAML inventory with the same contents as [`render_ini`].
+pub fn render_yaml(vars: &Vars, hosts: &[Host]) -> String {
+    let group = group_name(&vars.group);
+    let named = names(hosts);
+    let mut out = format!("all:\n  children:\n    {}:\n", group);
+    if named.is_empty() {
+        out.push_str("      hosts: {}\n");
+    } else {
+        out.push_str("      hosts:\n");
+    }
+    for (name, host) in &named {
+        out.push_str(&format!("        {}:\n", yaml_string(name)));
+        out.push_str(&format!("          ansible_host: {}\n", yaml_string(&host.ip)));
+        out.push_str(&format!("          mac: {}\n", yaml_string(&host.mac)));
+    }
+    out.push_str(&format!("      vars:\n        ansible_user: {}\n", yaml_string(&vars.user)));
+    if let Some(key) = &vars.key {
+        let key = yaml_string(&key.display().to_string());
+        out.push_str(&format!("        ansible_ssh_private_key_file: {}\n", key));
+    }
+    for (vendor, members) in vendor_groups(&named) {
+        out.push_str(&format!("    {}:\n      hosts:\n", vendor));
+        for name in members {
+            out.push_str(&format!("        {}: {{}}\n", yaml_string(name)));
+        }
+    }
+    out
+}
+
+/// A unique inventory name for each host: its hostname, else `device_`
+/// and its MAC; a name already taken gets the IP appended.
+fn names(hosts: &[Host]) -> Vec<(String, &Host)> {
+    let mut named: Vec<(String, &Host)> = Vec::new();
+    for host in hosts {
+        let base = match &host.hostname {
+            Some(hostname) => sanitize(hostname),
+            None => format!("device_{}", host.mac.replace(':', "")),
+        };
+        let mut name = base.clone();
+        if named.iter().any(|(taken, _)| *taken == name) {
+            name = format!("{}_{}", base, sanitize(&host.ip));
+        }
+        named.push((name, host));
+    }
+    named
+}
+
+/// Vendor group → the names of its hosts, for hosts of a known vendor.
+fn vendor_groups<'a>(named: &'a [(String, &Host)]) -> BTreeMap<String, Vec<&'a str>> {
+    let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for (name, host) in named {
+        if let Some(vendor) = oui::vendor(&host.mac) {
+            groups.entry(group_name(vendor)).or_default().push(name);
+        }
+    }
+    groups
+}
+
+/// `name` as a valid group name: lowercase letters, digits and `_`, not
+/// starting with a digit (e.g. `Raspberry Pi` → `raspberry_pi`).
+fn group_name(name: &str) -> String {
+    let mut group = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            group.push(c.to_ascii_lowercase());
+        } else if !group.ends_with('_') {
+            group.push('_');
+        }
+    }
+    let group = group.trim_matches('_').to_string();
+    match group.chars().next() {
+        None => DEFAULT_GROUP.to_string(),
+        Some(c) if c.is_ascii_digit() => format!("_{}", group),
+        Some(_) => group,
+    }
+}
+
+/// `name` with every character an inventory host name cannot hold
+/// replaced by `_`.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '.') { c } else { '_' })
+        .collect()
+}
+
+/// An INI value, quoted when it holds spaces or quotes.
+fn ini_value(value: &str) -> String {
+    if value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A double-quoted YAML string.
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,217 @@
+use radar_ip_core::errors::RadarError;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::warn;
+
+/// One line of the audit log, describing a single scan invocation.
+///
+/// Never contains key material or passwords.
+#[derive(Debug, Serialize)]
+pub struct AuditRecord {
+    /// RFC 3339 timestamp of when the scan finished.
+    pub timestamp: String,
+    /// Local account that ran the tool.
+    pub operator: String,
+    /// SSH username used against the scanned hosts.
+    pub ssh_user: String,
+    /// MAC address that was searched for; `None` for an inventory.
+    pub target_mac: Option<String>,
+    /// IP range that was scanned.
+    pub range: String,
+    /// `"found"`, `"not_found"` or `"error"`.
+    pub outcome: &'static str,
+    /// IP address of the matching host, if any.
+    pub found_ip: Option<String>,
+}
+
+impl AuditRecord {
+    /// Build a record from the scan inputs and its result.
+    ///
+    /// `result` is `Ok(Some(ip))` for a located host, or `Ok(None)` when the
+    /// MAC was found by a backend that does not yield an IP (e.g. SNMP).
+    pub fn new(
+        ssh_user: &str,
+        target_mac: Option<&str>,
+        range: &str,
+        result: Result<Option<String>, &RadarError>,
+    ) -> Self {
+        let (outcome, found_ip) = match result {
+            Ok(ip) => ("found", ip),
+            Err(RadarError::MacNotFound(_) | RadarError::NoHostReachable(..)) => {
+                ("not_found", None)
+            }
+            Err(_) => ("error", None),
+        };
+        Self::with_outcome(ssh_user, target_mac, range, outcome, found_ip)
+    }
+
+    fn with_outcome(
+        ssh_user: &str,
+        target_mac: Option<&str>,
+        range: &str,
+        outcome: &'static str,
+        found_ip: Option<String>,
+    ) -> Self {
+        Self {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            operator: local_operator(),
+            ssh_user: ssh_user.to_string(),
+            target_mac: target_mac.map(str::to_string),
+            range: range.to_string(),
+            outcome,
+            found_ip,
+        }
+    }
+}
+
+/// The audit lines of one CLI invocation, filled in as it runs and
+/// appended by [`Audit::finish`] once it ends, however it ends.
+#[derive(Debug, Default)]
+pub struct Audit {
+    /// `--audit-log`, once the config files are read.
+    pub path: Option<PathBuf>,
+    pub ssh_user: String,
+    /// What is scanned, once known.
+    pub range: String,
+    /// The MACs searched for; none for an inventory.
+    pub targets: Vec<String>,
+    /// The latest outcome of each target, once the scan has one.
+    records: Vec<AuditRecord>,
+}
+
+impl Audit {
+    /// The audit of an invocation that runs as `ssh_user` and looks for
+    /// `targets`, logged to `path`.
+    pub fn new(path: Option<PathBuf>, ssh_user: &str, targets: Vec<String>) -> Self {
+        Self {
+            path,
+            ssh_user: ssh_user.to_string(),
+            targets,
+            ..Default::default()
+        }
+    }
+
+    /// Record `result` for `mac`, or for the whole invocation when `None`,
+    /// replacing an earlier outcome (a watch records one per round).
+    pub fn record(&mut self, mac: Option<&str>, result: Result<Option<String>, &RadarError>) {
+        self.push(AuditRecord::new(&self.ssh_user, mac, &self.range, result));
+    }
+
+    /// Record `err` for every target, or for the invocation without any.
+    pub fn record_error(&mut self, err: &RadarError) {
+        match self.targets.clone().as_slice() {
+            [] => self.record(None, Err(err)),
+            targets => targets.iter().for_each(|mac| self.record(Some(mac), Err(err))),
+        }
+    }
+
+    fn push(&mut self, record: AuditRecord) {
+        self.records.retain(|r| r.target_mac != record.target_mac);
+        self.records.push(record);
+    }
+
+    /// Append every record to the audit log, if there is one. The invocation
+    /// and the targets it ended before finding an outcome for, such as after
+    /// an invalid argument or config file, are logged as `"error"`.
+    pub fn finish(mut self) {
+        let Some(path) = self.path.take() else {
+            return;
+        };
+        let unrecorded: Vec<Option<String>> = match self.targets.as_slice() {
+            [] if self.records.is_empty() => vec![None],
+            targets => targets
+                .iter()
+                .filter(|mac| !self.records.iter().any(|r| r.target_mac.as_ref() == Some(*mac)))
+                .map(|mac| Some(mac.clone()))
+                .collect(),
+        };
+        for mac in unrecorded {
+            let (user, range) = (&self.ssh_user, &self.range);
+            let record = AuditRecord::with_outcome(user, mac.as_deref(), range, "error", None);
+            self.push(record);
+        }
+        for record in &self.records {
+            append(&path, record);
+        }
+    }
+}
+
+/// Append `record` as a JSON line to the audit log at `path`.
+///
+/// The whole line is written with a single `write_all` on a file opened in
+/// append mode, so concurrent writers never interleave partial records.
+/// Failures are logged as warnings and never abort the scan.
+pub fn append(path: &Path, record: &AuditRecord) {
+    if let Err(e) = try_append(path, record) {
+        warn!("Failed to write audit log {}: {}", path.display(), e);
+    }
+}
+
+fn try_append(path: &Path, record: &AuditRecord) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())
+}
+
+/// Best-effort name of the local user running the tool.
+fn local_operator() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logged(audit: Audit) -> Vec<serde_json::Value> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        Audit { path: Some(path.clone()), ..audit }.finish();
+        let text = std::fs::read_to_string(&path).unwrap();
+        text.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn an_invocation_that_ends_early_logs_an_error_per_target() {
+        let targets = vec!["aa:bb:cc:dd:ee:ff".into(), "b8:27:eb:*".into()];
+        let lines = logged(Audit::new(None, "pi", targets));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["target_mac"], "aa:bb:cc:dd:ee:ff");
+        assert_eq!(lines[1]["target_mac"], "b8:27:eb:*");
+        assert!(lines.iter().all(|line| line["outcome"] == "error" && line["ssh_user"] == "pi"));
+    }
+
+    #[test]
+    fn an_inventory_logs_one_line_without_a_target() {
+        let lines = logged(Audit::new(None, "root", Vec::new()));
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["target_mac"], serde_json::Value::Null);
+        assert_eq!(lines[0]["outcome"], "error");
+
+        let mut audit = Audit::new(None, "root", Vec::new());
+        audit.range = "10.0.0.0/24".into();
+        audit.record(None, Ok(None));
+        let lines = logged(audit);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["outcome"], "found");
+        assert_eq!(lines[0]["range"], "10.0.0.0/24");
+    }
+
+    #[test]
+    fn a_later_outcome_replaces_an_earlier_one() {
+        let mac = "aa:bb:cc:dd:ee:ff";
+        let mut audit = Audit::new(None, "root", vec![mac.into()]);
+        audit.record(Some(mac), Ok(Some("10.0.0.5".into())));
+        audit.record(Some(mac), Err(&RadarError::MacNotFound(mac.into())));
+        let lines = logged(audit);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["outcome"], "not_found");
+        assert_eq!(lines[0]["found_ip"], serde_json::Value::Null);
+    }
+}
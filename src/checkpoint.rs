@@ -0,0 +1,94 @@
+use radar_ip_core::errors::RadarError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// One located target, stored as a JSON line.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    mac: String,
+    ip: String,
+}
+
+/// MACs already located by earlier runs, so an interrupted search can
+/// resume without looking for them again.
+///
+/// The file is append-only JSON lines (`{"mac":"…","ip":"…"}`); a line
+/// torn by a crash is ignored on load.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    /// Lowercase MAC → IP it was found at. Later lines win.
+    found: BTreeMap<String, String>,
+    /// The file ends mid-line, so the next append must start a new one.
+    torn: bool,
+}
+
+impl Checkpoint {
+    /// Load the checkpoint at `path`; a missing file is an empty checkpoint.
+    pub fn load(path: &Path) -> Result<Self, RadarError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(RadarError::Checkpoint(
+                    path.display().to_string(),
+                    e.to_string(),
+                ))
+            }
+        };
+
+        let mut found = BTreeMap::new();
+        for (n, line) in text.lines().enumerate() {
+            match serde_json::from_str::<Entry>(line) {
+                Ok(entry) => {
+                    found.insert(entry.mac.to_lowercase(), entry.ip);
+                }
+                Err(_) if line.trim().is_empty() => {}
+                Err(e) => warn!("Ignoring {}:{}: {}", path.display(), n + 1, e),
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            found,
+            torn: !text.is_empty() && !text.ends_with('\n'),
+        })
+    }
+
+    /// IP a previous run located `mac` at, if any.
+    pub fn get(&self, mac: &str) -> Option<&str> {
+        self.found.get(&mac.to_lowercase()).map(String::as_str)
+    }
+
+    /// Record that `mac` was found at `ip`. Failures are logged as warnings
+    /// and never abort the scan.
+    pub fn record(&mut self, mac: &str, ip: &str) {
+        let mac = mac.to_lowercase();
+        if self.found.get(&mac).map(String::as_str) == Some(ip) {
+            return;
+        }
+
+        let entry = Entry {
+            mac: mac.clone(),
+            ip: ip.to_string(),
+        };
+        match append(&self.path, &entry, self.torn) {
+            Ok(()) => self.torn = false,
+            Err(e) => warn!("Failed to write checkpoint {}: {}", self.path.display(), e),
+        }
+        self.found.insert(mac, entry.ip);
+    }
+}
+
+fn append(path: &Path, entry: &Entry, torn: bool) -> std::io::Result<()> {
+    let mut line = if torn { "\n".to_string() } else { String::new() };
+    line.push_str(&serde_json::to_string(entry)?);
+    line.push('\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())
+}
@@ -1,5 +1,24 @@
-use clap::Parser;
+use crate::config::Defaults;
+#[cfg(feature = "mqtt")]
+use crate::mqtt::{Broker, Publisher};
+#[cfg(feature = "webhook")]
+use crate::notifier::Template;
+use crate::logging::LogFormat;
+use crate::output::{MacCase, OutputFormat};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use ipnet::IpNet;
+use radar_ip_core::mdns::{self, MdnsConfig};
+use radar_ip_core::ssdp::{self, SsdpConfig};
+use radar_ip_core::scanner::{self, Prescan, Retry, ScanMode};
+#[cfg(feature = "snmp")]
+use radar_ip_core::snmp_client::SnmpFallback;
+use radar_ip_core::ssh_client::{
+    parse_fingerprint, HostKeyCheck, JumpHost, Platform, ProbeTemplate, SshTimeouts,
+};
+use regex::Regex;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// CLI arguments for the radar-ip tool.
 #[derive(Parser, Debug)]
@@ -7,30 +26,1119 @@ use std::path::PathBuf;
     author,
     version,
     about = "Scan an IP range via SSH and find which host owns a given MAC address",
-    long_about = None
+    long_about = None,
+    subcommand_negates_reqs = true
 )]
 pub struct CliArgs {
-    /// Target MAC address to search for (e.g. aa:bb:cc:dd:ee:ff)
-    #[arg(short = 'm', long)]
-    pub target_mac: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Target MAC address to search for (e.g. aa:bb:cc:dd:ee:ff), or an OUI
+    /// prefix matching every host of that vendor (e.g. 'b8:27:eb:*');
+    /// repeat to find several in one sweep
+    #[arg(
+        short = 'm',
+        long = "target-mac",
+        value_name = "TARGET_MAC",
+        required = true,
+        value_parser = parse_target
+    )]
+    pub target_macs: Vec<String>,
+
+    /// IP range(s) in CIDR notation, IPv4 or IPv6, or host names resolved
+    /// before the scan; repeat or comma-separate to scan several (e.g.
+    /// -r 10.8.0.0/24 -r fd00:8::/120 -r device-042.lab.local); required
+    /// unless set in the config directory
+    #[arg(short = 'r', long = "range", value_name = "IP_RANGE", global = true)]
+    pub ip_ranges: Vec<String>,
 
-    /// IP range in CIDR notation (e.g. 192.168.1.0/24)
-    #[arg(short = 'r', long = "range")]
-    pub ip_range: String,
+    /// Probe exactly the hosts in this file, one IP address or hostname per
+    /// line (`#` starts a comment), instead of a --range
+    #[arg(long, value_name = "PATH", global = true, conflicts_with = "ip_ranges")]
+    pub hosts_file: Option<PathBuf>,
+
+    /// Never probe these hosts: addresses, CIDRs or ranges, comma-separated
+    /// or repeated (e.g. 192.168.1.1,192.168.1.200-250); adds to
+    /// do-not-scan.txt
+    #[arg(long, value_name = "HOSTS", global = true, value_parser = parse_exclude)]
+    pub exclude: Vec<String>,
 
     /// Path to private key file for SSH authentication
-    #[arg(short = 'k', long = "key")]
+    #[arg(short = 'k', long = "key", global = true)]
     pub key_path: Option<PathBuf>,
 
     /// Password for SSH authentication (also used as key passphrase when --key is set)
-    #[arg(short = 'p', long)]
+    #[arg(short = 'p', long, global = true)]
     pub password: Option<String>,
 
-    /// SSH username
-    #[arg(short = 'u', long, default_value = "root")]
-    pub user: String,
+    /// SSH username [default: root]
+    #[arg(short = 'u', long, global = true)]
+    pub user: Option<String>,
+
+    /// SSH connection timeout in seconds [default: 5]
+    #[arg(long, global = true)]
+    pub timeout_sec: Option<u64>,
+
+    /// TCP connect timeout, e.g. 2s [default: --timeout-sec]
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, global = true)]
+    pub connect_timeout: Option<Duration>,
+
+    /// SSH handshake and host-key check timeout [default: --timeout-sec]
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, global = true)]
+    pub handshake_timeout: Option<Duration>,
+
+    /// SSH authentication timeout [default: --timeout-sec]
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, global = true)]
+    pub auth_timeout: Option<Duration>,
+
+    /// Timeout of each read while running a remote command
+    /// [default: --timeout-sec]
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, global = true)]
+    pub command_timeout: Option<Duration>,
+
+    /// Append a JSON line describing this scan to the given audit log file
+    #[arg(long, value_name = "PATH", global = true)]
+    pub audit_log: Option<PathBuf>,
+
+    /// Require HOST to present this SHA256 host-key fingerprint (repeatable,
+    /// e.g. 10.8.0.42=SHA256:abc...)
+    #[arg(
+        long = "pin-hostkey",
+        value_name = "HOST=SHA256",
+        value_parser = parse_pin,
+        global = true
+    )]
+    pub pin_hostkey: Vec<(String, Vec<u8>)>,
+
+    /// Tunnel every probe through this jump host, e.g. admin@bastion:2222;
+    /// it is logged into with the same credentials as the devices
+    #[arg(long, value_name = "[USER@]HOST[:PORT]", value_parser = JumpHost::parse, global = true)]
+    pub via: Option<JumpHost>,
+
+    /// How to verify host keys of hosts without a --pin-hostkey
+    #[arg(long, value_enum, default_value_t = HostKeyPolicy::Disabled, global = true)]
+    pub host_key_check: HostKeyPolicy,
+
+    /// known_hosts file used by --host-key-check [default: ~/.ssh/known_hosts]
+    #[arg(long, value_name = "PATH", global = true)]
+    pub known_hosts: Option<PathBuf>,
+
+    /// Scan the whole range and print every host that owns the MAC, one IP
+    /// per line
+    #[arg(long, conflicts_with_all = ["verify_after_found", "connect", "ssh_host", "hosts_entry"])]
+    pub all: bool,
+
+    /// Scan the whole range and fail if more than one host owns the MAC
+    #[arg(long)]
+    pub fail_on_multiple: bool,
+
+    /// Probe only this percentage of randomly selected hosts (non-exhaustive)
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent, global = true)]
+    pub sample: Option<f64>,
+
+    /// RNG seed for a reproducible --sample selection
+    #[arg(long, requires = "sample", global = true)]
+    pub seed: Option<u64>,
+
+    /// After a match, SSH into the found device and report its uptime
+    #[arg(long)]
+    pub verify_after_found: bool,
+
+    /// Once the device is found, log into it with ssh (same user, key or
+    /// password, port and jump host) and exit with the session's status
+    #[arg(long)]
+    pub connect: bool,
+
+    /// Print an OpenSSH `Host NAME` block for the found device (its IP, the
+    /// user, the --key and the --via jump host), for `ssh NAME`
+    #[arg(long, value_name = "NAME", value_parser = parse_host_alias)]
+    pub ssh_host: Option<String>,
+
+    /// Also write the --ssh-host block into ~/.ssh/config, replacing an
+    /// earlier block of that name
+    #[arg(long, requires = "ssh_host")]
+    pub write_ssh_config: bool,
+
+    /// Print a hosts-file line mapping the found IP to NAME
+    #[arg(long, value_name = "NAME", value_parser = parse_host_name)]
+    pub hosts_entry: Option<String>,
+
+    /// Also write the --hosts-entry line into the system hosts file
+    /// (/etc/hosts), replacing any other mapping of NAME
+    #[arg(long, requires = "hosts_entry")]
+    pub apply: bool,
+
+    /// Also read each host's OS release, kernel, uptime and addresses
+    #[arg(long, global = true)]
+    pub details: bool,
+
+    /// Open a new SSH session for every probe instead of keeping them open
+    /// between `watch` rounds and for --verify-after-found
+    #[arg(long, global = true)]
+    pub no_session_pool: bool,
+
+    /// After a match, look up the reverse DNS (PTR) name of each found IP
+    #[arg(long, global = true)]
+    pub reverse_dns: bool,
+
+    /// Wait a random 0..MAX milliseconds before each probe connects [default: 0]
+    #[arg(long, value_name = "MAX", global = true)]
+    pub jitter_ms: Option<u64>,
+
+    /// Probe at most N hosts at a time [default: 50]
+    #[arg(long, value_name = "N", global = true)]
+    pub concurrency: Option<usize>,
+
+    /// Keep the concurrency fixed instead of lowering it on bursts of
+    /// connection resets or failed handshakes
+    #[arg(long, global = true)]
+    pub fixed_concurrency: bool,
+
+    /// Before the SSH handshake, skip hosts that do not answer a ping
+    /// sweep and/or a TCP connect to the SSH port
+    #[arg(long, value_enum, default_value_t = PrescanMode::Tcp, global = true)]
+    pub prescan: PrescanMode,
+
+    /// How long the prescan waits for a host to answer, in milliseconds
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 300,
+        value_parser = clap::value_parser!(u64).range(1..),
+        global = true
+    )]
+    pub preprobe_ms: u64,
+
+    /// Try a host this many more times after a transient failure (timeout,
+    /// connection reset, no SSH banner)
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    pub retries: u32,
+
+    /// Wait before the first retry, in milliseconds; doubled for each
+    /// further one
+    #[arg(long, value_name = "MS", default_value_t = 500, global = true)]
+    pub retry_backoff_ms: u64,
+
+    /// Only match the MAC on interfaces whose name matches this regex
+    /// (e.g. '^(eth|en)')
+    #[arg(long, value_name = "PATTERN", value_parser = parse_regex, global = true)]
+    pub interface_regex: Option<Regex>,
+
+    /// List a host's MACs with this command instead of `ip link show` (and
+    /// its fallbacks), e.g. for appliances with a vendor CLI
+    #[arg(long, value_name = "COMMAND", global = true)]
+    pub probe_cmd: Option<String>,
+
+    /// Regex that finds the MACs in --probe-cmd's output: the `mac` group,
+    /// else the first group, else the whole match; an `iface` group names
+    /// the interface [default: any MAC]
+    #[arg(long, value_name = "PATTERN", value_parser = parse_regex, global = true)]
+    pub probe_regex: Option<Regex>,
+
+    /// What the probed hosts run, which picks the commands that list their
+    /// interfaces [default: auto]
+    #[arg(long, value_enum, global = true)]
+    pub platform: Option<HostPlatform>,
+
+    /// Shorthand for --platform busybox
+    #[arg(long, conflicts_with = "platform", global = true)]
+    pub busybox: bool,
+
+    /// Record every reachable host's interfaces into this SQLite database
+    /// (table `hosts`, upserted on each run)
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH", global = true)]
+    pub sqlite: Option<PathBuf>,
+
+    /// Scan history database, recorded after every scan and read by
+    /// `history` [default: history.db in the config dir]
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH", global = true)]
+    pub history_db: Option<PathBuf>,
+
+    /// Do not record this run in the scan history
+    #[cfg(feature = "sqlite")]
+    #[arg(long, global = true)]
+    pub no_history: bool,
+
+    /// Neither probe the IPs where the targets were last found first, nor
+    /// remember where they are found now
+    #[arg(long, global = true)]
+    pub no_ip_cache: bool,
+
+    /// Write one CSV row per probed host (ip, status, macs, error) to this
+    /// file, whatever the --format
+    #[arg(long, value_name = "PATH", global = true)]
+    pub report_file: Option<PathBuf>,
+
+    /// Print the public-key fingerprint of the key in use before scanning
+    #[arg(long, global = true)]
+    pub print_key_fingerprint: bool,
+
+    /// Check this always-reachable host's ARP table (e.g. the gateway) first
+    /// and skip the sweep if it already knows the MAC
+    #[arg(long, value_name = "HELPER_IP", global = true)]
+    pub arp_shortcut: Option<String>,
+
+    /// Check this machine's own ARP/neighbor table first (/proc/net/arp,
+    /// ip neigh, arp -a) and skip the sweep if it already knows the MAC
+    #[arg(long, global = true)]
+    pub local_arp: bool,
+
+    /// Resolve the MAC from this host's neighbor table alone (`ip neigh`,
+    /// else `arp -an`) instead of sweeping; --range is optional and limits
+    /// which entries count
+    #[arg(long, value_name = "HOST", conflicts_with = "arp_shortcut", global = true)]
+    pub gateway: Option<String>,
+
+    /// Resolve the MAC from the DHCP leases on this server or router alone
+    /// (dnsmasq, OpenWrt or ISC dhcpd) instead of sweeping, so devices
+    /// that are asleep are found too; --range is optional and limits which
+    /// leases count
+    #[arg(
+        long,
+        value_name = "HOST",
+        conflicts_with_all = ["arp_shortcut", "gateway", "mdns", "ssdp"],
+        global = true
+    )]
+    pub dhcp_server: Option<String>,
+
+    /// Lease file to read on --dhcp-server; repeat for several (default:
+    /// the usual dnsmasq, OpenWrt and dhcpd locations)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub lease_file: Vec<String>,
+
+    /// Browse mDNS first and probe only the hosts advertising --mdns-service
+    /// (within --range, which becomes optional); a TXT record naming the
+    /// MAC is a match without logging in
+    #[arg(long, conflicts_with = "gateway", global = true)]
+    pub mdns: bool,
+
+    /// Service type to browse with --mdns; repeat for several
+    #[arg(
+        long,
+        value_name = "TYPE",
+        default_values_t = mdns::DEFAULT_SERVICES.map(String::from),
+        global = true
+    )]
+    pub mdns_service: Vec<String>,
+
+    /// How long --mdns waits for answers
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "2s",
+        value_parser = humantime::parse_duration,
+        global = true
+    )]
+    pub mdns_timeout: Duration,
+
+    /// Multicast an SSDP M-SEARCH first and probe only the devices that
+    /// answer (within --range, which becomes optional); a time-based UUID
+    /// embedding the MAC is a match without logging in. With --mdns, the
+    /// hosts either finds are probed
+    #[arg(long, conflicts_with = "gateway", global = true)]
+    pub ssdp: bool,
+
+    /// Search target (ST) of the --ssdp M-SEARCH
+    #[arg(long, value_name = "ST", default_value = ssdp::DEFAULT_SEARCH_TARGET, global = true)]
+    pub ssdp_target: String,
+
+    /// How long --ssdp waits for answers
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "3s",
+        value_parser = humantime::parse_duration,
+        global = true
+    )]
+    pub ssdp_timeout: Duration,
+
+    /// Probe the whole range and print how many MACs each OUI vendor owns
+    #[arg(long, global = true)]
+    pub vendor_summary: bool,
+
+    /// Record located MACs in this file and, on a rerun with the same file,
+    /// skip the sweep for MACs it already holds
+    #[arg(long, value_name = "PATH")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Show a desktop notification with the IP when the device is found
+    #[arg(long)]
+    pub notify: bool,
+
+    /// POST the JSON scan report to this Slack/Teams-compatible webhook when
+    /// the scan completes
+    #[cfg(feature = "webhook")]
+    #[arg(long, value_name = "URL", global = true)]
+    pub webhook: Option<String>,
+
+    /// JSON body to POST for each `watch` change instead of the default one,
+    /// or @FILE to read it from; {event}, {mac}, {ip}, {previous_ip},
+    /// {timestamp} and {text} are filled in
+    #[cfg(feature = "webhook")]
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = Template::parse,
+        requires = "webhook",
+        global = true
+    )]
+    pub webhook_template: Option<Template>,
+
+    /// Publish found devices (and `watch` changes) as JSON to this MQTT
+    /// broker: mqtt://[USER[:PASSWORD]@]HOST[:PORT], with USER and PASSWORD
+    /// percent-encoded
+    #[cfg(feature = "mqtt")]
+    #[arg(long, value_name = "URL", value_parser = Broker::parse, global = true)]
+    pub mqtt_url: Option<Broker>,
+
+    /// MQTT topic; {mac} and {event} (found, moved, lost) are filled in
+    #[cfg(feature = "mqtt")]
+    #[arg(long, value_name = "TEMPLATE", default_value = "radar-ip/{mac}", global = true)]
+    pub mqtt_topic: String,
+
+    /// Publish with the MQTT retain flag, so new subscribers get the last IP
+    #[cfg(feature = "mqtt")]
+    #[arg(long, global = true)]
+    pub mqtt_retain: bool,
+
+    /// Abort if expanding the range into hosts takes longer than this
+    /// (e.g. 500ms, 2s)
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "5s",
+        value_parser = humantime::parse_duration,
+        global = true
+    )]
+    pub max_range_parse_time: Duration,
+
+    /// Directory holding config.toml, profiles/ and do-not-scan.txt
+    /// (defaults to the platform config dir, e.g. ~/.config/radar-ip)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Read defaults from this file instead of the config directory's
+    /// config.toml
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Layer profiles/NAME.toml from the config directory over config.toml
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+
+    /// Print human-readable text, a single JSON object for scripts, one
+    /// CSV row per probed host, or an Ansible inventory (INI or YAML) of the
+    /// found devices
+    #[arg(
+        long,
+        visible_alias = "output",
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        global = true
+    )]
+    pub format: OutputFormat,
+
+    /// Print only the found IP on stdout, and nothing if it is not found:
+    /// no banners or progress, failures on stderr. Text format only
+    #[arg(short = 'q', long, global = true)]
+    pub quiet: bool,
+
+    /// Print each host's outcome and probe time to stderr as the scan runs;
+    /// -vv also logs every SSH attempt and retry (unless RUST_LOG is set)
+    #[arg(short = 'v', long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// How log lines are written to stderr: `env_logger`-style text, or
+    /// one JSON object per line with the fields of each probe's span
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    pub log_format: LogFormat,
+
+    /// Letter case used when printing MAC addresses
+    #[arg(long, value_enum, default_value_t = MacCase::Lower, global = true)]
+    pub mac_case: MacCase,
+
+    /// Look the MAC up in a switch's forwarding database instead of SSHing
+    /// every host (e.g. public@10.8.0.2)
+    #[cfg(feature = "snmp")]
+    #[arg(long, value_name = "COMMUNITY@SWITCH")]
+    pub snmp: Option<String>,
+
+    /// Read the interface MACs of hosts that refuse SSH over SNMPv2c
+    /// (IF-MIB ifPhysAddress, UDP 161) with this community
+    #[cfg(feature = "snmp")]
+    #[arg(long, value_name = "COMMUNITY", global = true)]
+    pub snmp_fallback: Option<String>,
+
+    /// How long --snmp-fallback waits for each answer
+    #[cfg(feature = "snmp")]
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "2s",
+        value_parser = humantime::parse_duration,
+        global = true
+    )]
+    pub snmp_fallback_timeout: Duration,
+
+    /// Find the MAC from raw ARP replies on this local interface instead of
+    /// SSHing every host (IPv4 on the same L2 segment; needs root or
+    /// CAP_NET_RAW). --timeout-sec is how long to wait for replies
+    #[cfg(feature = "arp")]
+    #[arg(long, value_name = "INTERFACE", global = true)]
+    pub arp_scan: Option<String>,
+
+    /// How to discover devices
+    #[arg(long, value_enum, default_value_t = Method::Ssh, global = true)]
+    pub method: Method,
+
+    /// UDP port devices send their identification beacons to, with
+    /// --method beacon
+    #[arg(long, value_name = "PORT", default_value_t = 45454, global = true)]
+    pub beacon_port: u16,
+
+    /// Broadcast a discovery request on --beacon-port so devices answer at
+    /// once instead of at their next beacon
+    #[arg(long, global = true)]
+    pub beacon_solicit: bool,
+}
+
+/// Subcommands; without one, radar-ip searches for `--target-mac`.
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Probe every host in the range and list its IP, hostname and MACs
+    Inventory,
+    /// Search again on a schedule and report whenever a device shows up,
+    /// changes IP or disappears; runs until Ctrl+C
+    Watch(WatchArgs),
+    /// Locate one device and run a command on it over the session the scan
+    /// logged in with; prints its output and exits with its status
+    Exec(ExecArgs),
+    /// Locate one device and copy a local file to it over SFTP
+    Push(PushArgs),
+    /// Locate one device and copy a file from it over SFTP
+    Pull(PullArgs),
+    /// Locate one device and forward local ports through it over SSH (the
+    /// system `ssh`), until Ctrl+C
+    Tunnel(TunnelArgs),
+    /// List past scans, or where each MAC was last located, from the scan
+    /// history database
+    #[cfg(feature = "sqlite")]
+    History(HistoryArgs),
+}
+
+/// Options of `watch`.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct WatchArgs {
+    /// MAC address to keep track of (no OUI prefixes); repeat to watch
+    /// several
+    #[arg(
+        short = 'm',
+        long = "target-mac",
+        value_name = "TARGET_MAC",
+        required = true,
+        value_parser = parse_target
+    )]
+    pub target_macs: Vec<String>,
+
+    /// Time from the end of one scan to the start of the next (e.g. 30s,
+    /// 5m)
+    #[arg(long, value_name = "DURATION", default_value = "5m", value_parser = humantime::parse_duration)]
+    pub interval: Duration,
+
+    /// Serve Prometheus metrics at http://ADDR/metrics (e.g. 0.0.0.0:9464)
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_listen: Option<SocketAddr>,
+}
+
+/// Options of `exec`.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct ExecArgs {
+    /// MAC address of the device to run the command on (no OUI prefixes)
+    #[arg(short = 'm', long = "target-mac", value_name = "TARGET_MAC", value_parser = parse_target)]
+    pub target_mac: String,
+
+    /// The command, after `--`; its words are joined with spaces and run by
+    /// the device's shell, as with `ssh host command`
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    pub command: Vec<String>,
+}
+
+/// Options of `push`.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct PushArgs {
+    /// MAC address of the device to copy to (no OUI prefixes)
+    #[arg(short = 'm', long = "target-mac", value_name = "TARGET_MAC", value_parser = parse_target)]
+    pub target_mac: String,
+
+    /// The local file to copy
+    #[arg(value_name = "LOCAL")]
+    pub local: PathBuf,
+
+    /// Where on the device to put it; copied into a directory, it keeps
+    /// its name [default: the login directory]
+    #[arg(value_name = "REMOTE", default_value = "")]
+    pub remote: String,
+}
+
+/// Options of `pull`.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct PullArgs {
+    /// MAC address of the device to copy from (no OUI prefixes)
+    #[arg(short = 'm', long = "target-mac", value_name = "TARGET_MAC", value_parser = parse_target)]
+    pub target_mac: String,
+
+    /// The file on the device to copy
+    #[arg(value_name = "REMOTE")]
+    pub remote: String,
+
+    /// Where to put it; copied into a directory, it keeps its name
+    #[arg(value_name = "LOCAL", default_value = ".")]
+    pub local: PathBuf,
+}
+
+/// Options of `tunnel`.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct TunnelArgs {
+    /// MAC address of the device to tunnel through (no OUI prefixes)
+    #[arg(short = 'm', long = "target-mac", value_name = "TARGET_MAC", value_parser = parse_target)]
+    pub target_mac: String,
+
+    /// Forward a local port to HOST:HOSTPORT as seen from the device, e.g.
+    /// 8080:localhost:80 for a web UI bound to the device's localhost;
+    /// repeatable
+    #[arg(
+        short = 'L',
+        value_name = "[BIND:]PORT:HOST:HOSTPORT",
+        required = true,
+        value_parser = parse_forward
+    )]
+    pub forwards: Vec<String>,
+}
+
+/// Options of `history`.
+#[cfg(feature = "sqlite")]
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryArgs {
+    /// Only scans that searched for or found this MAC
+    #[arg(short = 'm', long = "target-mac", value_name = "TARGET_MAC", value_parser = parse_target)]
+    pub target_mac: Option<String>,
+
+    /// How many scans to list, newest first
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub limit: usize,
+
+    /// Instead, list each MAC ever found with its latest IP and when it
+    /// was found there
+    #[arg(long, conflicts_with_all = ["target_mac", "limit"])]
+    pub last_seen: bool,
+}
+
+/// `--method` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Method {
+    /// Log into every host and read its interfaces
+    Ssh,
+    /// Listen for UDP identification beacons; no SSH or credentials needed.
+    /// --timeout-sec is how long to listen
+    Beacon,
+}
+
+/// `--prescan` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PrescanMode {
+    /// Ping sweep first (raw ICMP, or an unprivileged ping socket), then
+    /// the TCP check
+    Ping,
+    /// TCP connect to the SSH port
+    Tcp,
+    /// Probe every host over SSH
+    None,
+}
+
+/// `--host-key-check` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HostKeyPolicy {
+    /// Accept any host key (alias: off)
+    #[value(alias = "off")]
+    Disabled,
+    /// Record unknown hosts, reject changed keys (alias: accept-new)
+    #[value(alias = "accept-new")]
+    Tofu,
+    /// Reject unknown hosts and changed keys
+    Strict,
+}
+
+/// `--platform` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HostPlatform {
+    /// Windows commands if the SSH banner names Windows, else Linux ones
+    Auto,
+    /// `ip link show`, `ifconfig -a`, then sysfs
+    Linux,
+    /// `ip -o link`, then sysfs; command timeout at most 2s, partial output
+    /// of stalled reads kept, channels closed without waiting
+    #[value(name = "busybox")]
+    BusyBox,
+    /// `ipconfig /all`, then PowerShell's `Get-NetAdapter`
+    Windows,
+}
+
+impl From<HostPlatform> for Platform {
+    fn from(platform: HostPlatform) -> Self {
+        match platform {
+            HostPlatform::Auto => Platform::Auto,
+            HostPlatform::Linux => Platform::Linux,
+            HostPlatform::BusyBox => Platform::BusyBox,
+            HostPlatform::Windows => Platform::Windows,
+        }
+    }
+}
+
+impl From<Platform> for HostPlatform {
+    fn from(platform: Platform) -> Self {
+        match platform {
+            Platform::Auto => HostPlatform::Auto,
+            Platform::Linux => HostPlatform::Linux,
+            Platform::BusyBox => HostPlatform::BusyBox,
+            Platform::Windows => HostPlatform::Windows,
+        }
+    }
+}
+
+impl CliArgs {
+    /// The host-key policy selected by `--host-key-check`/`--known-hosts`.
+    pub fn host_key_check(&self) -> HostKeyCheck {
+        let path = || {
+            self.known_hosts.clone().unwrap_or_else(|| {
+                std::env::var_os("HOME")
+                    .or_else(|| std::env::var_os("USERPROFILE"))
+                    .map(PathBuf::from)
+                    .unwrap_or_default()
+                    .join(".ssh")
+                    .join("known_hosts")
+            })
+        };
+        match self.host_key_check {
+            HostKeyPolicy::Disabled => HostKeyCheck::Disabled,
+            HostKeyPolicy::Tofu => HostKeyCheck::Tofu { path: path() },
+            HostKeyPolicy::Strict => HostKeyCheck::Strict { path: path() },
+        }
+    }
+
+    /// Fill every option not given on the command line from `defaults`.
+    /// Flags always win over config files.
+    pub fn apply_defaults(&mut self, defaults: Defaults) {
+        if self.ip_ranges.is_empty() {
+            self.ip_ranges.extend(defaults.range);
+        }
+        self.key_path = self.key_path.take().or(defaults.key);
+        self.user = self.user.take().or(defaults.user);
+        self.timeout_sec = self.timeout_sec.or(defaults.timeout_sec);
+        self.connect_timeout = self.connect_timeout.or(defaults.connect_timeout);
+        self.handshake_timeout = self.handshake_timeout.or(defaults.handshake_timeout);
+        self.auth_timeout = self.auth_timeout.or(defaults.auth_timeout);
+        self.command_timeout = self.command_timeout.or(defaults.command_timeout);
+        self.concurrency = self.concurrency.or(defaults.concurrency);
+        self.jitter_ms = self.jitter_ms.or(defaults.jitter_ms);
+        self.audit_log = self.audit_log.take().or(defaults.audit_log);
+        self.probe_cmd = self.probe_cmd.take().or(defaults.probe_cmd);
+        self.probe_regex = self.probe_regex.take().or(defaults.probe_regex);
+        self.platform = self.platform.or(defaults.platform.map(HostPlatform::from));
+    }
+
+    /// Where `--mqtt-url` messages go; `None` without one.
+    #[cfg(feature = "mqtt")]
+    pub fn mqtt(&self) -> Option<Publisher> {
+        self.mqtt_url.clone().map(|broker| Publisher {
+            broker,
+            topic: self.mqtt_topic.clone(),
+            retain: self.mqtt_retain,
+        })
+    }
+
+    /// Where the scan history lives: `--history-db`, else `history.db` in
+    /// the config directory; `None` if there is no config directory.
+    #[cfg(feature = "sqlite")]
+    pub fn history_path(&self) -> Option<PathBuf> {
+        let dir = || self.config_dir.clone().or_else(crate::config::default_dir);
+        let default = || dir().map(|d| d.join(crate::sqlite_store::HISTORY_FILE));
+        self.history_db.clone().or_else(default)
+    }
+
+    /// Where the last IP of each found MAC is kept: `last-ip.toml` in the
+    /// config directory; `None` with `--no-ip-cache` or without a config
+    /// directory.
+    pub fn ip_cache_path(&self) -> Option<PathBuf> {
+        if self.no_ip_cache {
+            return None;
+        }
+        let dir = self.config_dir.clone().or_else(crate::config::default_dir);
+        dir.map(|d| d.join(crate::ip_cache::CACHE_FILE))
+    }
+
+    /// Every `--range`, as one comma-separated list; `None` if there is none.
+    pub fn ip_range(&self) -> Option<String> {
+        (!self.ip_ranges.is_empty()).then(|| self.ip_ranges.join(","))
+    }
+
+    /// Networks covering every `--exclude` entry.
+    pub fn exclusions(&self) -> Vec<IpNet> {
+        self.exclude
+            .iter()
+            .flat_map(|list| scanner::parse_exclusions(list).unwrap_or_default())
+            .collect()
+    }
+
+    /// Target MACs and OUI prefixes, lowercase, in the order given, without
+    /// duplicates.
+    pub fn targets(&self) -> Vec<String> {
+        let watched = match &self.command {
+            Some(Command::Watch(watch)) => watch.target_macs.as_slice(),
+            Some(Command::Exec(ExecArgs { target_mac, .. }))
+            | Some(Command::Push(PushArgs { target_mac, .. }))
+            | Some(Command::Pull(PullArgs { target_mac, .. }))
+            | Some(Command::Tunnel(TunnelArgs { target_mac, .. })) => {
+                std::slice::from_ref(target_mac)
+            }
+            _ => &[],
+        };
+        let mut targets: Vec<String> = Vec::new();
+        for mac in self.target_macs.iter().chain(watched) {
+            if !targets.contains(mac) {
+                targets.push(mac.clone());
+            }
+        }
+        targets
+    }
+
+    /// The first option given that only works with a single target MAC.
+    pub fn single_target_flag(&self) -> Option<&'static str> {
+        #[cfg(feature = "snmp")]
+        if self.snmp.is_some() {
+            return Some("--snmp");
+        }
+        if let Some(name) = self.device_command() {
+            Some(name)
+        } else if self.all {
+            Some("--all")
+        } else if self.verify_after_found {
+            Some("--verify-after-found")
+        } else if self.connect {
+            Some("--connect")
+        } else if self.ssh_host.is_some() {
+            Some("--ssh-host")
+        } else if self.hosts_entry.is_some() {
+            Some("--hosts-entry")
+        } else {
+            None
+        }
+    }
+
+    /// The options of `exec`, if that is the command.
+    pub fn exec(&self) -> Option<&ExecArgs> {
+        match &self.command {
+            Some(Command::Exec(exec)) => Some(exec),
+            _ => None,
+        }
+    }
+
+    /// The name of the command, if it acts on one found device over SSH
+    /// once the scan is done (`exec`, `push`, `pull`, `tunnel`).
+    pub fn device_command(&self) -> Option<&'static str> {
+        match &self.command {
+            Some(Command::Exec(_)) => Some("exec"),
+            Some(Command::Push(_)) => Some("push"),
+            Some(Command::Pull(_)) => Some("pull"),
+            Some(Command::Tunnel(_)) => Some("tunnel"),
+            _ => None,
+        }
+    }
+
+    /// `--key` as an absolute path, for files that outlive the working
+    /// directory (`~/.ssh/config`, inventories).
+    pub fn absolute_key_path(&self) -> Option<PathBuf> {
+        let key = self.key_path.as_ref()?;
+        Some(std::path::absolute(key).unwrap_or_else(|_| key.clone()))
+    }
+
+    /// SSH username.
+    pub fn user(&self) -> &str {
+        self.user.as_deref().unwrap_or("root")
+    }
+
+    /// SSH connection timeout.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_sec.unwrap_or(5))
+    }
+
+    /// Per-stage SSH timeouts; a stage without its own flag uses
+    /// `--timeout-sec`.
+    pub fn ssh_timeouts(&self) -> SshTimeouts {
+        let base = self.timeout();
+        SshTimeouts {
+            connect: self.connect_timeout.unwrap_or(base),
+            handshake: self.handshake_timeout.unwrap_or(base),
+            auth: self.auth_timeout.unwrap_or(base),
+            command: self.command_timeout.unwrap_or(base),
+        }
+    }
+
+    /// The custom probe from `--probe-cmd` and `--probe-regex`, if any.
+    pub fn probe(&self) -> Option<ProbeTemplate> {
+        let command = self.probe_cmd.clone()?;
+        Some(ProbeTemplate::new(command, self.probe_regex.clone()))
+    }
+
+    /// The mDNS browse of `--mdns`, if given.
+    pub fn mdns(&self) -> Option<MdnsConfig> {
+        self.mdns.then(|| MdnsConfig {
+            services: self.mdns_service.clone(),
+            timeout: self.mdns_timeout,
+        })
+    }
+
+    /// The SSDP search of `--ssdp`, if given.
+    pub fn ssdp(&self) -> Option<SsdpConfig> {
+        self.ssdp.then(|| SsdpConfig {
+            search_target: self.ssdp_target.clone(),
+            timeout: self.ssdp_timeout,
+        })
+    }
+
+    /// The SNMP query of `--snmp-fallback`, if given.
+    #[cfg(feature = "snmp")]
+    pub fn snmp_fallback(&self) -> Option<SnmpFallback> {
+        self.snmp_fallback.as_ref().map(|community| SnmpFallback {
+            community: community.clone(),
+            port: 161,
+            timeout: self.snmp_fallback_timeout,
+        })
+    }
+
+    /// The platform from `--platform` or `--busybox`.
+    pub fn platform(&self) -> Platform {
+        if self.busybox {
+            Platform::BusyBox
+        } else {
+            self.platform.map(Platform::from).unwrap_or_default()
+        }
+    }
+
+    /// Maximum per-probe jitter.
+    pub fn jitter(&self) -> Duration {
+        Duration::from_millis(self.jitter_ms.unwrap_or(0))
+    }
+
+    /// How many hosts are probed at a time.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(scanner::DEFAULT_CONCURRENCY)
+    }
+
+    /// The prescan selected by `--prescan` and `--preprobe-ms`.
+    pub fn prescan(&self) -> Prescan {
+        let timeout = Duration::from_millis(self.preprobe_ms);
+        match self.prescan {
+            PrescanMode::None => Prescan::None,
+            PrescanMode::Tcp => Prescan::Tcp(timeout),
+            PrescanMode::Ping => Prescan::Ping(timeout),
+        }
+    }
+
+    /// Retry policy from `--retries` and `--retry-backoff-ms`.
+    pub fn retry(&self) -> Retry {
+        Retry {
+            attempts: self.retries.saturating_add(1),
+            backoff: Duration::from_millis(self.retry_backoff_ms),
+        }
+    }
+
+    /// What the sweep has to collect for the selected options.
+    pub fn scan_mode(&self) -> ScanMode {
+        if self.wants_inventory() {
+            ScanMode::Inventory
+        } else if self.all || self.fail_on_multiple {
+            ScanMode::AllMatches
+        } else {
+            ScanMode::FirstMatch
+        }
+    }
+
+    /// Whether an option needs every reachable host's identity, not just
+    /// the owner of the target MAC.
+    pub fn wants_inventory(&self) -> bool {
+        if self.vendor_summary || self.command == Some(Command::Inventory) {
+            return true;
+        }
+        #[cfg(feature = "sqlite")]
+        if self.sqlite.is_some() {
+            return true;
+        }
+        false
+    }
+}
+
+/// Parse a `HOST=FINGERPRINT` pin. HOST must be an IP address, and is
+/// kept in its canonical form so it matches the probed address however it
+/// was spelled (`fd00:0::1` pins `fd00::1`).
+fn parse_pin(s: &str) -> Result<(String, Vec<u8>), String> {
+    let (host, fingerprint) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected HOST=SHA256, got '{}'", s))?;
+    let ip: IpAddr = host
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected an IP address to pin, got '{}'", host.trim()))?;
+    let hash = parse_fingerprint(fingerprint)
+        .ok_or_else(|| format!("invalid SHA256 fingerprint '{}'", fingerprint))?;
+    Ok((ip.to_string(), hash))
+}
+
+/// Accept a list of hosts `scanner::parse_exclusions` understands.
+fn parse_exclude(s: &str) -> Result<String, String> {
+    scanner::parse_exclusions(s).map_err(|e| e.to_string())?;
+    Ok(s.to_string())
+}
+
+/// Accept a MAC address or an OUI prefix (`xx:xx:xx:*`), lowercased.
+fn parse_target(s: &str) -> Result<String, String> {
+    let re = Regex::new(r"^[0-9a-f]{2}(:[0-9a-f]{2}){2}(:\*|(:[0-9a-f]{2}){3})$")
+        .expect("target regex is valid");
+    let target = s.trim().to_lowercase();
+    if re.is_match(&target) {
+        Ok(target)
+    } else {
+        Err(format!(
+            "expected a MAC like aa:bb:cc:dd:ee:ff or an OUI prefix like b8:27:eb:*, got '{}'",
+            s
+        ))
+    }
+}
+
+/// Check a `Host` alias: one word, no patterns.
+fn parse_host_alias(s: &str) -> Result<String, String> {
+    let invalid = |c: char| c.is_whitespace() || matches!(c, '*' | '?' | '!' | ',' | '#');
+    if s.is_empty() || s.contains(invalid) {
+        Err(format!("expected a host alias without spaces or patterns, got '{}'", s))
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+/// Check a host name for a hosts file: dot-separated labels of letters,
+/// digits, `-` and `_`.
+fn parse_host_name(s: &str) -> Result<String, String> {
+    let label = r"[A-Za-z0-9_]([A-Za-z0-9_-]*[A-Za-z0-9_])?";
+    let re = Regex::new(&format!(r"^{0}(\.{0})*$", label)).expect("host name regex is valid");
+    if re.is_match(s) {
+        Ok(s.to_string())
+    } else {
+        Err(format!("expected a host name like device-042.lab, got '{}'", s))
+    }
+}
+
+/// Check an `ssh -L` forward, `[bind:]port:host:hostport`; IPv6 hosts are
+/// bracketed.
+fn parse_forward(s: &str) -> Result<String, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut bracketed = false;
+    for c in s.trim().chars() {
+        match c {
+            ':' if !bracketed => fields.push(std::mem::take(&mut field)),
+            c => {
+                bracketed = (bracketed || c == '[') && c != ']';
+                field.push(c);
+            }
+        }
+    }
+    fields.push(field);
+    let port = |p: &str| p.parse::<u16>().is_ok_and(|p| p > 0);
+    let valid = match fields.as_slice() {
+        [listen, host, to] => port(listen) && !host.is_empty() && port(to),
+        [_, listen, host, to] => port(listen) && !host.is_empty() && port(to),
+        _ => false,
+    };
+    if valid {
+        Ok(s.trim().to_string())
+    } else {
+        Err(format!("expected [BIND:]PORT:HOST:HOSTPORT like 8080:localhost:80, got '{}'", s))
+    }
+}
+
+/// Parse a percentage in `(0, 100]`.
+fn parse_percent(s: &str) -> Result<f64, String> {
+    let pct: f64 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if pct > 0.0 && pct <= 100.0 {
+        Ok(pct)
+    } else {
+        Err(format!("percentage must be in (0, 100], got {}", pct))
+    }
+}
+
+/// Compile a regex, reporting syntax errors at startup.
+fn parse_regex(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FINGERPRINT: &str = "SHA256:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU";
+
+    #[test]
+    fn pins_are_keyed_by_the_canonical_ip() {
+        let (host, hash) = parse_pin(&format!("fd00:0:0::01={}", FINGERPRINT)).unwrap();
+        assert_eq!(host, "fd00::1");
+        assert_eq!(hash, parse_fingerprint(FINGERPRINT).unwrap());
+        let (host, _) = parse_pin(&format!(" 10.0.0.5 ={}", FINGERPRINT)).unwrap();
+        assert_eq!(host, "10.0.0.5");
+    }
+
+    #[test]
+    fn pins_on_anything_but_an_ip_are_rejected() {
+        for host in ["010.0.0.5", "router.lab", "[fd00::1]", ""] {
+            let err = parse_pin(&format!("{}={}", host, FINGERPRINT)).unwrap_err();
+            assert!(err.starts_with("expected an IP address"), "{}: {}", host, err);
+        }
+        assert!(parse_pin("10.0.0.5").unwrap_err().starts_with("expected HOST=SHA256"));
+        assert!(parse_pin("10.0.0.5=SHA256:nope").unwrap_err().starts_with("invalid SHA256"));
+    }
+
+    #[test]
+    fn forwards_with_and_without_a_bind_address() {
+        for spec in [
+            "8080:localhost:80",
+            "127.0.0.1:8080:10.0.0.5:80",
+            "[::1]:8080:[fd00::5]:80",
+            "*:5432:db.lab:5432",
+        ] {
+            assert_eq!(parse_forward(spec).as_deref(), Ok(spec));
+        }
+        assert_eq!(parse_forward(" 8080:localhost:80\n").as_deref(), Ok("8080:localhost:80"));
+    }
 
-    /// SSH connection timeout in seconds
-    #[arg(long, default_value_t = 5)]
-    pub timeout_sec: u64,
-}
\ No newline at end of file
+    #[test]
+    fn malformed_forwards_are_rejected() {
+        for spec in [
+            "8080:localhost",
+            "8080",
+            "0:localhost:80",
+            "8080::80",
+            "8080:localhost:65536",
+            "http:localhost:80",
+            "a:1:b:2:c",
+            "8080:fd00::5:80",
+        ] {
+            assert_eq!(
+                parse_forward(spec),
+                Err(format!(
+                    "expected [BIND:]PORT:HOST:HOSTPORT like 8080:localhost:80, got '{}'",
+                    spec
+                ))
+            );
+        }
+    }
+}
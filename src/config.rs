@@ -0,0 +1,541 @@
+use ipnet::IpNet;
+use radar_ip_core::errors::RadarError;
+use radar_ip_core::scanner;
+use radar_ip_core::ssh_client::Platform;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Base defaults, read from `<dir>/config.toml`.
+const BASE_FILE: &str = "config.toml";
+/// Directory of named profiles, `<dir>/profiles/<name>.toml`.
+const PROFILE_DIR: &str = "profiles";
+/// Hosts and CIDRs that are never probed, one per line.
+const DO_NOT_SCAN_FILE: &str = "do-not-scan.txt";
+/// GUI device types, looked up in the working directory, then in
+/// [`default_dir`].
+const GUI_PROFILES_FILE: &str = "profiles.toml";
+/// What the GUI showed when it was last closed, in [`default_dir`].
+const GUI_STATE_FILE: &str = "gui-state.toml";
+/// The GUI's previous scans, in [`default_dir`].
+const GUI_HISTORY_FILE: &str = "history.toml";
+
+/// Defaults for CLI options, shared with the GUI. Keys use the long flag
+/// names, e.g.
+///
+/// ```toml
+/// range = ["10.8.0.0/24", "10.9.0.0/24"]
+/// user = "admin"
+/// key = "keys/fleet_rsa"
+/// timeout-sec = 3
+/// connect-timeout = "1s"
+/// concurrency = 20
+/// probe-cmd = "show interfaces | include address"
+/// ```
+///
+/// Relative paths are resolved against the directory of the file.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Defaults {
+    /// One range, or a list of them.
+    #[serde(deserialize_with = "one_or_many")]
+    pub range: Vec<String>,
+    pub key: Option<PathBuf>,
+    pub user: Option<String>,
+    pub timeout_sec: Option<u64>,
+    #[serde(deserialize_with = "duration")]
+    pub connect_timeout: Option<Duration>,
+    #[serde(deserialize_with = "duration")]
+    pub handshake_timeout: Option<Duration>,
+    #[serde(deserialize_with = "duration")]
+    pub auth_timeout: Option<Duration>,
+    #[serde(deserialize_with = "duration")]
+    pub command_timeout: Option<Duration>,
+    pub concurrency: Option<usize>,
+    pub jitter_ms: Option<u64>,
+    pub audit_log: Option<PathBuf>,
+    pub probe_cmd: Option<String>,
+    #[serde(deserialize_with = "regex")]
+    pub probe_regex: Option<Regex>,
+    pub platform: Option<Platform>,
+}
+
+impl Defaults {
+    /// Layer `over` on top of `self`: every value `over` sets wins.
+    fn overlay(self, over: Defaults) -> Defaults {
+        Defaults {
+            range: if over.range.is_empty() { self.range } else { over.range },
+            key: over.key.or(self.key),
+            user: over.user.or(self.user),
+            timeout_sec: over.timeout_sec.or(self.timeout_sec),
+            connect_timeout: over.connect_timeout.or(self.connect_timeout),
+            handshake_timeout: over.handshake_timeout.or(self.handshake_timeout),
+            auth_timeout: over.auth_timeout.or(self.auth_timeout),
+            command_timeout: over.command_timeout.or(self.command_timeout),
+            concurrency: over.concurrency.or(self.concurrency),
+            jitter_ms: over.jitter_ms.or(self.jitter_ms),
+            audit_log: over.audit_log.or(self.audit_log),
+            probe_cmd: over.probe_cmd.or(self.probe_cmd),
+            probe_regex: over.probe_regex.or(self.probe_regex),
+            platform: over.platform.or(self.platform),
+        }
+    }
+}
+
+/// Everything loaded from a config directory.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// `config.toml` with the selected profile layered on top.
+    pub defaults: Defaults,
+    /// Networks from `do-not-scan.txt`.
+    pub do_not_scan: Vec<IpNet>,
+}
+
+/// A device type selectable in the GUI, e.g.
+///
+/// ```toml
+/// [[profile]]
+/// name = "AI3"
+/// key_env = "AI3_PRIVATE_KEY"
+/// ip_range = "192.168.255.0/24"
+/// user = "pi"
+/// ```
+///
+/// The key comes from `key_env`, else the `key` file, else `config.toml`'s
+/// `key`, else the ssh-agent.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Label of the selector button.
+    pub name: String,
+    /// Environment variable holding the private key.
+    #[serde(default)]
+    pub key_env: Option<String>,
+    /// Private key file, relative to `profiles.toml`.
+    #[serde(default)]
+    pub key: Option<PathBuf>,
+    /// Default IP range.
+    pub ip_range: String,
+    /// Default SSH username.
+    pub user: String,
+    /// SSH port of the device.
+    #[serde(default = "ssh_port")]
+    pub port: u16,
+    /// Command that lists the device's MACs, when `ip link show` and the
+    /// other built-in commands do not.
+    #[serde(default)]
+    pub probe_cmd: Option<String>,
+    /// Regex that finds the MACs in `probe_cmd`'s output (any MAC if
+    /// unset).
+    #[serde(default)]
+    pub probe_regex: Option<String>,
+    /// What the device runs (`auto`, `linux`, `busybox` or `windows`).
+    #[serde(default)]
+    pub platform: Platform,
+}
+
+/// Top level of `profiles.toml`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfilesFile {
+    #[serde(rename = "profile")]
+    profiles: Vec<Profile>,
+}
+
+/// GUI device profiles from the first `profiles.toml` found, or the
+/// built-in HC/AI2/AI3 set if there is none (or it is invalid or empty).
+pub fn load_profiles() -> Vec<Profile> {
+    let candidates = [
+        Some(PathBuf::from(GUI_PROFILES_FILE)),
+        default_dir().map(|d| d.join(GUI_PROFILES_FILE)),
+    ];
+
+    for path in candidates.into_iter().flatten() {
+        let text = match read_optional(&path) {
+            Ok(Some(text)) => text,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("{}; using built-in profiles", e);
+                break;
+            }
+        };
+        match toml::from_str::<ProfilesFile>(&text) {
+            Ok(mut file) if !file.profiles.is_empty() => {
+                tracing::info!("Loaded {} profile(s) from {}", file.profiles.len(), path.display());
+                let dir = path.parent().unwrap_or(Path::new(""));
+                for key in file.profiles.iter_mut().filter_map(|p| p.key.as_mut()) {
+                    if key.is_relative() {
+                        *key = dir.join(&*key);
+                    }
+                }
+                return file.profiles;
+            }
+            Ok(_) => {
+                tracing::warn!("{} defines no profiles; using built-in profiles", path.display())
+            }
+            Err(e) => {
+                let msg = e.message();
+                tracing::warn!("Invalid {}: {}; using built-in profiles", path.display(), msg)
+            }
+        }
+        break;
+    }
+
+    builtin_profiles()
+}
+
+/// The device types shipped with radar-ip.
+fn builtin_profiles() -> Vec<Profile> {
+    let profile = |name: &str, key_env: &str, ip_range: &str, user: &str| Profile {
+        name: name.into(),
+        key_env: Some(key_env.into()),
+        key: None,
+        ip_range: ip_range.into(),
+        user: user.into(),
+        port: ssh_port(),
+        probe_cmd: None,
+        probe_regex: None,
+        platform: Platform::Auto,
+    };
+    vec![
+        profile("HC", "HC_PRIVATE_KEY", "10.8.0.0/24", "root"),
+        profile("AI2", "AI2_PRIVATE_KEY", "10.8.0.0/24", "nano"),
+        profile("AI3", "AI3_PRIVATE_KEY", "192.168.255.0/24", "pi"),
+    ]
+}
+
+fn ssh_port() -> u16 {
+    22
+}
+
+/// Platform config directory for radar-ip: `$XDG_CONFIG_HOME/radar-ip` (or
+/// `~/.config/radar-ip`) on Linux, `~/Library/Application Support/radar-ip`
+/// on macOS and `%APPDATA%\radar-ip` on Windows.
+pub fn default_dir() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|h| h.join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| home().map(|h| h.join(".config")))
+    };
+    base.map(|b| b.join("radar-ip"))
+}
+
+/// Load the config directory `dir` (or [`default_dir`] when `None`). `file`
+/// replaces its `config.toml`.
+///
+/// Every file is optional. A missing default directory yields an empty
+/// config (plus `file`); a missing explicit `dir` or `file`, or an unknown
+/// `profile`, is an error.
+pub fn load(
+    dir: Option<&Path>,
+    file: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<Config, RadarError> {
+    let base = match file {
+        Some(file) => {
+            let parent = file.parent().unwrap_or(Path::new(""));
+            let defaults = read_defaults(parent, file)?.ok_or_else(|| {
+                RadarError::Config(file.display().to_string(), "no such file".into())
+            })?;
+            tracing::info!("Loaded config file {}", file.display());
+            Some(defaults)
+        }
+        None => None,
+    };
+
+    let dir = match dir {
+        Some(dir) if !dir.is_dir() => {
+            return Err(RadarError::Config(
+                dir.display().to_string(),
+                "not a directory".into(),
+            ))
+        }
+        Some(dir) => dir.to_path_buf(),
+        None => match default_dir().filter(|d| d.is_dir()) {
+            Some(dir) => dir,
+            None if profile.is_some() => {
+                return Err(RadarError::Config(
+                    "--profile".into(),
+                    "no config directory found; pass --config-dir".into(),
+                ))
+            }
+            None => {
+                return Ok(Config {
+                    defaults: base.unwrap_or_default(),
+                    ..Config::default()
+                })
+            }
+        },
+    };
+    tracing::info!("Loading config from {}", dir.display());
+
+    let mut defaults = match base {
+        Some(defaults) => defaults,
+        None => read_defaults(&dir, &dir.join(BASE_FILE))?.unwrap_or_default(),
+    };
+    if let Some(name) = profile {
+        let path = dir.join(PROFILE_DIR).join(format!("{}.toml", name));
+        let layer = read_defaults(&dir, &path)?.ok_or_else(|| {
+            RadarError::Config(path.display().to_string(), format!("no profile '{}'", name))
+        })?;
+        defaults = defaults.overlay(layer);
+    }
+
+    Ok(Config {
+        defaults,
+        do_not_scan: read_do_not_scan(&dir.join(DO_NOT_SCAN_FILE))?,
+    })
+}
+
+/// Parse one TOML layer, or `Ok(None)` if `path` does not exist.
+fn read_defaults(dir: &Path, path: &Path) -> Result<Option<Defaults>, RadarError> {
+    let Some(text) = read_optional(path)? else {
+        return Ok(None);
+    };
+    let mut defaults: Defaults = toml::from_str(&text)
+        .map_err(|e| RadarError::Config(path.display().to_string(), e.message().to_string()))?;
+
+    for p in [&mut defaults.key, &mut defaults.audit_log].into_iter().flatten() {
+        if p.is_relative() {
+            *p = dir.join(&*p);
+        }
+    }
+    Ok(Some(defaults))
+}
+
+/// Parse the do-not-scan list: one IPv4/IPv6 address, CIDR or range (e.g.
+/// `10.8.0.200-250`) per line, `#` starts a comment.
+fn read_do_not_scan(path: &Path) -> Result<Vec<IpNet>, RadarError> {
+    let Some(text) = read_optional(path)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut nets = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let entry = line.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let entry_nets = scanner::parse_exclusion(entry).ok_or_else(|| {
+            RadarError::Config(
+                format!("{}:{}", path.display(), n + 1),
+                format!("'{}' is not an IP address, CIDR or range", entry),
+            )
+        })?;
+        nets.extend(entry_nets);
+    }
+    Ok(nets)
+}
+
+/// Read a hosts file: one IP address or hostname per line, `#` starts a
+/// comment. Hostnames resolve to their first address; one that does not
+/// resolve is skipped with a warning.
+pub fn load_hosts_file(path: &Path) -> Result<Vec<IpAddr>, RadarError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| RadarError::Config(path.display().to_string(), e.to_string()))?;
+
+    let mut hosts = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let entry = line.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+        if let Ok(ip) = entry.parse::<IpAddr>() {
+            hosts.push(ip);
+            continue;
+        }
+        match (entry, 22).to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(addr)) => hosts.push(addr.ip()),
+            Ok(None) => {
+                tracing::warn!("{}:{}: '{}' has no address; skipping", path.display(), n + 1, entry)
+            }
+            Err(e) => tracing::warn!(
+                "{}:{}: cannot resolve '{}': {}; skipping",
+                path.display(),
+                n + 1,
+                entry,
+                e
+            ),
+        }
+    }
+    Ok(hosts)
+}
+
+/// The GUI's inputs, restored on the next launch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiState {
+    pub mac: String,
+    pub ip_range: String,
+    pub exclude: String,
+    /// Name of the selected profile.
+    pub profile: String,
+    /// SSH user, as edited; empty for the profile's.
+    pub user: String,
+    /// The password itself is never saved.
+    pub auth: GuiAuth,
+    pub prescan: GuiPrescan,
+    pub ssh_timeout_secs: Option<u64>,
+    pub deadline_secs: Option<u64>,
+    pub concurrency: Option<usize>,
+    /// SSH port, when it differs from the profile's.
+    pub port: Option<u16>,
+    pub via: String,
+    /// Hide in the tray (or minimize) instead of quitting when the window
+    /// is closed mid-scan or while watching.
+    pub background_on_close: bool,
+    /// Scan again every `watch_interval_secs` until stopped.
+    pub watch: bool,
+    pub watch_interval_secs: Option<u64>,
+    /// Show a desktop notification when a scan finds its device.
+    pub notify: bool,
+    /// Private key picked in the GUI; empty to use the profile's.
+    pub key_file: String,
+}
+
+/// The GUI's prescan choices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuiPrescan {
+    None,
+    #[default]
+    Tcp,
+    Ping,
+}
+
+/// How the GUI logs in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuiAuth {
+    /// A private key; the password field is its passphrase.
+    #[default]
+    Key,
+    Password,
+}
+
+/// One finished GUI scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the scan started, RFC 3339.
+    pub timestamp: String,
+    pub mac: String,
+    pub ip_range: String,
+    /// Name of the profile it ran with.
+    pub profile: String,
+    /// The owner found, if any.
+    pub ip: Option<String>,
+    /// Why nothing was found.
+    pub error: Option<String>,
+    pub duration_secs: f64,
+}
+
+/// Top level of `history.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    #[serde(default, rename = "scan")]
+    scans: Vec<HistoryEntry>,
+}
+
+/// The GUI state saved by [`save_gui_state`], if any. An unreadable file is
+/// ignored with a warning.
+pub fn load_gui_state() -> Option<GuiState> {
+    read_gui_file(GUI_STATE_FILE)
+}
+
+/// Write `state` for the next launch, creating the config directory.
+pub fn save_gui_state(state: &GuiState) -> Result<(), RadarError> {
+    write_gui_file(GUI_STATE_FILE, state)
+}
+
+/// Previous GUI scans, newest first.
+pub fn load_history() -> Vec<HistoryEntry> {
+    read_gui_file::<HistoryFile>(GUI_HISTORY_FILE)
+        .map(|file| file.scans)
+        .unwrap_or_default()
+}
+
+/// Replace the saved GUI scans with `scans`.
+pub fn save_history(scans: &[HistoryEntry]) -> Result<(), RadarError> {
+    write_gui_file(
+        GUI_HISTORY_FILE,
+        &HistoryFile {
+            scans: scans.to_vec(),
+        },
+    )
+}
+
+/// Parse `name` from [`default_dir`]; a missing or invalid file is `None`,
+/// the latter with a warning.
+fn read_gui_file<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = default_dir()?.join(name);
+    let text = match read_optional(&path) {
+        Ok(text) => text?,
+        Err(e) => {
+            tracing::warn!("{}; starting with defaults", e);
+            return None;
+        }
+    };
+    toml::from_str(&text)
+        .map_err(|e| {
+            tracing::warn!("Invalid {}: {}; starting with defaults", path.display(), e.message())
+        })
+        .ok()
+}
+
+/// Write `value` to `name` in [`default_dir`], creating the directory.
+fn write_gui_file<T: Serialize>(name: &str, value: &T) -> Result<(), RadarError> {
+    let dir = default_dir()
+        .ok_or_else(|| RadarError::Config(name.into(), "no config directory".into()))?;
+    let path = dir.join(name);
+    let err = |e: String| RadarError::Config(path.display().to_string(), e);
+    let text = toml::to_string(value).map_err(|e| err(e.to_string()))?;
+    std::fs::create_dir_all(&dir).map_err(|e| err(e.to_string()))?;
+    std::fs::write(&path, text).map_err(|e| err(e.to_string()))
+}
+
+/// Read `path`, treating a missing file as `None`.
+fn read_optional(path: &Path) -> Result<Option<String>, RadarError> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(RadarError::Config(path.display().to_string(), e.to_string())),
+    }
+}
+
+/// Deserialize an optional regex, rejecting invalid patterns.
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    let pattern = Option::<String>::deserialize(deserializer)?;
+    pattern
+        .map(|p| Regex::new(&p).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Deserialize a string or a list of strings.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+/// Deserialize an optional duration such as `"1500ms"` or `"2s"`.
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let text = Option::<String>::deserialize(deserializer)?;
+    text.map(|t| humantime::parse_duration(&t).map_err(serde::de::Error::custom))
+        .transpose()
+}
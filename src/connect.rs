@@ -0,0 +1,231 @@
+use radar_ip_core::ssh_client::{AuthenticationMethod, HostKeyCheck, SshConfig};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, ExitCode, ExitStatus};
+
+/// Carries the password or key passphrase to the `radar-ip` that `ssh`
+/// runs as its `SSH_ASKPASS` helper (see [`answer_askpass`]).
+const ASKPASS_SECRET: &str = "RADAR_IP_ASKPASS_SECRET";
+
+/// The OpenSSH client, on the `PATH` on Linux, macOS and Windows 10+.
+const SSH: &str = "ssh";
+
+/// Where `ssh` keeps no host keys, for scans that accept any key.
+#[cfg(windows)]
+const NO_KNOWN_HOSTS: &str = "NUL";
+#[cfg(not(windows))]
+const NO_KNOWN_HOSTS: &str = "/dev/null";
+
+/// An interactive OpenSSH login to a found device with the credentials
+/// and host-key policy of the scan that found it.
+///
+/// The secret is never put on the command line: `ssh` asks this binary
+/// for it through `SSH_ASKPASS`, and a key that only exists in memory is
+/// written to a private file for as long as the login needs it.
+pub struct Login {
+    args: Vec<OsString>,
+    secret: Option<String>,
+    key_file: Option<KeyFile>,
+}
+
+impl Login {
+    pub fn new(config: &SshConfig, ip: &str) -> io::Result<Self> {
+        let mut args: Vec<OsString> = Vec::new();
+        let mut option = |name: &str, value: &str| {
+            args.push("-o".into());
+            args.push(format!("{}={}", name, value).into());
+        };
+        match &config.host_key_check {
+            HostKeyCheck::Disabled => {
+                option("StrictHostKeyChecking", "no");
+                option("UserKnownHostsFile", NO_KNOWN_HOSTS);
+                option("LogLevel", "ERROR");
+            }
+            HostKeyCheck::Tofu { path } => {
+                option("StrictHostKeyChecking", "accept-new");
+                option("UserKnownHostsFile", &path.display().to_string());
+            }
+            HostKeyCheck::Strict { path } => {
+                option("StrictHostKeyChecking", "yes");
+                option("UserKnownHostsFile", &path.display().to_string());
+            }
+        }
+
+        let (secret, key_file, key_path) = match &config.auth {
+            AuthenticationMethod::Password(password) => {
+                option("PubkeyAuthentication", "no");
+                option("PreferredAuthentications", "password,keyboard-interactive");
+                (Some(password.clone()), None, None)
+            }
+            AuthenticationMethod::PrivateKey { path, passphrase } => {
+                (passphrase.clone(), None, Some(path.clone()))
+            }
+            AuthenticationMethod::PrivateKeyMemory {
+                key_data,
+                passphrase,
+            } => {
+                let key_file = KeyFile::write(key_data)?;
+                let path = key_file.0.clone();
+                (passphrase.clone(), Some(key_file), Some(path))
+            }
+            AuthenticationMethod::Agent => (None, None, None),
+        };
+        if let Some(path) = key_path {
+            option("IdentitiesOnly", "yes");
+            args.push("-i".into());
+            args.push(path.into());
+        }
+
+        if let Some(jump) = &config.via {
+            let user = jump.user.as_deref().unwrap_or(&config.user);
+            let host = if jump.host.contains(':') {
+                format!("[{}]", jump.host)
+            } else {
+                jump.host.clone()
+            };
+            args.push("-J".into());
+            args.push(format!("{}@{}:{}", user, host, jump.port).into());
+        }
+        args.extend(["-p".into(), config.port.to_string().into()]);
+        args.extend(["-l".into(), config.user.as_str().into(), ip.into()]);
+
+        Ok(Self {
+            args,
+            secret: secret.filter(|s| !s.is_empty()),
+            key_file,
+        })
+    }
+
+    /// Only forward each `-L` spec (`[bind:]port:host:hostport`), without a
+    /// shell, and give up if any of them cannot be set up.
+    pub fn forward(mut self, forwards: &[String]) -> Self {
+        // Options go before the destination, the last argument.
+        let host = self.args.pop().expect("Login::new puts the host last");
+        self.args.extend(["-N".into(), "-o".into(), "ExitOnForwardFailure=yes".into()]);
+        for spec in forwards {
+            self.args.extend(["-L".into(), spec.into()]);
+        }
+        self.args.push(host);
+        self
+    }
+
+    /// Log in from this terminal and wait for the session to end.
+    pub fn run(self) -> io::Result<ExitStatus> {
+        self.command(SSH)?.args(&self.args).status()
+    }
+
+    /// Log in from a new terminal window, for the GUI. The returned guard
+    /// holds the key file of an in-memory key; drop it once the window is
+    /// done with it.
+    pub fn open_terminal(self) -> io::Result<Option<KeyFile>> {
+        terminal(&self)?;
+        Ok(self.key_file)
+    }
+
+    /// `program` (`ssh`, or a terminal that runs it) with the askpass
+    /// environment set when there is a secret to answer with.
+    fn command(&self, program: &str) -> io::Result<Command> {
+        let mut command = Command::new(program);
+        if let Some(secret) = &self.secret {
+            command
+                .env("SSH_ASKPASS", std::env::current_exe()?)
+                .env("SSH_ASKPASS_REQUIRE", "force")
+                .env(ASKPASS_SECRET, secret);
+        }
+        Ok(command)
+    }
+}
+
+/// A private key written out for `ssh -i`, deleted when dropped.
+pub struct KeyFile(PathBuf);
+
+impl KeyFile {
+    fn write(key_data: &str) -> io::Result<Self> {
+        let name = format!("radar-ip-key-{}-{}", std::process::id(), unique());
+        let path = std::env::temp_dir().join(name);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        let key = KeyFile(path);
+        let key_data = key_data.replace("\r\n", "\n");
+        file.write_all(key_data.as_bytes())?;
+        if !key_data.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+        Ok(key)
+    }
+}
+
+impl Drop for KeyFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// A number that differs between the key files of one process.
+fn unique() -> u64 {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// When `ssh` started this process as its askpass helper, print the
+/// secret for a password or passphrase prompt (and refuse any other, such
+/// as a host-key confirmation) and return the exit code; else `None`.
+pub fn answer_askpass() -> Option<ExitCode> {
+    let secret = std::env::var(ASKPASS_SECRET).ok()?;
+    let prompt = std::env::args().nth(1).unwrap_or_default().to_lowercase();
+    if prompt.contains("password") || prompt.contains("passphrase") {
+        println!("{}", secret);
+        Some(ExitCode::SUCCESS)
+    } else {
+        Some(ExitCode::FAILURE)
+    }
+}
+
+/// Open Terminal with `ssh …` typed in. Terminal does not inherit our
+/// environment, so `ssh` prompts for any password itself.
+#[cfg(target_os = "macos")]
+fn terminal(login: &Login) -> io::Result<()> {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "'\\''"));
+    let mut line = vec![SSH.to_string()];
+    line.extend(login.args.iter().map(|a| quote(&a.to_string_lossy())));
+    let script = format!(
+        "tell application \"Terminal\" to do script \"{}\"",
+        line.join(" ").replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    Command::new("osascript").arg("-e").arg(script).spawn().map(drop)
+}
+
+/// Start `ssh` in a new console window.
+#[cfg(windows)]
+fn terminal(login: &Login) -> io::Result<()> {
+    let mut command = login.command("cmd")?;
+    command.args(["/C", "start", "radar-ip", SSH]).args(&login.args);
+    command.spawn().map(drop)
+}
+
+/// Run `ssh` in the first terminal emulator found.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn terminal(login: &Login) -> io::Result<()> {
+    const TERMINALS: [(&str, &str); 4] = [
+        ("x-terminal-emulator", "-e"),
+        ("gnome-terminal", "--"),
+        ("konsole", "-e"),
+        ("xterm", "-e"),
+    ];
+    let mut last = io::Error::new(io::ErrorKind::NotFound, "no terminal emulator found");
+    for (program, run) in TERMINALS {
+        let mut command = login.command(program)?;
+        command.arg(run).arg(SSH).args(&login.args);
+        match command.spawn() {
+            Ok(_) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => last = e,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last)
+}
@@ -0,0 +1,40 @@
+use crate::output::MacCase;
+use radar_ip_core::report::HostResult;
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Header row; one row per probed host follows.
+const HEADER: &str = "ip,status,macs,error";
+
+/// Render `hosts` as CSV (RFC 4180, `\n` line endings), MACs separated by
+/// spaces and rendered in `mac_case`.
+pub fn render(hosts: &[HostResult], mac_case: MacCase) -> String {
+    let mut csv = format!("{}\n", HEADER);
+    for host in hosts {
+        let macs: Vec<String> = host.macs.iter().map(|m| mac_case.apply(m)).collect();
+        let macs = macs.join(" ");
+        let row = [
+            field(&host.ip),
+            field(host.status.as_str()),
+            field(&macs),
+            field(host.error.as_deref().unwrap_or_default()),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Write `hosts` as CSV to `path`, replacing any existing file.
+pub fn write_file(path: &Path, hosts: &[HostResult], mac_case: MacCase) -> std::io::Result<()> {
+    std::fs::write(path, render(hosts, mac_case))
+}
+
+/// Quote `value` if it contains a separator, quote or line break.
+fn field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
@@ -0,0 +1,112 @@
+use radar_ip_core::report::ScanMatch;
+#[cfg(any(target_os = "macos", windows))]
+use std::process::{Command, Stdio};
+use tracing::warn;
+
+/// Announce `found` on the desktop, one "MAC is at IP" line per match.
+pub fn found(found: &[ScanMatch]) {
+    let lines: Vec<String> = found.iter().map(|m| format!("{} is at {}", m.mac, m.ip)).collect();
+    let summary = match found.len() {
+        1 => "Radar-IP: device found".to_string(),
+        n => format!("Radar-IP: {} devices found", n),
+    };
+    show(&summary, &lines.join("\n"));
+}
+
+/// Show a desktop notification through the freedesktop notification
+/// service on the D-Bus session bus, on Linux and the BSDs.
+///
+/// Waits at most [`NOTIFY_TIMEOUT`] for the service, so a CLI that exits
+/// right after still gets its notification out. Failures are logged as
+/// warnings and never fail the scan.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn show(summary: &str, body: &str) {
+    let (summary, body) = (summary.to_string(), body.to_string());
+    let (sent, result) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sent.send(notify(&summary, &body));
+    });
+    let error = match result.recv_timeout(NOTIFY_TIMEOUT) {
+        Ok(Ok(())) => return,
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "the notification service did not answer".to_string(),
+    };
+    warn!("Cannot show a desktop notification: {}", error);
+}
+
+/// How long [`show`] waits for the notification service.
+#[cfg(not(any(target_os = "macos", windows)))]
+const NOTIFY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Call `org.freedesktop.Notifications.Notify`, as `notify-send` does.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn notify(summary: &str, body: &str) -> zbus::Result<()> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let connection = zbus::blocking::Connection::session()?;
+    let actions: Vec<&str> = Vec::new();
+    let hints: HashMap<&str, Value> = HashMap::new();
+    // App name, no notification to replace, no icon, and the server's
+    // default expiry.
+    let body = ("radar-ip", 0u32, "", summary, body, actions, hints, -1i32);
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &body,
+    )?;
+    Ok(())
+}
+
+/// Show a desktop notification through the platform's own tool:
+/// `osascript` on macOS and a PowerShell balloon tip on Windows.
+///
+/// The tool runs in the background and outlives a CLI that exits right
+/// after. Failures are logged as warnings and never fail the scan.
+#[cfg(any(target_os = "macos", windows))]
+pub fn show(summary: &str, body: &str) {
+    let mut command = command(summary, body);
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    match command.spawn() {
+        Ok(mut child) => {
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => {
+                    warn!("Desktop notification failed ({})", status)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Desktop notification failed: {}", e),
+            });
+        }
+        Err(e) => warn!("Cannot show a desktop notification: {}", e),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn command(summary: &str, body: &str) -> Command {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let script = format!("display notification {} with title {}", quote(body), quote(summary));
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(script);
+    command
+}
+
+#[cfg(windows)]
+fn command(summary: &str, body: &str) -> Command {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; \
+         $n.Visible = $true; \
+         $n.ShowBalloonTip(10000, {}, {}, 'Info'); \
+         Start-Sleep -Seconds 10; \
+         $n.Dispose()",
+        quote(summary),
+        quote(body)
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    command
+}
@@ -0,0 +1,42 @@
+use radar_ip_core::errors::RadarError;
+use std::process::ExitCode;
+
+/// How the CLI exits, for scripts. `exec`, `tunnel` and `--connect` exit
+/// with the remote command's or `ssh`'s status once the device is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Every target was found (or the subcommand succeeded).
+    Found = 0,
+    /// Anything else went wrong.
+    Failure = 1,
+    /// The scan ran but a target MAC is on no host that answered.
+    NotFound = 2,
+    /// The command line, a config file or the range is invalid.
+    InvalidArgs = 3,
+    /// No host could be probed: all were unreachable or refused the login.
+    Unreachable = 4,
+    /// Ctrl+C stopped the scan.
+    Cancelled = 130,
+}
+
+impl Exit {
+    /// The exit for a scan or subcommand that failed with `err`.
+    pub fn of(err: &RadarError) -> Self {
+        match err {
+            RadarError::MacNotFound(_) => Exit::NotFound,
+            RadarError::NoHostReachable(..) => Exit::Unreachable,
+            RadarError::InvalidIpRange(_)
+            | RadarError::RangeTooLarge(_)
+            | RadarError::RangeParseTimeout(..)
+            | RadarError::Config(..) => Exit::InvalidArgs,
+            RadarError::Cancelled => Exit::Cancelled,
+            _ => Exit::Failure,
+        }
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
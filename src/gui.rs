@@ -1,103 +1,834 @@
-use crate::scanner::Scanner;
-use crate::ssh_client::{AuthenticationMethod, SshConfig};
+use crate::config::{self, Defaults, GuiAuth, GuiPrescan, GuiState, HistoryEntry, Profile};
+use crate::connect::{KeyFile, Login};
+use crate::csv_export;
+use crate::desktop_notify;
+use crate::gui_log::LogBuffer;
+use crate::ip_cache::{self, IpCache};
+use crate::output::{MacCase, OutputFormat, OutputWriter};
+#[cfg(feature = "sqlite")]
+use crate::sqlite_store;
+use crate::tray::{Tray, TrayEvent};
+use crate::watch::Watcher;
 use eframe::egui;
+use ipnet::IpNet;
+use radar_ip_core::oui;
+use radar_ip_core::scanner::{
+    self, CancelToken, Prescan, ScanMode, ScanProgress, ScanRequest, Scanner,
+};
+use radar_ip_core::rdns;
+use radar_ip_core::report::{ScanMatch, ScanReport};
+use radar_ip_core::ssh_client::{
+    self, AuthenticationMethod, HostKeyCheck, JumpHost, ProbeTemplate, SshConfig, SshTimeouts,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-
-/// The supported device profiles.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum DeviceProfile {
-    HC,
-    AI2,
-    AI3,
+use std::time::{Duration, Instant, SystemTime};
+use tracing::level_filters::LevelFilter;
+use tracing::Level;
+
+/// Default limit of a whole GUI scan.
+const DEFAULT_DEADLINE: Duration = Duration::from_secs(15);
+/// Upper bound of the SSH timeout field.
+const MAX_SSH_TIMEOUT_SECS: u64 = 120;
+/// How long the prescan waits for each host.
+const PRESCAN_TIMEOUT: Duration = Duration::from_millis(300);
+/// Scans shown in the history panel and kept in `history.toml`.
+const HISTORY_LEN: usize = 50;
+/// Default time between two scans of a watch, as for `radar-ip watch`.
+const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Previous scans for the history panel, newest first: from the scan
+/// history database with the `sqlite` feature, unless it is unreadable or
+/// still empty, else from `history.toml`.
+fn load_history() -> Vec<HistoryEntry> {
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = config::default_dir().map(|d| d.join(sqlite_store::HISTORY_FILE)) {
+        match sqlite_store::history(&path, None, HISTORY_LEN) {
+            Ok(scans) if !scans.is_empty() => {
+                return scans.into_iter().map(HistoryEntry::from).collect()
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Cannot read the scan history {}: {}", path.display(), e),
+        }
+    }
+    config::load_history()
 }
 
-impl DeviceProfile {
-    /// Environment variable name that holds the private key for this profile.
-    fn env_key_name(self) -> &'static str {
+/// Possible scan states.
+#[derive(Debug, Clone)]
+enum ScanState {
+    Idle,
+    Scanning,
+    /// Every owner of a target, at least one.
+    Found(Vec<ScanMatch>),
+    Error(String),
+}
+
+/// Columns of the results table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Ip,
+    Mac,
+    Hostname,
+    DnsName,
+    Latency,
+}
+
+impl Column {
+    const ALL: [Column; 5] = [
+        Column::Ip,
+        Column::Mac,
+        Column::Hostname,
+        Column::DnsName,
+        Column::Latency,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Ip => "IP",
+            Column::Mac => "MAC",
+            Column::Hostname => "Hostname",
+            Column::DnsName => "DNS name",
+            Column::Latency => "Latency",
+        }
+    }
+
+    /// Sort `matches` by this column.
+    fn sort(self, matches: &mut [ScanMatch], descending: bool) {
         match self {
-            DeviceProfile::HC  => "HC_PRIVATE_KEY",
-            DeviceProfile::AI2 => "AI3_PRIVATE_KEY",
-            DeviceProfile::AI3 => "AI3_PRIVATE_KEY",
+            Column::Ip => matches.sort_by_key(|m| m.ip.parse::<IpAddr>().ok()),
+            Column::Mac => matches.sort_by(|a, b| a.mac.cmp(&b.mac)),
+            Column::Hostname => matches.sort_by(|a, b| a.hostname.cmp(&b.hostname)),
+            Column::DnsName => matches.sort_by(|a, b| a.dns_name.cmp(&b.dns_name)),
+            Column::Latency => matches.sort_by_key(|m| m.latency),
+        }
+        if descending {
+            matches.reverse();
         }
     }
+}
 
-    /// Default IP range for this device type.
-    fn default_ip_range(self) -> &'static str {
+/// Formats of the "Export…" button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
         match self {
-            DeviceProfile::HC  => "10.8.0.0/24",
-            DeviceProfile::AI2 => "10.8.0.0/24",
-            DeviceProfile::AI3 => "192.168.255.0/24",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
         }
     }
 
-    /// Default SSH username for this device type.
-    fn default_user(self) -> &'static str {
+    /// The whole report as pretty JSON, or one CSV row per probed host.
+    fn render(self, report: &ScanReport) -> String {
         match self {
-            DeviceProfile::HC  => "root",
-            DeviceProfile::AI2 => "nano",
-            DeviceProfile::AI3 => "pi",
+            ExportFormat::Json => serde_json::to_string_pretty(report)
+                .expect("a ScanReport always serializes"),
+            ExportFormat::Csv => csv_export::render(&report.hosts, MacCase::default()),
         }
     }
 }
 
-/// Possible scan states.
-#[derive(Debug, Clone)]
-enum ScanState {
-    Idle,
-    Scanning,
-    Found(String),
-    Error(String),
+/// What the file browser is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Purpose {
+    /// Open a private key.
+    Key,
+    /// Save the last report under the typed name.
+    Export { name: String, format: ExportFormat },
+}
+
+/// A minimal file browser, for picking a private key or where to export.
+struct FileBrowser {
+    purpose: Purpose,
+    dir: PathBuf,
+    /// Subdirectories, then files, by name; `.pub` files are left out when
+    /// picking a key.
+    entries: Vec<(String, bool)>,
+    error: Option<String>,
+}
+
+impl FileBrowser {
+    /// Pick a key, starting next to `current`, else in `~/.ssh`, else the
+    /// home or working directory.
+    fn open_key(current: &str) -> Self {
+        let home = home_dir();
+        let dir = Path::new(current)
+            .parent()
+            .filter(|d| d.is_dir())
+            .map(Path::to_path_buf)
+            .or_else(|| home.as_ref().map(|h| h.join(".ssh")).filter(|d| d.is_dir()))
+            .or(home)
+            .unwrap_or_else(|| PathBuf::from("."));
+        Self::new(Purpose::Key, dir)
+    }
+
+    /// Choose where to export, starting in the home directory.
+    fn save_report() -> Self {
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        let name = format!("radar-ip-{}.json", timestamp.replace(':', "-"));
+        let purpose = Purpose::Export {
+            name,
+            format: ExportFormat::Json,
+        };
+        Self::new(purpose, home_dir().unwrap_or_else(|| PathBuf::from(".")))
+    }
+
+    fn new(purpose: Purpose, dir: PathBuf) -> Self {
+        let mut browser = Self {
+            purpose,
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            error: None,
+        };
+        browser.enter(dir);
+        browser
+    }
+
+    fn title(&self) -> &'static str {
+        match self.purpose {
+            Purpose::Key => "Choose a private key",
+            Purpose::Export { .. } => "Export the results",
+        }
+    }
+
+    fn enter(&mut self, dir: PathBuf) {
+        self.entries.clear();
+        self.error = None;
+        match std::fs::read_dir(&dir) {
+            Ok(read) => {
+                for entry in read.flatten() {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    let is_dir = entry.path().is_dir();
+                    if is_dir || self.purpose != Purpose::Key || !name.ends_with(".pub") {
+                        self.entries.push((name, is_dir));
+                    }
+                }
+                self.entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            }
+            Err(e) => self.error = Some(format!("Cannot read {}: {}", dir.display(), e)),
+        }
+        self.dir = dir;
+    }
+
+    /// Draw the browser; returns the chosen path.
+    fn show(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let mut chosen = None;
+        ui.horizontal(|ui| {
+            let parent = self.dir.parent().map(Path::to_path_buf);
+            if ui.add_enabled(parent.is_some(), egui::Button::new("⬆ Up")).clicked() {
+                if let Some(parent) = parent {
+                    self.enter(parent);
+                }
+            }
+            ui.label(egui::RichText::new(self.dir.display().to_string()).monospace());
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::from_rgb(255, 160, 160), error);
+        }
+        ui.separator();
+        let mut open_dir = None;
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            for (name, is_dir) in &self.entries {
+                let label = match (*is_dir, &self.purpose) {
+                    (true, _) => format!("📁 {}", name),
+                    (false, Purpose::Key) => format!("🔑 {}", name),
+                    (false, Purpose::Export { .. }) => format!("📄 {}", name),
+                };
+                if ui.selectable_label(false, label).clicked() {
+                    let path = self.dir.join(name);
+                    match &mut self.purpose {
+                        _ if *is_dir => open_dir = Some(path),
+                        Purpose::Key => chosen = Some(path),
+                        Purpose::Export { name: save_as, .. } => *save_as = name.clone(),
+                    }
+                }
+            }
+        });
+        if let Some(dir) = open_dir {
+            self.enter(dir);
+        }
+
+        if let Purpose::Export { name, format } = &mut self.purpose {
+            ui.separator();
+            ui.horizontal(|ui| {
+                for (choice, label) in [(ExportFormat::Json, "JSON"), (ExportFormat::Csv, "CSV")] {
+                    if ui.selectable_value(format, choice, label).changed() {
+                        let stem = Path::new(name.as_str()).with_extension(choice.extension());
+                        *name = stem.display().to_string();
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(name).desired_width(240.0));
+                if ui.add_enabled(!name.trim().is_empty(), egui::Button::new("💾 Save")).clicked() {
+                    chosen = Some(self.dir.join(name.trim()));
+                }
+            });
+        }
+        chosen
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
 }
 
 /// Main application state.
 pub struct RadarApp {
     mac_input: String,
     ip_range: String,
-    profile: DeviceProfile,
-    prev_profile: DeviceProfile,
+    /// Hosts to skip, e.g. `10.8.0.1,10.8.0.200-250`.
+    exclude: String,
+    /// Filter applied before hosts are probed over SSH.
+    prescan: Prescan,
+    /// Limit of each SSH stage (connect, handshake, auth, command), in
+    /// seconds.
+    ssh_timeout_secs: u64,
+    /// Limit of the whole scan, in seconds.
+    deadline_secs: u64,
+    /// Hosts probed at once.
+    concurrency: usize,
+    /// SSH port, pre-filled from the profile.
+    ssh_port: u16,
+    /// Bastion to tunnel probes through, `[user@]host[:port]`; empty for
+    /// none.
+    via: String,
+    /// Closing the window during a scan or watch hides it in the tray (or
+    /// minimizes it without one) instead, so the scan goes on.
+    background_on_close: bool,
+    /// Scan again `watch_interval_secs` after each scan until stopped,
+    /// notifying when a device shows up, moves or disappears.
+    watch: bool,
+    watch_interval_secs: u64,
+    /// Wakes the running watch for its next scan; `None` unless watching.
+    wake: Option<Arc<tokio::sync::Notify>>,
+    /// When the running watch scans next; `None` while it scans.
+    next_scan: Arc<Mutex<Option<Instant>>>,
+    /// The tray icon, where there is a tray.
+    tray: Option<Tray>,
+    /// Last IP found as the tray shows it.
+    tray_ip: Option<String>,
+    /// "Quit" was picked in the tray: close even while scanning.
+    quitting: bool,
+    /// Desktop notification when a scan finds its device.
+    notify: bool,
+    /// Title last sent to the window.
+    title: String,
+    /// Device types from `profiles.toml` (or the built-ins); never empty.
+    profiles: Vec<Profile>,
+    /// Index of the selected profile.
+    profile: usize,
+    prev_profile: usize,
+    /// Key (with `ssh_password` as its passphrase) or password login.
+    auth: GuiAuth,
+    /// Private key file picked in the GUI, used before the profile's key;
+    /// empty for none.
+    key_file: String,
+    /// The open "Browse…" or "Export…" dialog.
+    browser: Option<FileBrowser>,
+    /// Defaults from `config.toml`, shared with the CLI.
+    defaults: Defaults,
+    /// Networks from `do-not-scan.txt`, skipped on top of `exclude`.
+    do_not_scan: Vec<IpNet>,
+    /// Finished scans, newest first.
+    history: Vec<HistoryEntry>,
+    /// The running scan's history entry, completed when its result is in.
+    pending: Option<(HistoryEntry, Instant)>,
     scan_state: Arc<Mutex<ScanState>>,
+    /// Report of the last finished scan, for "Export…".
+    last_report: Arc<Mutex<Option<ScanReport>>>,
+    /// Outcome of the last export.
+    export_status: Option<String>,
+    /// Credentials of the last scan started, for "Connect".
+    login_config: Option<SshConfig>,
+    /// In-memory keys written out for "Connect"; deleted on exit.
+    key_files: Vec<KeyFile>,
+    /// Why the last "Connect" failed.
+    connect_status: Option<String>,
+    /// Progress of the current scan and when it started; replaced on every
+    /// start and every scan of a watch.
+    progress: Arc<Mutex<(Arc<ScanProgress>, Instant)>>,
+    /// Sort column of the results table, and whether it is descending.
+    sort: (Column, bool),
+    /// Cancels the current scan.
+    cancel: Option<CancelToken>,
+    /// Bumped on every start and cancel; a scan thread only publishes its
+    /// result while the generation it started with is still current.
+    generation: Arc<AtomicU64>,
+    /// Log records shown in the bottom panel.
+    log: LogBuffer,
+    /// Least severe level the log panel shows.
+    log_level: LevelFilter,
     ssh_password: String,
     ssh_user: String,
 }
 
 impl RadarApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        let default_profile = DeviceProfile::HC;
+    pub fn new(cc: &eframe::CreationContext<'_>, log: LogBuffer) -> Self {
+        let profiles = config::load_profiles();
         let ssh_password = std::env::var("SSH_PASSWORD").unwrap_or_default();
+        let file_config = config::load(None, None, None).unwrap_or_else(|e| {
+            tracing::warn!("{}; ignoring the config directory", e);
+            config::Config::default()
+        });
+        let defaults = file_config.defaults;
 
-        Self {
+        let mut app = Self {
             mac_input: String::new(),
-            ip_range: default_profile.default_ip_range().to_string(),
-            profile: default_profile,
-            prev_profile: default_profile,
+            ip_range: if defaults.range.is_empty() {
+                profiles[0].ip_range.clone()
+            } else {
+                defaults.range.join(",")
+            },
+            exclude: String::new(),
+            prescan: Prescan::Tcp(PRESCAN_TIMEOUT),
+            ssh_timeout_secs: defaults.timeout_sec.unwrap_or(3),
+            deadline_secs: DEFAULT_DEADLINE.as_secs(),
+            concurrency: defaults.concurrency.unwrap_or(scanner::DEFAULT_CONCURRENCY),
+            ssh_port: profiles[0].port,
+            via: String::new(),
+            background_on_close: false,
+            watch: false,
+            watch_interval_secs: DEFAULT_WATCH_INTERVAL.as_secs(),
+            wake: None,
+            next_scan: Arc::new(Mutex::new(None)),
+            tray: Tray::start(&cc.egui_ctx),
+            tray_ip: None,
+            quitting: false,
+            notify: false,
+            title: String::new(),
+            ssh_user: defaults.user.clone().unwrap_or_else(|| profiles[0].user.clone()),
+            profiles,
+            profile: 0,
+            prev_profile: 0,
+            auth: GuiAuth::Key,
+            key_file: String::new(),
+            browser: None,
+            defaults,
+            do_not_scan: file_config.do_not_scan,
+            history: load_history(),
+            pending: None,
             scan_state: Arc::new(Mutex::new(ScanState::Idle)),
+            last_report: Arc::new(Mutex::new(None)),
+            export_status: None,
+            login_config: None,
+            key_files: Vec::new(),
+            connect_status: None,
+            progress: Arc::new(Mutex::new((Arc::default(), Instant::now()))),
+            sort: (Column::Ip, false),
+            cancel: None,
+            generation: Arc::new(AtomicU64::new(0)),
+            log,
+            log_level: LevelFilter::INFO,
             ssh_password,
-            ssh_user: default_profile.default_user().to_string(),
+        };
+        if let Some(state) = config::load_gui_state() {
+            app.restore(state);
+        }
+        app
+    }
+
+    /// Bring back the inputs of the last session.
+    fn restore(&mut self, state: GuiState) {
+        if let Some(i) = self.profiles.iter().position(|p| p.name == state.profile) {
+            self.profile = i;
+            self.prev_profile = i;
+            self.ssh_port = self.profiles[i].port;
+            if self.defaults.user.is_none() {
+                self.ssh_user = self.profiles[i].user.clone();
+            }
+        }
+        if !state.user.is_empty() {
+            self.ssh_user = state.user;
+        }
+        self.mac_input = state.mac;
+        if !state.ip_range.is_empty() {
+            self.ip_range = state.ip_range;
+        }
+        self.exclude = state.exclude;
+        self.auth = state.auth;
+        self.prescan = match state.prescan {
+            GuiPrescan::None => Prescan::None,
+            GuiPrescan::Tcp => Prescan::Tcp(PRESCAN_TIMEOUT),
+            GuiPrescan::Ping => Prescan::Ping(PRESCAN_TIMEOUT),
+        };
+        if let Some(port) = state.port {
+            self.ssh_port = port;
+        }
+        if let Some(secs) = state.deadline_secs {
+            self.deadline_secs = secs.max(1);
+        }
+        if let Some(concurrency) = state.concurrency {
+            self.concurrency = concurrency.max(1);
+        }
+        if let Some(secs) = state.ssh_timeout_secs {
+            self.ssh_timeout_secs = secs.clamp(1, MAX_SSH_TIMEOUT_SECS);
+        }
+        self.via = state.via;
+        self.background_on_close = state.background_on_close;
+        self.watch = state.watch;
+        if let Some(secs) = state.watch_interval_secs {
+            self.watch_interval_secs = secs.max(1);
+        }
+        self.notify = state.notify;
+        self.key_file = state.key_file;
+    }
+
+    /// A sortable table of every match, one copy button per row.
+    fn results_table(&mut self, ui: &mut egui::Ui, mut matches: Vec<ScanMatch>) {
+        let (column, descending) = self.sort;
+        column.sort(&mut matches, descending);
+
+        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            egui::Grid::new("results_table")
+                .num_columns(6)
+                .striped(true)
+                .spacing([14.0, 6.0])
+                .show(ui, |ui| {
+                    for header in Column::ALL {
+                        let arrow = match (header == column, descending) {
+                            (true, false) => " ⏶",
+                            (true, true) => " ⏷",
+                            (false, _) => "",
+                        };
+                        let title =
+                            egui::RichText::new(format!("{}{}", header.title(), arrow)).strong();
+                        if ui.add(egui::Button::new(title).frame(false)).clicked() {
+                            self.sort = (header, header == column && !descending);
+                        }
+                    }
+                    ui.end_row();
+
+                    for m in &matches {
+                        ui.label(egui::RichText::new(&m.ip).monospace());
+                        ui.label(egui::RichText::new(&m.mac).monospace());
+                        ui.label(m.hostname.as_deref().unwrap_or("-"));
+                        ui.label(m.dns_name.as_deref().unwrap_or("-"));
+                        ui.label(match m.latency {
+                            Some(latency) => format!("{} ms", latency.as_millis()),
+                            None => "-".into(),
+                        });
+                        if ui.button("📋").on_hover_text("Copy IP").clicked() {
+                            ui.output_mut(|o| o.copied_text = m.ip.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    /// The collapsible log viewer, newest lines at the bottom.
+    fn log_panel(&mut self, ui: &mut egui::Ui) {
+        let lines = self.log.lines(self.log_level);
+        egui::CollapsingHeader::new(format!("Log ({})", lines.len()))
+            .id_salt("log_panel")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (level, name) in [
+                        (LevelFilter::ERROR, "Error"),
+                        (LevelFilter::WARN, "Warn"),
+                        (LevelFilter::INFO, "Info"),
+                        (LevelFilter::DEBUG, "Debug"),
+                    ] {
+                        ui.selectable_value(&mut self.log_level, level, name);
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.log.clear();
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .auto_shrink([false, true])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &lines {
+                            let time = humantime::format_rfc3339_seconds(line.time).to_string();
+                            let color = match line.level {
+                                Level::ERROR => egui::Color32::from_rgb(255, 90, 90),
+                                Level::WARN => egui::Color32::from_rgb(255, 200, 80),
+                                Level::INFO => egui::Color32::from_gray(200),
+                                _ => egui::Color32::from_gray(130),
+                            };
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} {:<5} {}",
+                                    &time[11..19],
+                                    line.level,
+                                    line.message
+                                ))
+                                .monospace()
+                                .size(11.0)
+                                .color(color),
+                            );
+                        }
+                    });
+            });
+    }
+
+    /// Record the result of the scan that just ended in the history.
+    fn finish_history(&mut self, state: &ScanState) {
+        let Some((mut entry, started)) = self.pending.take() else {
+            return;
+        };
+        match state {
+            ScanState::Found(matches) => {
+                let ips: Vec<&str> = matches.iter().map(|m| m.ip.as_str()).collect();
+                entry.ip = Some(ips.join(", "));
+            }
+            ScanState::Error(msg) => entry.error = Some(msg.clone()),
+            ScanState::Idle | ScanState::Scanning => return,
+        }
+        entry.duration_secs = started.elapsed().as_secs_f64();
+        #[cfg(feature = "sqlite")]
+        let recorded = self.record_history(&entry, started.elapsed());
+        #[cfg(not(feature = "sqlite"))]
+        let recorded = false;
+        self.history.insert(0, entry);
+        self.history.truncate(HISTORY_LEN);
+        if recorded {
+            return;
+        }
+        if let Err(e) = config::save_history(&self.history) {
+            tracing::warn!("Cannot save the scan history: {}", e);
+        }
+    }
+
+    /// Append the scan `entry` describes, with its report, to the scan
+    /// history database shared with the CLI. Returns whether it worked.
+    #[cfg(feature = "sqlite")]
+    fn record_history(&self, entry: &HistoryEntry, elapsed: Duration) -> bool {
+        let Some(path) = config::default_dir().map(|d| d.join(sqlite_store::HISTORY_FILE)) else {
+            return false;
+        };
+        let report = self.last_report.lock().unwrap().clone();
+        let targets: Vec<String> = entry.mac.split(',').map(|m| m.trim().to_string()).collect();
+        let record = sqlite_store::ScanRecord {
+            started_at: humantime::parse_rfc3339(&entry.timestamp).unwrap_or(SystemTime::now()),
+            source: "gui",
+            profile: Some(&entry.profile),
+            targets: &targets,
+            range: &entry.ip_range,
+            elapsed,
+            report: report.as_ref(),
+            error: entry.error.as_deref(),
+        };
+        match sqlite_store::record_scan(&path, &record) {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::warn!("Cannot record the scan in {}: {}", path.display(), e);
+                false
+            }
+        }
+    }
+
+    /// The window title: progress while scanning, then the last IP found.
+    fn window_title(&self, state: &ScanState) -> String {
+        let progress = self.progress.lock().unwrap().0.clone();
+        match state {
+            ScanState::Scanning => format!(
+                "Radar-IP Scanner — scanning {}/{}",
+                progress.done(),
+                progress.total()
+            ),
+            ScanState::Found(matches) if matches.len() > 1 => {
+                format!("Radar-IP Scanner — {} devices found", matches.len())
+            }
+            ScanState::Found(matches) => format!("Radar-IP Scanner — {}", matches[0].ip),
+            ScanState::Idle | ScanState::Error(_) => match self.last_found(state) {
+                Some(ip) => format!("Radar-IP Scanner — last found {}", ip),
+                None => "Radar-IP Scanner".into(),
+            },
+        }
+    }
+
+    /// The IPs of the last scan that found something.
+    fn last_found(&self, state: &ScanState) -> Option<String> {
+        match state {
+            ScanState::Found(matches) => {
+                let ips: Vec<&str> = matches.iter().map(|m| m.ip.as_str()).collect();
+                Some(ips.join(", "))
+            }
+            _ => self.history.iter().find_map(|entry| entry.ip.clone()),
+        }
+    }
+
+    /// Act on what was picked in the tray.
+    fn tray_event(&mut self, ctx: &egui::Context, event: TrayEvent, state: &ScanState) {
+        match event {
+            TrayEvent::ScanNow if matches!(state, ScanState::Scanning) => {}
+            TrayEvent::ScanNow => match &self.wake {
+                Some(wake) => wake.notify_one(),
+                None if !self.mac_input.trim().is_empty() => self.start_scan(ctx.clone()),
+                None => ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true)),
+            },
+            TrayEvent::Show => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+            TrayEvent::Quit => {
+                self.quitting = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    /// Open a terminal window logged into `ip` with the credentials of the
+    /// scan that found it.
+    fn connect(&mut self, ip: &str) {
+        let Some(config) = &self.login_config else {
+            return;
+        };
+        tracing::info!("Opening an SSH session to {}", ip);
+        self.connect_status = match Login::new(config, ip).and_then(Login::open_terminal) {
+            Ok(key_file) => {
+                self.key_files.extend(key_file);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Cannot open an SSH session to {}: {}", ip, e);
+                Some(format!("Cannot open a terminal: {}", e))
+            }
+        };
+    }
+
+    /// Write the last report to `path`.
+    fn export(&mut self, path: &Path, format: ExportFormat) {
+        let Some(report) = self.last_report.lock().unwrap().clone() else {
+            return;
+        };
+        self.export_status = Some(match std::fs::write(path, format.render(&report)) {
+            Ok(()) => {
+                tracing::info!("Exported {} host(s) to {}", report.hosts.len(), path.display());
+                format!("Saved to {}", path.display())
+            }
+            Err(e) => {
+                tracing::warn!("Cannot export to {}: {}", path.display(), e);
+                format!("Cannot write {}: {}", path.display(), e)
+            }
+        });
+    }
+
+    /// Save the current inputs for the next launch.
+    fn save_state(&self) {
+        let state = GuiState {
+            mac: self.mac_input.clone(),
+            ip_range: self.ip_range.clone(),
+            exclude: self.exclude.clone(),
+            profile: self.profiles[self.profile].name.clone(),
+            user: self.ssh_user.trim().to_string(),
+            auth: self.auth,
+            prescan: match self.prescan {
+                Prescan::None => GuiPrescan::None,
+                Prescan::Tcp(_) => GuiPrescan::Tcp,
+                Prescan::Ping(_) => GuiPrescan::Ping,
+            },
+            ssh_timeout_secs: Some(self.ssh_timeout_secs),
+            deadline_secs: Some(self.deadline_secs),
+            concurrency: Some(self.concurrency),
+            port: Some(self.ssh_port).filter(|&port| port != self.profiles[self.profile].port),
+            via: self.via.clone(),
+            background_on_close: self.background_on_close,
+            watch: self.watch,
+            watch_interval_secs: Some(self.watch_interval_secs),
+            notify: self.notify,
+            key_file: self.key_file.clone(),
+        };
+        if let Err(e) = config::save_gui_state(&state) {
+            tracing::warn!("Cannot save the GUI state: {}", e);
         }
     }
 }
 
 impl eframe::App for RadarApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_state();
+        self.cancel_scan();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Auto-fill IP range and SSH user when the user switches device profile.
+        // Auto-fill IP range, SSH user and port when the user switches
+        // device profile.
         if self.profile != self.prev_profile {
-            self.ip_range = self.profile.default_ip_range().to_string();
-            self.ssh_user = self.profile.default_user().to_string();
+            let profile = &self.profiles[self.profile];
+            self.ip_range = profile.ip_range.clone();
+            self.ssh_user = profile.user.clone();
+            self.ssh_port = profile.port;
             self.prev_profile = self.profile;
         }
 
         // Request repaint while scanning so the UI stays responsive.
         let current_state = self.scan_state.lock().unwrap().clone();
+        if !matches!(current_state, ScanState::Scanning) {
+            self.finish_history(&current_state);
+        }
         if matches!(current_state, ScanState::Scanning) {
             ctx.request_repaint();
         }
 
+        while let Some(event) = self.tray.as_ref().and_then(Tray::event) {
+            self.tray_event(ctx, event, &current_state);
+        }
+        if !matches!(current_state, ScanState::Scanning) {
+            let ip = self.last_found(&current_state);
+            if let Some(tray) = self.tray.as_ref().filter(|_| ip != self.tray_ip) {
+                tray.set_last_found(ip.as_deref());
+                self.tray_ip = ip;
+            }
+        }
+        // Keep a running scan or watch alive when the window is closed: in
+        // the tray if there is one, else minimized, where the taskbar entry
+        // shows how it is going.
+        let busy = matches!(current_state, ScanState::Scanning) || self.wake.is_some();
+        let closing = ctx.input(|i| i.viewport().close_requested()) && !self.quitting;
+        if closing && self.background_on_close && busy {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            if self.tray.is_some() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+            }
+        }
+        let title = self.window_title(&current_state);
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
+
         // ── Custom dark theme with accent ────────────────────────────────
         let mut style = (*ctx.style()).clone();
         style.visuals = egui::Visuals::dark();
         ctx.set_style(style);
 
+        if let Some(browser) = &mut self.browser {
+            let mut open = true;
+            let mut chosen = None;
+            egui::Window::new(browser.title())
+                .open(&mut open)
+                .collapsible(false)
+                .default_width(360.0)
+                .show(ctx, |ui| chosen = browser.show(ui));
+            if let Some(path) = chosen {
+                match browser.purpose.clone() {
+                    Purpose::Key => self.key_file = path.display().to_string(),
+                    Purpose::Export { format, .. } => self.export(&path, format),
+                }
+                open = false;
+            }
+            if !open {
+                self.browser = None;
+            }
+        }
+
+        egui::TopBottomPanel::bottom("log").show(ctx, |ui| self.log_panel(ui));
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.add_space(20.0);
@@ -132,25 +863,81 @@ impl eframe::App for RadarApp {
                             .size(15.0)
                             .color(egui::Color32::from_rgb(180, 220, 255)),
                     );
-                    ui.horizontal(|ui| {
-                        ui.selectable_value(&mut self.profile, DeviceProfile::HC, "🏠 HC");
-                        ui.selectable_value(&mut self.profile, DeviceProfile::AI2, "📦 AI2");
-                        ui.selectable_value(&mut self.profile, DeviceProfile::AI3, "🤖 AI3");
+                    ui.horizontal_wrapped(|ui| {
+                        for (i, profile) in self.profiles.iter().enumerate() {
+                            ui.selectable_value(&mut self.profile, i, &profile.name);
+                        }
                     });
                     ui.end_row();
 
-                    // SSH User (read-only, auto-filled from profile)
+                    // SSH User (auto-filled from profile, editable)
                     ui.label(
                         egui::RichText::new("SSH User")
                             .size(15.0)
                             .color(egui::Color32::from_rgb(180, 220, 255)),
                     );
+                    let user_edit = egui::TextEdit::singleline(&mut self.ssh_user)
+                        .hint_text(&self.profiles[self.profile].user)
+                        .desired_width(260.0)
+                        .font(egui::TextStyle::Monospace);
+                    ui.add(user_edit);
+                    ui.end_row();
+
+                    // Authentication method
+                    ui.label(
+                        egui::RichText::new("Login")
+                            .size(15.0)
+                            .color(egui::Color32::from_rgb(180, 220, 255)),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.auth, GuiAuth::Key, "Key");
+                        ui.selectable_value(&mut self.auth, GuiAuth::Password, "Password");
+                    });
+                    ui.end_row();
+
+                    // Private key file, overriding the profile's key
+                    if self.auth == GuiAuth::Key {
+                        ui.label(
+                            egui::RichText::new("SSH Key")
+                                .size(15.0)
+                                .color(egui::Color32::from_rgb(180, 220, 255)),
+                        );
+                        ui.horizontal(|ui| {
+                            let key_edit = egui::TextEdit::singleline(&mut self.key_file)
+                                .hint_text("from profile or ssh-agent")
+                                .desired_width(180.0)
+                                .font(egui::TextStyle::Monospace);
+                            ui.add(key_edit);
+                            if ui.button("Browse…").clicked() {
+                                self.browser = Some(FileBrowser::open_key(&self.key_file));
+                            }
+                            if !self.key_file.is_empty()
+                                && ui.button("✖").on_hover_text("Use the profile's key").clicked()
+                            {
+                                self.key_file.clear();
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    // Password, or the key's passphrase; never saved
+                    let password_label = match self.auth {
+                        GuiAuth::Key => "Passphrase",
+                        GuiAuth::Password => "Password",
+                    };
                     ui.label(
-                        egui::RichText::new(&self.ssh_user)
+                        egui::RichText::new(password_label)
                             .size(15.0)
-                            .family(egui::FontFamily::Monospace)
-                            .color(egui::Color32::from_rgb(200, 200, 200)),
+                            .color(egui::Color32::from_rgb(180, 220, 255)),
                     );
+                    let password_edit = egui::TextEdit::singleline(&mut self.ssh_password)
+                        .password(true)
+                        .hint_text(match self.auth {
+                            GuiAuth::Key => "if the key is encrypted",
+                            GuiAuth::Password => "SSH password",
+                        })
+                        .desired_width(260.0);
+                    ui.add(password_edit);
                     ui.end_row();
 
                     // MAC address
@@ -160,7 +947,7 @@ impl eframe::App for RadarApp {
                             .color(egui::Color32::from_rgb(180, 220, 255)),
                     );
                     let mac_edit = egui::TextEdit::singleline(&mut self.mac_input)
-                        .hint_text("aa:bb:cc:dd:ee:ff")
+                        .hint_text("aa:bb:cc:dd:ee:ff, b8:27:eb:*")
                         .desired_width(260.0)
                         .font(egui::TextStyle::Monospace);
                     ui.add(mac_edit);
@@ -178,7 +965,95 @@ impl eframe::App for RadarApp {
                         .font(egui::TextStyle::Monospace);
                     ui.add(range_edit);
                     ui.end_row();
+
+                    // Hosts to skip
+                    ui.label(
+                        egui::RichText::new("Exclude")
+                            .size(15.0)
+                            .color(egui::Color32::from_rgb(180, 220, 255)),
+                    );
+                    let exclude_edit = egui::TextEdit::singleline(&mut self.exclude)
+                        .hint_text("192.168.1.1,192.168.1.200-250")
+                        .desired_width(260.0)
+                        .font(egui::TextStyle::Monospace);
+                    ui.add(exclude_edit);
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            egui::CollapsingHeader::new("Advanced").show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Prescan").on_hover_text(
+                        "Skip hosts that do not answer before trying SSH. \
+                         Ping falls back to TCP without ICMP privileges.",
+                    );
+                    ui.selectable_value(&mut self.prescan, Prescan::None, "None");
+                    ui.selectable_value(&mut self.prescan, Prescan::Tcp(PRESCAN_TIMEOUT), "TCP");
+                    ui.selectable_value(&mut self.prescan, Prescan::Ping(PRESCAN_TIMEOUT), "Ping");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("SSH timeout").on_hover_text(
+                        "Limit of each step of a probe: connect, handshake, \
+                         authentication and every command.",
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.ssh_timeout_secs)
+                            .range(1..=MAX_SSH_TIMEOUT_SECS)
+                            .suffix(" s"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Scan deadline").on_hover_text(
+                        "Give up on the whole scan after this long. Raise it \
+                         for slow links or large ranges.",
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.deadline_secs)
+                            .range(1..=3600)
+                            .suffix(" s"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Concurrency").on_hover_text("Hosts probed at the same time.");
+                    ui.add(egui::DragValue::new(&mut self.concurrency).range(1..=1024));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("SSH port").on_hover_text("Defaults to the profile's port.");
+                    ui.add(egui::DragValue::new(&mut self.ssh_port).range(1..=65535));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Jump host").on_hover_text(
+                        "Tunnel every probe through this bastion, logging in \
+                         with the same key.",
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.via)
+                            .hint_text("user@bastion:22")
+                            .desired_width(200.0)
+                            .font(egui::TextStyle::Monospace),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.watch, "Watch, scanning every").on_hover_text(
+                        "Scan again this long after each scan until stopped, and \
+                         log (or notify, with the option below) each device that \
+                         shows up, changes IP or stops answering.",
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut self.watch_interval_secs)
+                            .range(1..=86400)
+                            .suffix(" s"),
+                    );
                 });
+                ui.checkbox(&mut self.notify, "Notify when found")
+                    .on_hover_text("Show a desktop notification with the IP.");
+                ui.checkbox(&mut self.background_on_close, "Keep scanning when closed")
+                    .on_hover_text(
+                        "Closing the window during a scan or watch hides it in \
+                         the tray, or minimizes it without one, and the scan goes \
+                         on; quit from the tray, or close the window once idle.",
+                    );
+            });
 
             ui.add_space(20.0);
 
@@ -206,6 +1081,24 @@ impl eframe::App for RadarApp {
                 if btn.clicked() && !is_scanning && !self.mac_input.trim().is_empty() {
                     self.start_scan(ctx.clone());
                 }
+
+                if self.wake.is_some() {
+                    ui.add_space(8.0);
+                    let next = *self.next_scan.lock().unwrap();
+                    let status = match next {
+                        Some(at) => {
+                            let left = at.saturating_duration_since(Instant::now());
+                            // Tick the countdown once a second.
+                            ctx.request_repaint_after(Duration::from_secs(1));
+                            format!("👁 Watching · next scan in {} s", left.as_secs())
+                        }
+                        None => "👁 Watching".to_string(),
+                    };
+                    ui.label(egui::RichText::new(status).color(egui::Color32::from_gray(170)));
+                    if ui.button("⏹ Stop watching").clicked() {
+                        self.cancel_scan();
+                    }
+                }
             });
 
             ui.add_space(20.0);
@@ -223,15 +1116,46 @@ impl eframe::App for RadarApp {
                         );
                     }
                     ScanState::Scanning => {
-                        ui.spinner();
+                        let (progress, started) = self.progress.lock().unwrap().clone();
+                        let (done, total) = (progress.done(), progress.total());
+                        let bar = match total {
+                            0 => egui::ProgressBar::new(0.0)
+                                .text("Preparing the range...")
+                                .animate(true),
+                            total => egui::ProgressBar::new(done as f32 / total as f32)
+                                .text(format!("{}/{} hosts", done, total)),
+                        };
+                        ui.add(bar.desired_width(320.0));
                         ui.add_space(8.0);
                         ui.label(
-                            egui::RichText::new("Scanning network, please wait...")
-                                .size(14.0)
-                                .color(egui::Color32::from_rgb(255, 200, 80)),
+                            egui::RichText::new(format!(
+                                "{} found · {} errors · {:.1}s",
+                                progress.found(),
+                                progress.failed(),
+                                started.elapsed().as_secs_f64()
+                            ))
+                            .size(14.0)
+                            .color(egui::Color32::from_rgb(255, 200, 80)),
                         );
+                        ui.add_space(8.0);
+                        let escape = ui.input(|i| i.key_pressed(egui::Key::Escape));
+                        if ui.button("✖ Cancel").on_hover_text("Esc").clicked() || escape {
+                            self.cancel_scan();
+                        }
                     }
-                    ScanState::Found(ip) => {
+                    ScanState::Found(matches) if matches.len() > 1 => {
+                        ui.label(
+                            egui::RichText::new(format!("✅ {} Devices Found", matches.len()))
+                                .size(18.0)
+                                .strong()
+                                .color(egui::Color32::from_rgb(80, 220, 100)),
+                        );
+                        ui.add_space(10.0);
+                        self.results_table(ui, matches.clone());
+                    }
+                    ScanState::Found(matches) => {
+                        let ip = &matches[0].ip;
+                        let mut connect_to = None;
                         ui.label(
                             egui::RichText::new("✅ Device Found!")
                                 .size(18.0)
@@ -257,8 +1181,40 @@ impl eframe::App for RadarApp {
                                     if ui.button("📋 Copy").clicked() {
                                         ui.output_mut(|o| o.copied_text = ip.clone());
                                     }
+                                    if ui
+                                        .button("🖥 Connect")
+                                        .on_hover_text("Open an SSH session in a terminal")
+                                        .clicked()
+                                    {
+                                        connect_to = Some(ip.clone());
+                                    }
                                 });
                             });
+                        // The device's own name guards against a cloned
+                        // image answering with the right MAC.
+                        let (hostname, dns_name) = (&matches[0].hostname, &matches[0].dns_name);
+                        let dns_name =
+                            dns_name.as_ref().filter(|&name| Some(name) != hostname.as_ref());
+                        for name in hostname.iter().chain(dns_name) {
+                            ui.add_space(4.0);
+                            ui.label(
+                                egui::RichText::new(name)
+                                    .size(14.0)
+                                    .monospace()
+                                    .color(egui::Color32::from_gray(180)),
+                            );
+                        }
+                        if let Some(ip) = connect_to {
+                            self.connect(&ip);
+                        }
+                        if let Some(status) = &self.connect_status {
+                            ui.add_space(4.0);
+                            ui.label(
+                                egui::RichText::new(status)
+                                    .size(12.0)
+                                    .color(egui::Color32::from_rgb(255, 160, 160)),
+                            );
+                        }
                     }
                     ScanState::Error(msg) => {
                         ui.label(
@@ -275,8 +1231,88 @@ impl eframe::App for RadarApp {
                         );
                     }
                 }
+
+                if !is_scanning && self.last_report.lock().unwrap().is_some() {
+                    ui.add_space(8.0);
+                    if ui
+                        .button("💾 Export…")
+                        .on_hover_text("Save every probed host and its error as JSON or CSV")
+                        .clicked()
+                    {
+                        self.browser = Some(FileBrowser::save_report());
+                    }
+                    if let Some(status) = &self.export_status {
+                        ui.label(
+                            egui::RichText::new(status)
+                                .size(12.0)
+                                .color(egui::Color32::from_gray(160)),
+                        );
+                    }
+                }
             });
 
+            // ── History ───────────────────────────────────────────────
+            ui.add_space(12.0);
+            let mut rescan = None;
+            egui::CollapsingHeader::new(format!("History ({})", self.history.len())).show(
+                ui,
+                |ui| {
+                    if self.history.is_empty() {
+                        ui.label(
+                            egui::RichText::new("No scans yet")
+                                .color(egui::Color32::from_gray(120)),
+                        );
+                    }
+                    egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                        for (i, entry) in self.history.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let when = entry.timestamp.replace('T', " ").replace('Z', "");
+                                ui.label(egui::RichText::new(when).monospace().size(11.0));
+                                ui.label(egui::RichText::new(&entry.mac).monospace());
+                                match &entry.ip {
+                                    Some(ip) => ui.label(
+                                        egui::RichText::new(format!("→ {}", ip))
+                                            .monospace()
+                                            .color(egui::Color32::from_rgb(100, 255, 130)),
+                                    ),
+                                    None => ui
+                                        .label(
+                                            egui::RichText::new("→ not found")
+                                                .color(egui::Color32::from_rgb(255, 160, 160)),
+                                        )
+                                        .on_hover_text(entry.error.as_deref().unwrap_or_default()),
+                                };
+                                ui.label(format!("{:.1}s", entry.duration_secs));
+                                if ui
+                                    .add_enabled(!is_scanning, egui::Button::new("↻"))
+                                    .on_hover_text("Scan again")
+                                    .clicked()
+                                {
+                                    rescan = Some(i);
+                                }
+                                if let Some(ip) = &entry.ip {
+                                    if ui.button("📋").on_hover_text("Copy IP").clicked() {
+                                        ui.output_mut(|o| o.copied_text = ip.clone());
+                                    }
+                                }
+                            });
+                        }
+                    });
+                },
+            );
+            if let Some(i) = rescan {
+                let entry = self.history[i].clone();
+                if let Some(p) = self.profiles.iter().position(|p| p.name == entry.profile) {
+                    self.profile = p;
+                    self.prev_profile = p;
+                    self.ssh_user = self.profiles[p].user.clone();
+                    self.ssh_port = self.profiles[p].port;
+                }
+                self.mac_input = entry.mac;
+                self.ip_range = entry.ip_range;
+                self.start_scan(ctx.clone());
+            }
+
             // ── Footer ────────────────────────────────────────────────
             ui.add_space(20.0);
             ui.vertical_centered(|ui| {
@@ -291,61 +1327,305 @@ impl eframe::App for RadarApp {
 }
 
 impl RadarApp {
-    /// Kick off the scan in a background Tokio task.
+    /// Kick off the scan in a background Tokio task, and with "Watch" the
+    /// scans after it. Stops the watch running before.
     fn start_scan(&mut self, ctx: egui::Context) {
+        self.save_state();
+        if let Some(cancel) = self.cancel.take() {
+            cancel.cancel();
+        }
+        self.wake = None;
         let mac = self.mac_input.trim().to_string();
+        // Several MACs or OUI prefixes, e.g. `aa:bb:cc:dd:ee:ff, b8:27:eb:*`.
+        let targets: Vec<String> = mac
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect();
         let ip_range = self.ip_range.trim().to_string();
-        let profile = self.profile;
+        let profile = self.profiles[self.profile].clone();
         let password = self.ssh_password.clone();
-        let user = self.ssh_user.clone();
+        // An emptied field falls back to the profile's user.
+        let user = match self.ssh_user.trim() {
+            "" => profile.user.clone(),
+            user => user.to_string(),
+        };
+        let prescan = self.prescan;
+        let base = Duration::from_secs(self.ssh_timeout_secs);
+        let timeouts = SshTimeouts {
+            connect: self.defaults.connect_timeout.unwrap_or(base),
+            handshake: self.defaults.handshake_timeout.unwrap_or(base),
+            auth: self.defaults.auth_timeout.unwrap_or(base),
+            command: self.defaults.command_timeout.unwrap_or(base),
+        };
+        let concurrency = self.concurrency;
+        let deadline = Duration::from_secs(self.deadline_secs);
+        let notify = self.notify;
+        let watch = self.watch.then(|| Duration::from_secs(self.watch_interval_secs));
+        let jitter = Duration::from_millis(self.defaults.jitter_ms.unwrap_or(0));
         let state = self.scan_state.clone();
+        let last_report = self.last_report.clone();
+        *last_report.lock().unwrap() = None;
+        self.export_status = None;
+        self.connect_status = None;
+
+        // Fresh progress and cancel token, so nothing leaks from a previous
+        // run.
+        let generation = self.generation.clone();
+        let scan_id = generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut progress = Arc::new(ScanProgress::default());
+        *self.progress.lock().unwrap() = (progress.clone(), Instant::now());
+        let shared_progress = self.progress.clone();
+        let cancel = CancelToken::default();
+        self.cancel = Some(cancel.clone());
 
         // Mark as scanning.
         *state.lock().unwrap() = ScanState::Scanning;
 
-        // Load the private key from the environment variable.
-        let key_env = profile.env_key_name();
-        let key_data = match std::env::var(key_env) {
-            Ok(k) if !k.is_empty() => k,
-            _ => {
-                *state.lock().unwrap() = ScanState::Error(format!(
-                    "Private key not found in environment variable '{}'.\n\
-                     Make sure .env is present and contains {}.",
-                    key_env, key_env
-                ));
+        // Like `radar-ip watch`, which tells devices apart by their whole
+        // MAC.
+        if let Some(prefix) = targets.iter().find(|t| oui::is_prefix(t)).filter(|_| self.watch) {
+            *state.lock().unwrap() = ScanState::Error(format!(
+                "Watch needs whole MAC addresses, not the prefix {}.",
+                prefix
+            ));
+            ctx.request_repaint();
+            return;
+        }
+
+        let mut do_not_scan = match scanner::parse_exclusions(&self.exclude) {
+            Ok(nets) => nets,
+            Err(e) => {
+                *state.lock().unwrap() = ScanState::Error(format!("Invalid exclusion: {}", e));
                 ctx.request_repaint();
                 return;
             }
         };
+        do_not_scan.extend(self.do_not_scan.iter().copied());
 
-        let auth = AuthenticationMethod::PrivateKeyMemory {
-            key_data,
-            passphrase: Some(password),
+        let via = match self.via.trim() {
+            "" => None,
+            via => match JumpHost::parse(via) {
+                Ok(jump) => Some(jump),
+                Err(e) => {
+                    *state.lock().unwrap() = ScanState::Error(format!("Invalid jump host: {}", e));
+                    ctx.request_repaint();
+                    return;
+                }
+            },
+        };
+
+        let probe_regex = match profile.probe_regex.as_deref().map(regex::Regex::new).transpose() {
+            Ok(regex) => regex,
+            Err(e) => {
+                *state.lock().unwrap() = ScanState::Error(format!("Invalid probe_regex: {}", e));
+                ctx.request_repaint();
+                return;
+            }
+        };
+        let probe = profile.probe_cmd.map(|command| ProbeTemplate::new(command, probe_regex));
+
+        // The key picked with Browse…, else the profile's environment
+        // variable, else its key file, else the `key` of config.toml, else
+        // the ssh-agent.
+        let picked = match self.key_file.trim() {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        };
+        let key_data = profile
+            .key_env
+            .as_deref()
+            .filter(|_| picked.is_none())
+            .and_then(|var| std::env::var(var).ok())
+            .filter(|k| !k.is_empty());
+        let key_file = picked.or(profile.key).or_else(|| self.defaults.key.clone());
+        let auth = match self.auth {
+            GuiAuth::Password if password.is_empty() => {
+                *state.lock().unwrap() = ScanState::Error("Enter the SSH password.".into());
+                ctx.request_repaint();
+                return;
+            }
+            GuiAuth::Password => AuthenticationMethod::Password(password),
+            GuiAuth::Key => match (key_data, key_file) {
+                (Some(key_data), _) => AuthenticationMethod::PrivateKeyMemory {
+                    key_data,
+                    passphrase: Some(password),
+                },
+                (None, Some(path)) => AuthenticationMethod::PrivateKey {
+                    path,
+                    passphrase: Some(password),
+                },
+                (None, None) if ssh_client::agent_available() => AuthenticationMethod::Agent,
+                (None, None) => {
+                    *state.lock().unwrap() = ScanState::Error(match &profile.key_env {
+                        Some(key_env) => format!(
+                            "Private key not found in environment variable '{}'.\n\
+                             Choose a key file with Browse…, or make sure .env \
+                             is present and contains {}.",
+                            key_env, key_env
+                        ),
+                        None => format!(
+                            "Profile '{}' has no key and no ssh-agent is running.\n\
+                             Choose a key file with Browse….",
+                            profile.name
+                        ),
+                    });
+                    ctx.request_repaint();
+                    return;
+                }
+            },
         };
 
         let config = SshConfig {
             user,
-            port: 22,
+            port: self.ssh_port,
             auth,
-            timeout: Duration::from_secs(3), // per-host TCP timeout
+            timeouts,
+            pinned_host_keys: HashMap::new(),
+            host_key_check: HostKeyCheck::Disabled,
+            interface_filter: None,
+            probe,
+            platform: profile.platform,
+            via,
+            details: false,
+            pool: None,
         };
+        self.login_config = Some(config.clone());
 
-        // Spawn a background thread with a 15-second overall scan deadline.
+        let entry = HistoryEntry {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            mac: mac.clone(),
+            ip_range: ip_range.clone(),
+            profile: profile.name.clone(),
+            ip: None,
+            error: None,
+            duration_secs: 0.0,
+        };
+        self.pending = Some((entry, Instant::now()));
+
+        let ip_cache = config::default_dir().map(|d| d.join(ip_cache::CACHE_FILE));
+        let ip_cache = ip_cache.map(|path| IpCache::load(&path));
+        let wake = Arc::new(tokio::sync::Notify::new());
+        if watch.is_some() {
+            self.wake = Some(wake.clone());
+        }
+        let next_scan = self.next_scan.clone();
+
+        // Spawn a background thread; each scan is bounded by the overall
+        // scan deadline.
         std::thread::spawn(move || {
+            let mut ip_cache = ip_cache;
+            let mut watcher = Watcher::new(&targets, None);
+            let out = OutputWriter::new(MacCase::default(), OutputFormat::Text, &targets);
             let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
             rt.block_on(async {
-                let scanner = Scanner::new(config, mac);
-                let scan_future = scanner.scan(&ip_range);
-                let result = tokio::time::timeout(Duration::from_secs(15), scan_future).await;
-
-                let mut s = state.lock().unwrap();
-                match result {
-                    Ok(Ok(ip)) => *s = ScanState::Found(ip),
-                    Ok(Err(e)) => *s = ScanState::Error(e.to_string()),
-                    Err(_) => *s = ScanState::Error("Scan timed out after 15 seconds".into()),
+                loop {
+                    let priority = ip_cache.as_ref().map(|c| c.priority_hosts(&targets));
+                    let scanner = Scanner::new(config.clone(), targets.clone())
+                        .with_priority_hosts(priority.unwrap_or_default())
+                        .with_jitter(jitter)
+                        .with_concurrency(concurrency)
+                        .with_do_not_scan(do_not_scan.clone())
+                        .with_prescan(prescan)
+                        .with_progress(progress.clone())
+                        .with_cancel(cancel.clone())
+                        .with_reverse_dns(rdns::DEFAULT_TIMEOUT);
+                    // A cancelled scan aborts its probes and returns at once;
+                    // dropping the handle on timeout does the same.
+                    let handle = scanner.spawn(ScanRequest {
+                        range: ip_range.clone(),
+                        hosts: Vec::new(),
+                        mode: ScanMode::FirstMatch,
+                    });
+                    let result = tokio::time::timeout(deadline, handle.wait()).await;
+
+                    // Cancelled: `cancel_scan` already reset the state.
+                    let mut changes = Vec::new();
+                    let mut found = Vec::new();
+                    {
+                        let mut s = state.lock().unwrap();
+                        if cancel.is_cancelled() || generation.load(Ordering::SeqCst) != scan_id {
+                            return;
+                        }
+                        if let Ok(Ok(report)) = &result {
+                            *last_report.lock().unwrap() = Some(report.clone());
+                            if let Some(ip_cache) = &mut ip_cache {
+                                ip_cache.update(&report.matches);
+                            }
+                            changes = watcher.update(&report.matches);
+                        }
+                        match result {
+                            Ok(Ok(report)) if !report.matches.is_empty() => {
+                                found.clone_from(&report.matches);
+                                *s = ScanState::Found(report.matches)
+                            }
+                            Ok(Ok(report)) => {
+                                let not_found = report.not_found(&targets.join(", "));
+                                *s = ScanState::Error(not_found.to_string())
+                            }
+                            Ok(Err(e)) => *s = ScanState::Error(e.to_string()),
+                            Err(_) => {
+                                *s = ScanState::Error(format!(
+                                    "Scan timed out after {}",
+                                    humantime::format_duration(deadline)
+                                ))
+                            }
+                        }
+                    }
+
+                    // Watching: report what changed since the last scan, then
+                    // wait for the next, or "Scan now".
+                    let Some(interval) = watch else {
+                        if notify && !found.is_empty() {
+                            desktop_notify::found(&found);
+                        }
+                        return;
+                    };
+                    for change in &changes {
+                        tracing::info!("{}", out.describe(change));
+                        if notify {
+                            desktop_notify::show(out.watch_title(change), &out.describe(change));
+                        }
+                    }
+                    *next_scan.lock().unwrap() = Some(Instant::now() + interval);
+                    ctx.request_repaint();
+                    tokio::select! {
+                        _ = tokio::time::sleep(interval) => {}
+                        _ = wake.notified() => {}
+                        _ = cancel.cancelled() => return,
+                    }
+                    *next_scan.lock().unwrap() = None;
+                    let mut s = state.lock().unwrap();
+                    if generation.load(Ordering::SeqCst) != scan_id {
+                        return;
+                    }
+                    progress = Arc::new(ScanProgress::default());
+                    *shared_progress.lock().unwrap() = (progress.clone(), Instant::now());
+                    *s = ScanState::Scanning;
+                    ctx.request_repaint();
                 }
             });
+            // Probes aborted after the match may still sit in libssh2 on the
+            // blocking pool; let them run out without holding this thread.
+            rt.shutdown_background();
             ctx.request_repaint();
         });
     }
+
+    /// Stop the running scan, or watch, and return to `Idle` unless the
+    /// watch is between scans, whose last result stays. Its probes are
+    /// aborted and any result it still produces is discarded.
+    fn cancel_scan(&mut self) {
+        self.pending = None;
+        self.wake = None;
+        *self.next_scan.lock().unwrap() = None;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Some(cancel) = self.cancel.take() {
+            cancel.cancel();
+        }
+        let mut state = self.scan_state.lock().unwrap();
+        if matches!(*state, ScanState::Scanning) {
+            *state = ScanState::Idle;
+        }
+    }
 }
@@ -0,0 +1,58 @@
+use crate::logging::{LogFormat, Logger};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::level_filters::LevelFilter;
+use tracing::Level;
+
+/// Lines kept for the log panel; older ones are dropped.
+const CAPACITY: usize = 2000;
+
+/// One captured event.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub time: SystemTime,
+    pub level: Level,
+    pub message: String,
+}
+
+/// The most recent log lines, shared between the logger and the GUI.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<LogLine>>>);
+
+impl LogBuffer {
+    /// Lines at `filter` or more severe, oldest first.
+    pub fn lines(&self, filter: LevelFilter) -> Vec<LogLine> {
+        let lines = self.0.lock().unwrap();
+        lines.iter().filter(|l| l.level <= filter).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    /// Whether the panel keeps events of `level` from `target`: radar-ip's
+    /// own down to debug, plus warnings of every other crate.
+    pub fn captures(level: Level, target: &str) -> bool {
+        match level {
+            Level::ERROR | Level::WARN => true,
+            Level::INFO | Level::DEBUG => target.starts_with("radar_ip"),
+            _ => false,
+        }
+    }
+
+    pub fn push(&self, line: LogLine) {
+        let mut lines = self.0.lock().unwrap();
+        if lines.len() == CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+/// Install the [`Logger`] with a [`LogBuffer`] for the GUI.
+pub fn init() -> LogBuffer {
+    let buffer = LogBuffer::default();
+    Logger::new(LogFormat::Text).with_buffer(buffer.clone()).install();
+    buffer
+}
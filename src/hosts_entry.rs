@@ -0,0 +1,193 @@
+use std::fs::OpenOptions;
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// A hosts-file line mapping a found device's IP to a stable name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostsEntry {
+    pub name: String,
+    pub ip: String,
+    /// The MAC it was found by, noted in a comment.
+    pub mac: String,
+}
+
+impl HostsEntry {
+    /// The line, without a newline.
+    pub fn render(&self) -> String {
+        format!("{}\t{}\t# radar-ip: {}", self.ip, self.name, self.mac)
+    }
+}
+
+/// The system hosts file.
+pub fn default_path() -> PathBuf {
+    if cfg!(windows) {
+        let root = std::env::var_os("SystemRoot").unwrap_or_else(|| r"C:\Windows".into());
+        PathBuf::from(root).join(r"System32\drivers\etc\hosts")
+    } else {
+        PathBuf::from("/etc/hosts")
+    }
+}
+
+/// Make the hosts file at `path` map [`HostsEntry::name`] to its IP only:
+/// the entry replaces the first line naming it, the name is dropped from
+/// any other line (a line left without names goes), and the entry is
+/// appended if no line named it. Returns `false`, writing nothing, if the
+/// file already held exactly that.
+///
+/// The new contents go to a temporary file next to it first, so a failed
+/// write (e.g. a full disk) leaves the hosts file as it was. That file is
+/// then copied over it in place, which keeps its owner and permissions and
+/// also works where it is a bind mount, as in containers.
+pub fn apply(path: &Path, entry: &HostsEntry) -> io::Result<bool> {
+    let text = std::fs::read_to_string(path)?;
+    let updated = update(&text, entry);
+    if updated == text {
+        return Ok(false);
+    }
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let mut staged = NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+    staged.write_all(updated.as_bytes())?;
+    staged.as_file().sync_all()?;
+    staged.rewind()?;
+    let mut hosts = OpenOptions::new().write(true).truncate(true).open(path)?;
+    io::copy(staged.as_file_mut(), &mut hosts)?;
+    hosts.sync_all()?;
+    Ok(true)
+}
+
+/// `text` with `entry` applied as [`apply`] describes.
+fn update(text: &str, entry: &HostsEntry) -> String {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut out = String::new();
+    let mut placed = false;
+    for line in text.lines() {
+        let (content, comment) = match line.find('#') {
+            Some(at) => line.split_at(at),
+            None => (line, ""),
+        };
+        let mut fields = content.split_whitespace();
+        let ip = fields.next();
+        let names: Vec<&str> = fields.collect();
+        let keep = match ip {
+            Some(ip) if names.iter().any(|n| n.eq_ignore_ascii_case(&entry.name)) => {
+                if !placed {
+                    out.push_str(&entry.render());
+                    out.push_str(newline);
+                    placed = true;
+                }
+                let others: Vec<&str> =
+                    names.into_iter().filter(|n| !n.eq_ignore_ascii_case(&entry.name)).collect();
+                (!others.is_empty()).then(|| {
+                    let mut kept = format!("{}\t{}", ip, others.join(" "));
+                    if !comment.is_empty() {
+                        kept.push(' ');
+                        kept.push_str(comment);
+                    }
+                    kept
+                })
+            }
+            _ => Some(line.to_string()),
+        };
+        if let Some(line) = keep {
+            out.push_str(&line);
+            out.push_str(newline);
+        }
+    }
+    if !placed {
+        out.push_str(&entry.render());
+        out.push_str(newline);
+    }
+    // A file that had no final newline keeps it that way.
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.truncate(out.len() - newline.len());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY: &str = "10.0.0.5\tdev\t# radar-ip: aa:bb:cc:dd:ee:ff";
+
+    fn entry() -> HostsEntry {
+        HostsEntry {
+            name: "dev".into(),
+            ip: "10.0.0.5".into(),
+            mac: "aa:bb:cc:dd:ee:ff".into(),
+        }
+    }
+
+    #[test]
+    fn first_line_naming_the_host_is_replaced() {
+        let text = "127.0.0.1 localhost\n10.0.0.1 dev\n192.168.1.1 router\n";
+        assert_eq!(
+            update(text, &entry()),
+            format!("127.0.0.1 localhost\n{}\n192.168.1.1 router\n", ENTRY)
+        );
+        // Appended when no line names it; comments mentioning it stay.
+        let text = "127.0.0.1 localhost\n# dev was 10.0.0.1\n";
+        assert_eq!(update(text, &entry()), format!("{}{}\n", text, ENTRY));
+    }
+
+    #[test]
+    fn name_is_removed_from_other_lines() {
+        let text = "10.0.0.1 dev\n10.0.0.2 DEV nas # lab\n10.0.0.3 dev\n";
+        assert_eq!(update(text, &entry()), format!("{}\n10.0.0.2\tnas # lab\n", ENTRY));
+    }
+
+    #[test]
+    fn crlf_is_kept() {
+        let text = "127.0.0.1 localhost\r\n10.0.0.1 dev\r\n";
+        assert_eq!(update(text, &entry()), format!("127.0.0.1 localhost\r\n{}\r\n", ENTRY));
+        let text = "127.0.0.1 localhost\r\n";
+        assert_eq!(update(text, &entry()), format!("{}{}\r\n", text, ENTRY));
+    }
+
+    #[test]
+    fn missing_final_newline_stays_missing() {
+        let text = "127.0.0.1 localhost\n10.0.0.1 dev";
+        assert_eq!(update(text, &entry()), format!("127.0.0.1 localhost\n{}", ENTRY));
+        let text = "127.0.0.1 localhost";
+        assert_eq!(update(text, &entry()), format!("{}\n{}", text, ENTRY));
+    }
+
+    #[test]
+    fn matching_entry_is_left_alone() {
+        let text = format!("127.0.0.1 localhost\n{}\n", ENTRY);
+        assert_eq!(update(&text, &entry()), text);
+        let text = format!("127.0.0.1 localhost\r\n{}", ENTRY);
+        assert_eq!(update(&text, &entry()), text);
+    }
+
+    #[test]
+    fn apply_rewrites_in_place_only_when_needed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts");
+        std::fs::write(&path, "127.0.0.1 localhost\n10.0.0.1 dev\n").unwrap();
+        #[cfg(unix)]
+        let inode = {
+            use std::os::unix::fs::{MetadataExt, PermissionsExt};
+            let permissions = std::fs::Permissions::from_mode(0o644);
+            std::fs::set_permissions(&path, permissions).unwrap();
+            std::fs::metadata(&path).unwrap().ino()
+        };
+
+        assert!(apply(&path, &entry()).unwrap());
+        let expected = format!("127.0.0.1 localhost\n{}\n", ENTRY);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{MetadataExt, PermissionsExt};
+            let metadata = std::fs::metadata(&path).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o644);
+            assert_eq!(metadata.ino(), inode);
+        }
+        // The staged copy is gone.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        assert!(!apply(&path, &entry()).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+    }
+}
@@ -0,0 +1,99 @@
+use radar_ip_core::oui;
+use radar_ip_core::report::ScanMatch;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Where each MAC was last found, in the config directory.
+pub const CACHE_FILE: &str = "last-ip.toml";
+
+/// MAC → the IP a scan last found it at, kept across runs so the next scan
+/// can try there first (see `Scanner::with_priority_hosts`).
+#[derive(Debug, Default)]
+pub struct IpCache {
+    path: PathBuf,
+    file: CacheFile,
+}
+
+/// Contents of [`CACHE_FILE`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    #[serde(default)]
+    last_ip: BTreeMap<String, String>,
+}
+
+impl IpCache {
+    /// Load the cache at `path`; a missing file is an empty cache, an
+    /// unreadable one is replaced with a warning.
+    pub fn load(path: &Path) -> Self {
+        let file = match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|e: toml::de::Error| {
+                tracing::warn!("Invalid {}: {}; starting afresh", path.display(), e.message());
+                CacheFile::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CacheFile::default(),
+            Err(e) => {
+                tracing::warn!("Cannot read {}: {}; starting afresh", path.display(), e);
+                CacheFile::default()
+            }
+        };
+        Self {
+            path: path.to_path_buf(),
+            file,
+        }
+    }
+
+    /// The last IP of every MAC `targets` match, each with its immediate
+    /// neighbors: the addresses worth probing before the rest of a range.
+    pub fn priority_hosts(&self, targets: &[String]) -> Vec<IpAddr> {
+        let mut hosts = Vec::new();
+        let cached = self.file.last_ip.iter().filter(|(mac, _)| {
+            targets.iter().any(|target| oui::target_matches(target, mac))
+        });
+        for ip in cached.filter_map(|(_, ip)| ip.parse::<IpAddr>().ok()) {
+            for host in around(ip) {
+                if !hosts.contains(&host) {
+                    hosts.push(host);
+                }
+            }
+        }
+        hosts
+    }
+
+    /// Remember where `found` are now and write the cache back. Failures
+    /// only warn.
+    pub fn update(&mut self, found: &[ScanMatch]) {
+        if found.is_empty() {
+            return;
+        }
+        for m in found {
+            self.file.last_ip.insert(m.mac.to_lowercase(), m.ip.clone());
+        }
+        let text = match toml::to_string(&self.file) {
+            Ok(text) => text,
+            Err(e) => return tracing::warn!("Cannot encode {}: {}", self.path.display(), e),
+        };
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(&self.path, text) {
+            tracing::warn!("Cannot write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// `ip` and the addresses just below and above it.
+fn around(ip: IpAddr) -> Vec<IpAddr> {
+    let neighbors = match ip {
+        IpAddr::V4(v4) => {
+            let n = u32::from(v4);
+            [n.checked_sub(1), n.checked_add(1)].map(|n| n.map(|n| IpAddr::V4(n.into())))
+        }
+        IpAddr::V6(v6) => {
+            let n = u128::from(v6);
+            [n.checked_sub(1), n.checked_add(1)].map(|n| n.map(|n| IpAddr::V6(n.into())))
+        }
+    };
+    std::iter::once(ip).chain(neighbors.into_iter().flatten()).collect()
+}
@@ -0,0 +1,584 @@
+use crate::gui_log::{LogBuffer, LogLine};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// How log lines are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    /// `[2026-01-01T12:00:00Z INFO  target] span{field=value}: message`,
+    /// as `env_logger` wrote them.
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of the event and of the
+    /// spans it happened in.
+    Json,
+}
+
+/// The `tracing` subscriber of the CLI and the GUI: writes what `RUST_LOG`
+/// enables to stderr, and feeds the GUI's [`LogBuffer`].
+///
+/// Spans are written when they close, with every field recorded on them
+/// by then (a probe's `outcome` and `duration_ms`, say). Records of crates
+/// that still use `log` go the same way, without spans.
+pub struct Logger {
+    filter: Filter,
+    format: LogFormat,
+    buffer: Option<LogBuffer>,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    next_id: AtomicU64,
+    /// Lines written, instead of stderr.
+    #[cfg(test)]
+    written: Mutex<Vec<String>>,
+}
+
+impl Logger {
+    /// A logger filtered by `RUST_LOG`, errors only if it is not set.
+    pub fn new(format: LogFormat) -> Self {
+        let filter = match std::env::var("RUST_LOG") {
+            Ok(spec) => Filter::parse(&spec),
+            Err(_) => Filter::parse("error"),
+        };
+        Self {
+            filter,
+            format,
+            buffer: None,
+            spans: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            #[cfg(test)]
+            written: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Also write radar-ip's debug messages, unless `RUST_LOG` says what
+    /// to write.
+    pub fn with_debug(mut self) -> Self {
+        if std::env::var_os("RUST_LOG").is_none() {
+            // `radar_ip` covers `radar_ip_core` too.
+            self.filter.directives.push((Some("radar_ip".to_string()), LevelFilter::DEBUG));
+        }
+        self
+    }
+
+    /// Keep what [`LogBuffer::captures`] in `buffer` as well.
+    pub fn with_buffer(mut self, buffer: LogBuffer) -> Self {
+        self.buffer = Some(buffer);
+        self
+    }
+
+    /// Make this the logger of the process, for `tracing` and `log` alike.
+    pub fn install(self) {
+        let logger = Arc::new(self);
+        log::set_max_level(match logger.max_level() {
+            LevelFilter::OFF => log::LevelFilter::Off,
+            LevelFilter::ERROR => log::LevelFilter::Error,
+            LevelFilter::WARN => log::LevelFilter::Warn,
+            LevelFilter::INFO => log::LevelFilter::Info,
+            LevelFilter::DEBUG => log::LevelFilter::Debug,
+            LevelFilter::TRACE => log::LevelFilter::Trace,
+        });
+        if let Err(e) = log::set_boxed_logger(Box::new(LogBridge(logger.clone()))) {
+            eprintln!("Cannot install the logger for `log` records: {}", e);
+        }
+        if let Err(e) = tracing::subscriber::set_global_default(logger) {
+            eprintln!("Cannot install the logger: {}", e);
+        }
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        match self.buffer {
+            Some(_) => self.filter.max().max(LevelFilter::DEBUG),
+            None => self.filter.max(),
+        }
+    }
+
+    fn captures(&self, level: Level, target: &str) -> bool {
+        self.buffer.is_some() && LogBuffer::captures(level, target)
+    }
+
+    /// Write `message` and `fields` of `target` to stderr if the filter
+    /// lets them through, in the spans `spans` (outermost first), and keep
+    /// them in the buffer if it captures them.
+    fn log(&self, level: Level, target: &str, spans: &[Span], message: &str, fields: &Fields) {
+        if let Some(buffer) = self.buffer.as_ref().filter(|_| LogBuffer::captures(level, target)) {
+            buffer.push(LogLine {
+                time: SystemTime::now(),
+                level,
+                message: format!("{}{}", message, fields.text()),
+            });
+        }
+        if !self.filter.enabled(level, target) {
+            return;
+        }
+        let line = self.render(level, target, spans, message, fields);
+        #[cfg(test)]
+        self.written.lock().unwrap().push(line);
+        #[cfg(not(test))]
+        let _ = writeln!(io::stderr().lock(), "{}", line);
+    }
+
+    /// The line [`Logger::log`] writes, in the format of the logger.
+    fn render(
+        &self,
+        level: Level,
+        target: &str,
+        spans: &[Span],
+        message: &str,
+        fields: &Fields,
+    ) -> String {
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        match self.format {
+            LogFormat::Text => {
+                let spans: String = spans
+                    .iter()
+                    .map(|s| match s.fields.values.is_empty() {
+                        true => format!("{}: ", s.name),
+                        false => format!("{}{{{}}}: ", s.name, s.fields.text().trim_start()),
+                    })
+                    .collect();
+                format!(
+                    "[{} {:<5} {}] {}{}{}",
+                    timestamp,
+                    level,
+                    target,
+                    spans,
+                    message,
+                    fields.text()
+                )
+            }
+            LogFormat::Json => {
+                let mut line = Map::new();
+                line.insert("timestamp".into(), timestamp.into());
+                line.insert("level".into(), level.as_str().into());
+                line.insert("target".into(), target.into());
+                line.insert("message".into(), message.into());
+                if !fields.values.is_empty() {
+                    line.insert("fields".into(), fields.json().into());
+                }
+                if !spans.is_empty() {
+                    let spans = spans.iter().map(|s| {
+                        let mut span = Map::new();
+                        span.insert("name".into(), s.name.into());
+                        span.extend(s.fields.json());
+                        Value::Object(span)
+                    });
+                    line.insert("spans".into(), spans.collect());
+                }
+                Value::Object(line).to_string()
+            }
+        }
+    }
+
+    /// `id` and the spans it is in, outermost first.
+    fn context(spans: &HashMap<u64, SpanData>, id: Option<u64>) -> Vec<Span> {
+        let mut context = Vec::new();
+        let mut next = id;
+        while let Some(data) = next.and_then(|id| spans.get(&id)) {
+            context.push(Span {
+                name: data.metadata.name(),
+                fields: data.fields.clone(),
+            });
+            next = data.parent;
+        }
+        context.reverse();
+        context
+    }
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let (level, target) = (*metadata.level(), metadata.target());
+        self.filter.enabled(level, target) || (metadata.is_event() && self.captures(level, target))
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.max_level())
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let parent = match span.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if span.is_contextual() => current(),
+            None => None,
+        };
+        let data = SpanData {
+            metadata: span.metadata(),
+            parent,
+            fields,
+            refs: 1,
+        };
+        self.spans.lock().unwrap().insert(id, data);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            values.record(&mut data.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let parent = match event.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if event.is_contextual() => current(),
+            None => None,
+        };
+        let spans = Self::context(&self.spans.lock().unwrap(), parent);
+        let metadata = event.metadata();
+        let message = fields.message.take().unwrap_or_default();
+        self.log(*metadata.level(), metadata.target(), &spans, &message, &fields);
+    }
+
+    fn enter(&self, span: &Id) {
+        STACK.with(|stack| stack.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if let Some(at) = stack.iter().rposition(|id| *id == span.into_u64()) {
+                stack.remove(at);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+        let id = span.into_u64();
+        let Some(data) = spans.get_mut(&id) else {
+            return false;
+        };
+        data.refs -= 1;
+        if data.refs > 0 {
+            return false;
+        }
+        let context = Self::context(&spans, Some(id));
+        let metadata = spans.remove(&id).map(|data| data.metadata);
+        drop(spans);
+        if let Some(metadata) = metadata {
+            let (level, target) = (*metadata.level(), metadata.target());
+            if self.filter.enabled(level, target) {
+                self.log(level, target, &context, "close", &Fields::default());
+            }
+        }
+        true
+    }
+}
+
+/// Hands the records of crates that log through `log` to the [`Logger`].
+struct LogBridge(Arc<Logger>);
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        let level = level_of(metadata.level());
+        self.0.filter.enabled(level, metadata.target()) || self.0.captures(level, metadata.target())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let message = record.args().to_string();
+            let level = level_of(record.level());
+            self.0.log(level, record.target(), &[], &message, &Fields::default());
+        }
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+fn level_of(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::ERROR,
+        log::Level::Warn => Level::WARN,
+        log::Level::Info => Level::INFO,
+        log::Level::Debug => Level::DEBUG,
+        log::Level::Trace => Level::TRACE,
+    }
+}
+
+thread_local! {
+    /// The spans entered on this thread, innermost last.
+    static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The innermost span entered on this thread.
+fn current() -> Option<u64> {
+    STACK.with(|stack| stack.borrow().last().copied())
+}
+
+/// A span that has not closed yet.
+struct SpanData {
+    metadata: &'static Metadata<'static>,
+    parent: Option<u64>,
+    fields: Fields,
+    /// Handles to it; it closes when the last one is dropped.
+    refs: usize,
+}
+
+/// A span around an event, as it is written.
+struct Span {
+    name: &'static str,
+    fields: Fields,
+}
+
+/// The fields of an event or span, in the order they were recorded.
+#[derive(Debug, Clone, Default)]
+struct Fields {
+    message: Option<String>,
+    values: Vec<(&'static str, Value)>,
+}
+
+impl Fields {
+    /// ` name=value` for each field.
+    fn text(&self) -> String {
+        self.values
+            .iter()
+            .map(|(name, value)| match value {
+                Value::String(s) => format!(" {}={}", name, s),
+                value => format!(" {}={}", name, value),
+            })
+            .collect()
+    }
+
+    fn json(&self) -> Map<String, Value> {
+        self.values.iter().map(|(name, value)| (name.to_string(), value.clone())).collect()
+    }
+
+    fn set(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = Some(match value {
+                Value::String(s) => s,
+                value => value.to_string(),
+            });
+        } else if let Some(slot) = self.values.iter_mut().find(|(name, _)| *name == field.name()) {
+            slot.1 = value;
+        } else {
+            self.values.push((field.name(), value));
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field, Value::String(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, Value::String(value.to_string()));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, value.into());
+    }
+}
+
+/// A `RUST_LOG` filter: comma-separated `target=level`, `target` (all
+/// levels) or `level` (every other target) directives, as `env_logger`
+/// reads them. A target matches the targets it prefixes; the longest one
+/// wins. Without a bare level, other targets write nothing.
+#[derive(Debug, Clone)]
+struct Filter {
+    directives: Vec<(Option<String>, LevelFilter)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Self {
+        let mut directives = Vec::new();
+        // `env_logger`'s `/regex` message filter is not supported.
+        let spec = spec.split('/').next().unwrap_or_default();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let directive = match directive.split_once('=') {
+                Some((target, level)) => match level.trim().parse() {
+                    Ok(level) => (Some(target.trim().to_string()), level),
+                    Err(_) => {
+                        eprintln!("Ignoring the RUST_LOG directive '{}': bad level", directive);
+                        continue;
+                    }
+                },
+                None => match directive.parse() {
+                    Ok(level) => (None, level),
+                    Err(_) => (Some(directive.to_string()), LevelFilter::TRACE),
+                },
+            };
+            directives.push(directive);
+        }
+        Self { directives }
+    }
+
+    fn enabled(&self, level: Level, target: &str) -> bool {
+        level <= self.level(target)
+    }
+
+    fn level(&self, target: &str) -> LevelFilter {
+        let by_target = self
+            .directives
+            .iter()
+            .filter_map(|(name, level)| Some((name.as_deref()?, *level)))
+            .filter(|(name, _)| target.starts_with(name))
+            .max_by_key(|(name, _)| name.len());
+        match by_target {
+            Some((_, level)) => level,
+            None => {
+                let default = self.directives.iter().rev().find(|(name, _)| name.is_none());
+                default.map_or(LevelFilter::OFF, |(_, level)| *level)
+            }
+        }
+    }
+
+    fn max(&self) -> LevelFilter {
+        self.directives.iter().map(|(_, level)| *level).max().unwrap_or(LevelFilter::OFF)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logger(spec: &str, format: LogFormat) -> Arc<Logger> {
+        Arc::new(Logger {
+            filter: Filter::parse(spec),
+            format,
+            buffer: None,
+            spans: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            written: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// What `logger` writes while a probe span records its outcome around
+    /// one event, then closes.
+    fn probe(logger: &Arc<Logger>) -> Vec<String> {
+        tracing::subscriber::with_default(logger.clone(), || {
+            let span = tracing::info_span!(
+                target: "radar_ip_core::scanner",
+                "probe",
+                ip = "10.0.0.1",
+                outcome = tracing::field::Empty
+            );
+            let _entered = span.enter();
+            tracing::info!(target: "radar_ip_core::scanner", attempt = 2, "connected");
+            span.record("outcome", "match");
+        });
+        logger.written.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn directives_set_the_level_of_their_targets() {
+        let filter = Filter::parse("radar_ip_core=debug, warn");
+        assert_eq!(filter.level("radar_ip_core::scanner"), LevelFilter::DEBUG);
+        assert_eq!(filter.level("ureq"), LevelFilter::WARN);
+        assert!(filter.enabled(Level::DEBUG, "radar_ip_core"));
+        assert!(!filter.enabled(Level::INFO, "ureq"));
+        assert_eq!(filter.max(), LevelFilter::DEBUG);
+
+        // Without a bare level, other targets write nothing.
+        let filter = Filter::parse("radar_ip=info");
+        assert_eq!(filter.level("eframe"), LevelFilter::OFF);
+        assert_eq!(Filter::parse("").max(), LevelFilter::OFF);
+    }
+
+    #[test]
+    fn bare_target_enables_every_level() {
+        let filter = Filter::parse("radar_ip_core,error");
+        assert_eq!(filter.level("radar_ip_core::ssh_client"), LevelFilter::TRACE);
+        assert_eq!(filter.level("ssh2"), LevelFilter::ERROR);
+    }
+
+    #[test]
+    fn longest_matching_target_wins() {
+        let filter =
+            Filter::parse("radar_ip_core::scanner=warn,radar_ip=info,radar_ip_core=trace");
+        assert_eq!(filter.level("radar_ip_core::scanner"), LevelFilter::WARN);
+        assert_eq!(filter.level("radar_ip_core::ssh_client"), LevelFilter::TRACE);
+        assert_eq!(filter.level("radar_ip::gui"), LevelFilter::INFO);
+    }
+
+    #[test]
+    fn bad_levels_and_message_filters_are_ignored() {
+        let filter = Filter::parse("radar_ip=loud,info");
+        assert_eq!(filter.directives, [(None, LevelFilter::INFO)]);
+        // `env_logger`'s `/regex` part is dropped, not read as a target.
+        let filter = Filter::parse("debug,ssh2=warn/connect.*");
+        assert_eq!(filter.level("radar_ip"), LevelFilter::DEBUG);
+        assert_eq!(filter.level("ssh2"), LevelFilter::WARN);
+    }
+
+    #[test]
+    fn close_line_has_the_fields_recorded_on_the_span() {
+        let lines = probe(&logger("radar_ip_core=info", LogFormat::Text));
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        let event = " INFO  radar_ip_core::scanner] probe{ip=10.0.0.1}: connected attempt=2";
+        assert!(lines[0].ends_with(event), "{}", lines[0]);
+        let close = " INFO  radar_ip_core::scanner] probe{ip=10.0.0.1 outcome=match}: close";
+        assert!(lines[1].ends_with(close), "{}", lines[1]);
+
+        // Filtered out together with its events.
+        assert!(probe(&logger("radar_ip_core=warn", LogFormat::Text)).is_empty());
+    }
+
+    #[test]
+    fn json_lines_have_the_event_and_span_fields() {
+        let lines = probe(&logger("radar_ip_core=info", LogFormat::Json));
+        let lines: Vec<Value> = lines.iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let timestamp = lines[0]["timestamp"].as_str().unwrap();
+        assert!(humantime::parse_rfc3339(timestamp).is_ok(), "{}", timestamp);
+        let mut event = lines[0].clone();
+        event.as_object_mut().unwrap().remove("timestamp");
+        assert_eq!(
+            event,
+            serde_json::json!({
+                "level": "INFO",
+                "target": "radar_ip_core::scanner",
+                "message": "connected",
+                "fields": {"attempt": 2},
+                "spans": [{"name": "probe", "ip": "10.0.0.1"}],
+            })
+        );
+
+        // The close line has no `fields` of its own.
+        let close = lines[1].as_object().unwrap();
+        let mut keys: Vec<&str> = close.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["level", "message", "spans", "target", "timestamp"]);
+        assert_eq!(close["message"], "close");
+        let span = serde_json::json!([{"name": "probe", "ip": "10.0.0.1", "outcome": "match"}]);
+        assert_eq!(close["spans"], span);
+    }
+}
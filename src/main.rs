@@ -1,21 +1,118 @@
+mod ansible;
+mod audit;
+mod checkpoint;
 mod cli;
-mod errors;
+mod config;
+mod connect;
+mod csv_export;
+mod desktop_notify;
+mod exit;
 mod gui;
-mod scanner;
-mod ssh_client;
+mod gui_log;
+mod hosts_entry;
+mod ip_cache;
+mod key_fingerprint;
+mod logging;
+mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "webhook")]
+mod notifier;
+mod output;
+mod progress_bar;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+mod ssh_host;
+mod tray;
+mod watch;
 
+use clap::Parser;
+use checkpoint::Checkpoint;
+#[cfg(feature = "sqlite")]
+use cli::HistoryArgs;
+use cli::{CliArgs, Command, ExecArgs, Method, PullArgs, PushArgs, TunnelArgs, WatchArgs};
 use gui::RadarApp;
+use ip_cache::IpCache;
+use output::OutputWriter;
+use progress_bar::ProgressBar;
+#[cfg(feature = "arp")]
+use radar_ip_core::arp_scan;
+use radar_ip_core::beacon::BeaconConfig;
+use radar_ip_core::errors::RadarError;
+use radar_ip_core::oui;
+use radar_ip_core::rdns;
+use radar_ip_core::report::NotFoundReason;
+use radar_ip_core::report::{ScanMatch, ScanReport};
+use radar_ip_core::scanner::{CancelToken, Sample, ScanMode, ScanProgress, ScanRequest, Scanner};
+#[cfg(feature = "snmp")]
+use radar_ip_core::snmp_client;
+use radar_ip_core::ssh_client::{self, AuthenticationMethod, SessionPool, SshConfig};
+use std::io::{self, IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::Arc;
+#[cfg(feature = "sqlite")]
+use std::time::SystemTime;
+use exit::Exit;
+use metrics::Metrics;
+use watch::Watcher;
+
+fn main() -> ExitCode {
+    // Started by `ssh` for the password of a --connect login.
+    if let Some(code) = connect::answer_askpass() {
+        return code;
+    }
 
-fn main() -> eframe::Result {
     // Load .env file (silently ignore if missing).
     let _ = dotenvy::dotenv();
 
-    // Initialize logging.
-    env_logger::init();
+    // Any command-line argument selects CLI mode; a bare launch opens the GUI.
+    if std::env::args_os().len() > 1 {
+        let args = match CliArgs::try_parse() {
+            Ok(args) => args,
+            // --help and --version are not errors.
+            Err(e) => {
+                let _ = e.print();
+                return match e.use_stderr() {
+                    true => Exit::InvalidArgs.into(),
+                    false => ExitCode::SUCCESS,
+                };
+            }
+        };
+        init_logger(&args);
+        let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
+        let code = rt.block_on(run_cli(args));
+        // Don't wait for probes the scan already gave up on.
+        rt.shutdown_background();
+        return code;
+    }
+
+    // The GUI also shows the log in its bottom panel.
+    let log = gui_log::init();
+    match run_gui(log) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("GUI error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Log to stderr as `RUST_LOG` says, in the `--log-format`; without it,
+/// `-vv` shows the scanner's debug messages, such as each SSH attempt and
+/// retry.
+fn init_logger(args: &CliArgs) {
+    let logger = logging::Logger::new(args.log_format);
+    match args.verbose {
+        0 | 1 => logger.install(),
+        _ => logger.with_debug().install(),
+    }
+}
 
+/// Launch the native GUI window.
+fn run_gui(log: gui_log::LogBuffer) -> eframe::Result {
     let options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
-            .with_inner_size([480.0, 480.0])
+            .with_inner_size([480.0, 520.0])
             .with_min_inner_size([400.0, 400.0])
             .with_title("Radar-IP Scanner"),
         ..Default::default()
@@ -24,6 +121,1095 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Radar-IP",
         options,
-        Box::new(|cc| Ok(Box::new(RadarApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(RadarApp::new(cc, log)))),
     )
-}
\ No newline at end of file
+}
+
+/// Run a single scan described by `args` and print the outcome. With
+/// `--audit-log`, every invocation ends with its audit lines, whichever
+/// way it ends.
+async fn run_cli(args: CliArgs) -> ExitCode {
+    let mut audit = audit::Audit::new(args.audit_log.clone(), args.user(), args.targets());
+    let code = scan_cli(args, &mut audit).await;
+    audit.finish();
+    code
+}
+
+/// The body of [`run_cli`], which records each outcome in `audit`.
+async fn scan_cli(mut args: CliArgs, audit: &mut audit::Audit) -> ExitCode {
+    let targets = args.targets();
+    let mut out = OutputWriter::new(args.mac_case, args.format, &targets);
+    if args.quiet || args.exec().is_some() {
+        out = out.with_quiet();
+    }
+    // Several MACs, or an OUI prefix that may match many hosts.
+    let many = targets.len() > 1 || targets.iter().any(|t| oui::is_prefix(t));
+    if let (true, Some(flag)) = (many, args.single_target_flag()) {
+        eprintln!("FAILED   {} takes a single --target-mac without wildcards.", flag);
+        return Exit::InvalidArgs.into();
+    }
+
+    // ── 0. Layer config files under the flags ────────────────────────────
+    let file_config = match config::load(
+        args.config_dir.as_deref(),
+        args.config.as_deref(),
+        args.profile.as_deref(),
+    ) {
+        Ok(file_config) => file_config,
+        Err(e) => {
+            out.failed(&e);
+            return Exit::of(&e).into();
+        }
+    };
+    args.apply_defaults(file_config.defaults);
+    audit.path = args.audit_log.clone();
+    audit.ssh_user = args.user().to_string();
+    #[cfg(feature = "sqlite")]
+    if let Some(Command::History(history)) = &args.command {
+        // Reading past scans scans nothing.
+        audit.path = None;
+        return run_history(&args, history, &out);
+    }
+    let mut do_not_scan = file_config.do_not_scan;
+    do_not_scan.extend(args.exclusions());
+
+    // `ip_range` names what is scanned in reports: the ranges, or the
+    // hosts file that replaces them.
+    let (ip_range, request) = match &args.hosts_file {
+        Some(path) => match config::load_hosts_file(path) {
+            Ok(hosts) => (
+                path.display().to_string(),
+                ScanRequest {
+                    range: String::new(),
+                    hosts,
+                    mode: args.scan_mode(),
+                },
+            ),
+            Err(e) => {
+                out.failed(&e);
+                return Exit::of(&e).into();
+            }
+        },
+        None => match args.ip_range() {
+            Some(range) => (
+                range.clone(),
+                ScanRequest {
+                    range,
+                    hosts: Vec::new(),
+                    mode: args.scan_mode(),
+                },
+            ),
+            // The gateway's table alone is the search space.
+            None if args.gateway.is_some() => (
+                format!("neighbors of {}", args.gateway.as_deref().unwrap_or_default()),
+                ScanRequest {
+                    range: String::new(),
+                    hosts: Vec::new(),
+                    mode: args.scan_mode(),
+                },
+            ),
+            // So are the server's leases.
+            None if args.dhcp_server.is_some() => (
+                format!("leases of {}", args.dhcp_server.as_deref().unwrap_or_default()),
+                ScanRequest {
+                    range: String::new(),
+                    hosts: Vec::new(),
+                    mode: args.scan_mode(),
+                },
+            ),
+            // Every host the discovery passes find is the search space.
+            None if args.mdns || args.ssdp => (
+                "discovered hosts".to_string(),
+                ScanRequest {
+                    range: String::new(),
+                    hosts: Vec::new(),
+                    mode: args.scan_mode(),
+                },
+            ),
+            None => {
+                eprintln!("FAILED   No range given: pass --range or set `range` in config.toml.");
+                return Exit::InvalidArgs.into();
+            }
+        },
+    };
+    audit.range = ip_range.clone();
+    let out = out.with_range(&ip_range).with_ansible(ansible::Vars {
+        group: args.profile.clone().unwrap_or_else(|| ansible::DEFAULT_GROUP.to_string()),
+        user: args.user().to_string(),
+        key: args.absolute_key_path(),
+    });
+
+    if args.command == Some(Command::Inventory) {
+        let request = ScanRequest {
+            mode: ScanMode::Inventory,
+            ..request
+        };
+        return run_inventory(&args, request, do_not_scan, &out, audit).await;
+    }
+    if let Some(Command::Watch(watch)) = &args.command {
+        return run_watch(&args, &targets, watch, request, do_not_scan, &out, audit).await;
+    }
+
+    #[cfg(feature = "snmp")]
+    if let Some(spec) = &args.snmp {
+        return run_snmp(&args, &targets[0], spec, &out, audit).await;
+    }
+
+    // ── 1. Skip MACs a checkpoint already located ───────────────────────
+    let mut checkpoint = match args.checkpoint.as_deref().map(Checkpoint::load).transpose() {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            out.failed(&e);
+            return Exit::of(&e).into();
+        }
+    };
+    let mut resumed = Vec::new();
+    let mut pending = Vec::new();
+    for mac in &targets {
+        let known = checkpoint
+            .as_ref()
+            .filter(|_| args.scan_mode() == ScanMode::FirstMatch)
+            .and_then(|c| c.get(mac));
+        match known {
+            Some(ip) => resumed.push(ScanMatch {
+                ip: ip.to_string(),
+                mac: mac.clone(),
+                interface: None,
+                hostname: None,
+                latency: None,
+                dns_name: None,
+                details: None,
+            }),
+            None => pending.push(mac.clone()),
+        }
+    }
+    if !resumed.is_empty() {
+        out.resumed(resumed.len(), pending.len());
+    }
+
+    // ── 2. Pick the backend and, for SSH, the authentication method ──────
+    let Some((scanner, config)) = backend(&args, pending.clone(), &out) else {
+        return Exit::InvalidArgs.into();
+    };
+
+    // ── 3. Scan for the rest ─────────────────────────────────────────────
+    let result = if pending.is_empty() {
+        Ok(resumed)
+    } else {
+        match sweep(&args, scanner, &pending, request, do_not_scan, &out).await {
+            Ok(mut found) => {
+                found.extend(resumed);
+                Ok(found)
+            }
+            Err(RadarError::MacNotFound(_) | RadarError::NoHostReachable(..))
+                if !resumed.is_empty() =>
+            {
+                Ok(resumed)
+            }
+            Err(e) => Err(e),
+        }
+    };
+
+    if let (Some(checkpoint), Ok(found)) = (&mut checkpoint, &result) {
+        // With --all, only the lowest IP of each MAC is kept.
+        let mut recorded: Vec<&str> = Vec::new();
+        for m in found {
+            if !recorded.contains(&m.mac.as_str()) {
+                checkpoint.record(&m.mac, &m.ip);
+                recorded.push(&m.mac);
+            }
+        }
+    }
+
+    // ── 4. Audit, one line per target (never fails the scan) ─────────────
+    for mac in &targets {
+        let missing = RadarError::MacNotFound(mac.clone());
+        let outcome = match &result {
+            Ok(found) => match found.iter().find(|m| oui::target_matches(mac, &m.mac)) {
+                Some(m) => Ok(Some(m.ip.clone())),
+                None => Err(&missing),
+            },
+            Err(e) => Err(e),
+        };
+        audit.record(Some(mac), outcome);
+    }
+
+    if let (true, Ok(found)) = (args.notify, &result) {
+        desktop_notify::found(found);
+    }
+    #[cfg(feature = "mqtt")]
+    if let (Some(publisher), Ok(found)) = (args.mqtt(), &result) {
+        let found = found.clone();
+        tokio::task::spawn_blocking(move || {
+            publisher.publish(&found.iter().map(mqtt::Message::found).collect::<Vec<_>>())
+        })
+        .await
+        .expect("MQTT task panicked");
+    }
+
+    // ── 5. Report ────────────────────────────────────────────────────────
+    match result {
+        Ok(found) if many => {
+            out.found_many(&found);
+            let all_found = targets
+                .iter()
+                .all(|mac| found.iter().any(|m| oui::target_matches(mac, &m.mac)));
+            if all_found {
+                Exit::Found.into()
+            } else {
+                Exit::NotFound.into()
+            }
+        }
+        Ok(found) if args.all => {
+            out.found_all(&found);
+            Exit::Found.into()
+        }
+        Ok(found) => {
+            // `sweep` never returns an empty success.
+            let found = &found[0];
+            match (&args.command, config.clone()) {
+                (Some(Command::Exec(exec)), Some(config)) => {
+                    tracing::info!("Found {} at {}", found.mac, found.ip);
+                    return run_exec(config, found.ip.clone(), exec).await;
+                }
+                (Some(Command::Push(push)), Some(config)) => {
+                    return run_push(config, found, push, &out).await;
+                }
+                (Some(Command::Pull(pull)), Some(config)) => {
+                    return run_pull(config, found, pull, &out).await;
+                }
+                (Some(Command::Tunnel(tunnel)), Some(config)) => {
+                    out.found(found, None);
+                    return run_tunnel(&config, &found.ip, tunnel, &out);
+                }
+                _ => {}
+            }
+            let uptime = match config.as_ref().filter(|_| args.verify_after_found) {
+                Some(config) => Some(verify_uptime(config.clone(), found.ip.clone()).await),
+                None => None,
+            };
+            out.found(found, uptime.as_ref());
+            if let Some(name) = &args.ssh_host {
+                if !ssh_host_entry(&args, name, found, &out) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            if let Some(name) = &args.hosts_entry {
+                if !hosts_file_entry(&args, name, found, &out) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            match config.filter(|_| args.connect) {
+                Some(config) => login(&config, &found.ip),
+                None => Exit::Found.into(),
+            }
+        }
+        Err(e) => {
+            out.failed(&e);
+            Exit::of(&e).into()
+        }
+    }
+}
+
+/// Print the `--ssh-host` block for `found` and, with
+/// `--write-ssh-config`, write it into `~/.ssh/config`; `false` if that
+/// fails.
+fn ssh_host_entry(args: &CliArgs, name: &str, found: &ScanMatch, out: &OutputWriter) -> bool {
+    let entry = ssh_host::HostEntry {
+        name: name.to_string(),
+        ip: found.ip.clone(),
+        user: args.user().to_string(),
+        port: 22,
+        key: args.absolute_key_path(),
+        via: args.via.clone(),
+        mac: found.mac.clone(),
+    };
+    out.ssh_host(&entry.render());
+    if !args.write_ssh_config {
+        return true;
+    }
+    let Some(path) = ssh_host::default_path() else {
+        eprintln!("FAILED   No home directory for ~/.ssh/config (HOME is not set).");
+        return false;
+    };
+    match ssh_host::write(&path, &entry) {
+        Ok(()) => {
+            out.wrote(&path);
+            true
+        }
+        Err(e) => {
+            eprintln!("FAILED   Cannot write {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+/// Print the `--hosts-entry` line for `found` and, with `--apply`, write
+/// it into the system hosts file; `false` if that fails.
+fn hosts_file_entry(args: &CliArgs, name: &str, found: &ScanMatch, out: &OutputWriter) -> bool {
+    let entry = hosts_entry::HostsEntry {
+        name: name.to_string(),
+        ip: found.ip.clone(),
+        mac: found.mac.clone(),
+    };
+    out.hosts_entry(&entry.render());
+    if !args.apply {
+        return true;
+    }
+    let path = hosts_entry::default_path();
+    match hosts_entry::apply(&path, &entry) {
+        Ok(true) => {
+            out.wrote(&path);
+            true
+        }
+        Ok(false) => {
+            tracing::info!("{} already maps {} to {}", path.display(), name, found.ip);
+            true
+        }
+        Err(e) => {
+            let hint = if e.kind() == io::ErrorKind::PermissionDenied {
+                " (run as root, or Administrator on Windows)"
+            } else {
+                ""
+            };
+            eprintln!("FAILED   Cannot update {}: {}{}", path.display(), e, hint);
+            false
+        }
+    }
+}
+
+/// Log into `ip` for `--connect`, and exit with the session's status.
+fn login(config: &SshConfig, ip: &str) -> ExitCode {
+    let status = connect::Login::new(config, ip).and_then(|login| login.run());
+    match status {
+        Ok(status) => match status.code() {
+            Some(code) => exit_code(code),
+            None => ExitCode::FAILURE,
+        },
+        Err(e) => {
+            eprintln!("FAILED   Cannot run ssh to {}: {}", ip, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the `exec` command on `ip`, print what it printed, and exit with
+/// its status.
+async fn run_exec(config: SshConfig, ip: String, exec: &ExecArgs) -> ExitCode {
+    let command = exec.command.join(" ");
+    let host = ip.clone();
+    let output = tokio::task::spawn_blocking(move || config.run_command_output(&ip, &command))
+        .await
+        .expect("exec task panicked");
+    match output {
+        Ok(output) => {
+            let _ = io::stdout().write_all(&output.stdout);
+            let _ = io::stderr().write_all(&output.stderr);
+            exit_code(output.status)
+        }
+        Err(e) => {
+            eprintln!("FAILED   Cannot run the command on {}: {}", host, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Hold the `tunnel` forwards open to `ip` with the system `ssh` until it
+/// ends, and exit with its status.
+fn run_tunnel(config: &SshConfig, ip: &str, tunnel: &TunnelArgs, out: &OutputWriter) -> ExitCode {
+    let status = connect::Login::new(config, ip).and_then(|login| {
+        out.tunnel(ip, &tunnel.forwards);
+        login.forward(&tunnel.forwards).run()
+    });
+    match status {
+        Ok(status) => match status.code() {
+            Some(code) => exit_code(code),
+            None => ExitCode::FAILURE,
+        },
+        Err(e) => {
+            eprintln!("FAILED   Cannot run ssh to {}: {}", ip, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Copy the `push` file to the `found` device.
+async fn run_push(
+    config: SshConfig,
+    found: &ScanMatch,
+    push: &PushArgs,
+    out: &OutputWriter,
+) -> ExitCode {
+    let push = push.clone();
+    let ip = found.ip.clone();
+    let copied = tokio::task::spawn_blocking(move || {
+        config.upload(&ip, &push.local, &push.remote)
+    })
+    .await
+    .expect("SFTP task panicked");
+    match copied {
+        Ok((remote, bytes)) => {
+            out.found(found, None);
+            out.copied(bytes, &scp_path(&found.ip, &remote));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            out.failed(&e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Copy the `pull` file from the `found` device.
+async fn run_pull(
+    config: SshConfig,
+    found: &ScanMatch,
+    pull: &PullArgs,
+    out: &OutputWriter,
+) -> ExitCode {
+    let pull = pull.clone();
+    let ip = found.ip.clone();
+    let copied = tokio::task::spawn_blocking(move || {
+        config.download(&ip, &pull.remote, &pull.local)
+    })
+    .await
+    .expect("SFTP task panicked");
+    match copied {
+        Ok((local, bytes)) => {
+            out.found(found, None);
+            out.copied(bytes, &local.display().to_string());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            out.failed(&e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `ip:path` as `scp` writes it, bracketing IPv6 addresses.
+fn scp_path(ip: &str, path: &str) -> String {
+    if ip.contains(':') {
+        format!("[{}]:{}", ip, path)
+    } else {
+        format!("{}:{}", ip, path)
+    }
+}
+
+/// The exit code for a remote or child process status; statuses outside
+/// 0–255 become 1.
+fn exit_code(status: i32) -> ExitCode {
+    ExitCode::from(u8::try_from(status).unwrap_or(1))
+}
+
+/// Build the scanner for the selected backend, plus the SSH settings it
+/// uses (`None` for ARP and beacons). Prints why and returns `None` if SSH
+/// credentials are missing.
+fn backend(
+    args: &CliArgs,
+    targets: Vec<String>,
+    out: &OutputWriter,
+) -> Option<(Scanner, Option<SshConfig>)> {
+    let device = args.device_command();
+    if args.method == Method::Beacon {
+        let ssh_only = [
+            (args.verify_after_found, "--verify-after-found"),
+            (args.connect, "--connect"),
+            (device.is_some(), device.unwrap_or_default()),
+            (args.arp_shortcut.is_some(), "--arp-shortcut"),
+            (args.gateway.is_some(), "--gateway"),
+            (args.dhcp_server.is_some(), "--dhcp-server"),
+            (args.mdns, "--mdns"),
+            (args.ssdp, "--ssdp"),
+        ];
+        if let Some((_, flag)) = ssh_only.iter().find(|(set, _)| *set) {
+            eprintln!("FAILED   {} needs SSH and cannot be combined with --method beacon.", flag);
+            return None;
+        }
+        #[cfg(feature = "arp")]
+        if args.arp_scan.is_some() {
+            eprintln!("FAILED   --arp-scan and --method beacon are alternatives; pick one.");
+            return None;
+        }
+        let config = BeaconConfig {
+            port: args.beacon_port,
+            timeout: args.timeout(),
+            solicit: args.beacon_solicit,
+        };
+        return Some((Scanner::new_beacon(config, targets), None));
+    }
+
+    #[cfg(feature = "arp")]
+    if let Some(interface) = &args.arp_scan {
+        let ssh_only = [
+            (args.verify_after_found, "--verify-after-found"),
+            (args.connect, "--connect"),
+            (device.is_some(), device.unwrap_or_default()),
+            (args.arp_shortcut.is_some(), "--arp-shortcut"),
+            (args.gateway.is_some(), "--gateway"),
+            (args.dhcp_server.is_some(), "--dhcp-server"),
+            (args.mdns, "--mdns"),
+            (args.ssdp, "--ssdp"),
+        ];
+        if let Some((_, flag)) = ssh_only.iter().find(|(set, _)| *set) {
+            eprintln!("FAILED   {} needs SSH and cannot be combined with --arp-scan.", flag);
+            return None;
+        }
+        let config = arp_scan::ArpConfig {
+            interface: interface.clone(),
+            timeout: args.timeout(),
+        };
+        return Some((Scanner::new_arp(config, targets), None));
+    }
+
+    if args.probe_regex.is_some() && args.probe_cmd.is_none() {
+        eprintln!("FAILED   --probe-regex needs --probe-cmd (or probe-cmd in the config).");
+        return None;
+    }
+
+    let auth = match (&args.key_path, &args.password) {
+        (Some(path), passphrase) => AuthenticationMethod::PrivateKey {
+            path: path.clone(),
+            passphrase: passphrase.clone(),
+        },
+        (None, Some(pwd)) => AuthenticationMethod::Password(pwd.clone()),
+        (None, None) if ssh_client::agent_available() => {
+            tracing::info!("No --key or --password; authenticating with ssh-agent");
+            AuthenticationMethod::Agent
+        }
+        (None, None) => {
+            eprintln!(
+                "FAILED   Either --key or --password must be given, or an ssh-agent must be \
+                 running (SSH_AUTH_SOCK)."
+            );
+            return None;
+        }
+    };
+
+    if args.print_key_fingerprint {
+        match key_fingerprint::describe(&auth) {
+            Ok(Some(fingerprint)) => out.key_fingerprint(&fingerprint),
+            Ok(None) => tracing::info!("Password authentication: no key to fingerprint"),
+            Err(e) => tracing::warn!("Could not fingerprint key: {}", e),
+        }
+    }
+
+    let config = SshConfig {
+        user: args.user().to_string(),
+        port: 22,
+        auth,
+        timeouts: args.ssh_timeouts(),
+        pinned_host_keys: args.pin_hostkey.iter().cloned().collect(),
+        host_key_check: args.host_key_check(),
+        interface_filter: args.interface_regex.clone(),
+        probe: args.probe(),
+        platform: args.platform(),
+        via: args.via.clone(),
+        details: args.details,
+        // The follow-up command reuses the session of the found host.
+        pool: ((args.verify_after_found || args.device_command().is_some())
+            && !args.no_session_pool)
+            .then(|| SessionPool::new(ssh_client::DEFAULT_KEEPALIVE)),
+    };
+    Some((Scanner::new(config.clone(), targets), Some(config)))
+}
+
+/// Run `request` for `targets` and reduce the report to the match(es) the
+/// CLI reports — all of them with `--all`, else the first per MAC —
+/// printing stats and inventory summaries on the way.
+async fn sweep(
+    args: &CliArgs,
+    scanner: Scanner,
+    targets: &[String],
+    request: ScanRequest,
+    do_not_scan: Vec<ipnet::IpNet>,
+    out: &OutputWriter,
+) -> Result<Vec<ScanMatch>, RadarError> {
+    #[cfg(any(feature = "webhook", feature = "sqlite"))]
+    let range = scanned_range(args, &request);
+    #[cfg(feature = "sqlite")]
+    let started = SystemTime::now();
+    let mut ip_cache = args.ip_cache_path().map(|path| IpCache::load(&path));
+    let priority = ip_cache.as_ref().map(|c| c.priority_hosts(targets));
+    let scanner = scanner.with_priority_hosts(priority.unwrap_or_default());
+    let report = scan(args, scanner, request, do_not_scan, out).await;
+    #[cfg(feature = "sqlite")]
+    record_history(args, "scan", targets, &range, started, report.as_ref());
+    if let (Some(ip_cache), Ok(report)) = (&mut ip_cache, &report) {
+        ip_cache.update(&report.matches);
+    }
+
+    #[cfg(feature = "webhook")]
+    if let (Some(url), Ok(report)) = (&args.webhook, &report) {
+        let (url, mac, report) = (url.clone(), targets.join(", "), report.clone());
+        tokio::task::spawn_blocking(move || notifier::post(&url, &mac, &range, &report))
+            .await
+            .expect("webhook task panicked");
+    }
+
+    report.and_then(|report| {
+        summarize(args, &report, out);
+
+        if report.matches.is_empty() {
+            return Err(report.not_found(&targets.join(", ")));
+        }
+        if args.fail_on_multiple {
+            for (mac, _) in report.found() {
+                let ips: Vec<String> = report
+                    .matches
+                    .iter()
+                    .filter(|m| m.mac == mac)
+                    .map(|m| m.ip.clone())
+                    .collect();
+                if ips.len() > 1 {
+                    return Err(RadarError::MultipleMatches(mac, ips));
+                }
+            }
+        }
+        if args.all {
+            return Ok(report.matches);
+        }
+
+        // Matches are sorted by address: keep the lowest IP of each MAC.
+        let mut found: Vec<ScanMatch> = Vec::new();
+        for m in report.matches {
+            if !found.iter().any(|f| f.mac == m.mac) {
+                found.push(m);
+            }
+        }
+        Ok(found)
+    })
+}
+
+/// Probe every host `request` covers and print the inventory table.
+async fn run_inventory(
+    args: &CliArgs,
+    request: ScanRequest,
+    do_not_scan: Vec<ipnet::IpNet>,
+    out: &OutputWriter,
+    audit: &mut audit::Audit,
+) -> ExitCode {
+    let Some((scanner, _)) = backend(args, Vec::new(), out) else {
+        return Exit::InvalidArgs.into();
+    };
+
+    #[cfg(feature = "sqlite")]
+    let (range, started) = (scanned_range(args, &request), SystemTime::now());
+    let report = scan(args, scanner, request, do_not_scan, out).await;
+    #[cfg(feature = "sqlite")]
+    record_history(args, "inventory", &[], &range, started, report.as_ref());
+    match &report {
+        Ok(report) if report.inventory.as_ref().is_some_and(|hosts| !hosts.is_empty()) => {
+            audit.record(None, Ok(None))
+        }
+        Ok(report) => audit.record(None, Err(&report.not_found("any device"))),
+        Err(e) => audit.record(None, Err(e)),
+    }
+    match report {
+        Ok(report) => {
+            summarize(args, &report, out);
+            let hosts = report.inventory.as_deref().unwrap_or_default();
+            out.inventory(hosts, report.first_error());
+            match report.reason {
+                _ if !hosts.is_empty() => Exit::Found.into(),
+                Some(NotFoundReason::AllHostsFailed { .. }) => Exit::Unreachable.into(),
+                _ => Exit::Failure.into(),
+            }
+        }
+        Err(e) => {
+            out.failed(&e);
+            Exit::of(&e).into()
+        }
+    }
+}
+
+/// Search for `targets` again `--interval` after each scan until Ctrl+C,
+/// reporting every MAC that shows up, moves to another IP or disappears.
+/// With `--checkpoint`, the last known IPs survive restarts; with
+/// `--metrics-listen`, Prometheus can scrape the counters meanwhile.
+async fn run_watch(
+    args: &CliArgs,
+    targets: &[String],
+    watch: &WatchArgs,
+    request: ScanRequest,
+    do_not_scan: Vec<ipnet::IpNet>,
+    out: &OutputWriter,
+    audit: &mut audit::Audit,
+) -> ExitCode {
+    if let Some(prefix) = targets.iter().find(|t| oui::is_prefix(t)) {
+        eprintln!("FAILED   watch needs whole MAC addresses, not the prefix {}.", prefix);
+        return Exit::InvalidArgs.into();
+    }
+    let mut checkpoint = match args.checkpoint.as_deref().map(Checkpoint::load).transpose() {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            out.failed(&e);
+            return Exit::of(&e).into();
+        }
+    };
+    let mut watcher = Watcher::new(targets, checkpoint.as_ref());
+    #[cfg(feature = "mqtt")]
+    let publisher = args.mqtt();
+    let metrics = Metrics::new(targets);
+    let mut ip_cache = args.ip_cache_path().map(|path| IpCache::load(&path));
+    let pool = (!args.no_session_pool).then(|| SessionPool::new(ssh_client::DEFAULT_KEEPALIVE));
+    if let Some(addr) = watch.metrics_listen {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => {
+                tokio::spawn(metrics::serve(listener, metrics.clone()));
+            }
+            Err(e) => {
+                eprintln!("FAILED   Cannot serve metrics on {}: {}", addr, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let request = ScanRequest {
+        mode: ScanMode::FirstMatch,
+        ..request
+    };
+
+    loop {
+        let Some((scanner, _)) = backend(args, targets.to_vec(), out) else {
+            return Exit::InvalidArgs.into();
+        };
+        let priority = ip_cache.as_ref().map(|c| c.priority_hosts(targets));
+        let mut scanner = configure(args, scanner, do_not_scan.clone(), out)
+            .with_priority_hosts(priority.unwrap_or_default());
+        if let Some(pool) = &pool {
+            scanner = scanner.with_session_pool(pool.clone());
+        }
+        #[cfg(feature = "sqlite")]
+        let started = SystemTime::now();
+        let report = tokio::select! {
+            report = run_traced(args, scanner, request.clone(), out) => report,
+            _ = tokio::signal::ctrl_c() => return ExitCode::SUCCESS,
+        };
+        #[cfg(feature = "sqlite")]
+        {
+            let range = scanned_range(args, &request);
+            record_history(args, "watch", targets, &range, started, report.as_ref());
+        }
+        let report = match report {
+            Ok(report) => report,
+            Err(e) => {
+                audit.record_error(&e);
+                out.failed(&e);
+                return Exit::of(&e).into();
+            }
+        };
+        // The audit log keeps where the last round found each target.
+        for mac in targets {
+            match report.matches.iter().find(|m| &m.mac == mac) {
+                Some(m) => audit.record(Some(mac), Ok(Some(m.ip.clone()))),
+                None => audit.record(Some(mac), Err(&report.not_found(mac))),
+            }
+        }
+
+        metrics.record(&report);
+        if let Some(ip_cache) = &mut ip_cache {
+            ip_cache.update(&report.matches);
+        }
+        let changes = watcher.update(&report.matches);
+        for change in &changes {
+            out.watch(change);
+            if let (Some(checkpoint), Some((mac, ip))) = (&mut checkpoint, change.located()) {
+                checkpoint.record(mac, ip);
+            }
+            if args.notify {
+                desktop_notify::show(out.watch_title(change), &out.describe(change));
+            }
+        }
+        #[cfg(feature = "webhook")]
+        if let Some(url) = args.webhook.clone() {
+            let template = args.webhook_template.clone();
+            let changes: Vec<_> = changes.iter().map(|c| (c.clone(), out.describe(c))).collect();
+            tokio::task::spawn_blocking(move || {
+                for (change, text) in &changes {
+                    notifier::post_change(&url, template.as_ref(), change, text);
+                }
+            })
+            .await
+            .expect("webhook task panicked");
+        }
+        #[cfg(feature = "mqtt")]
+        if let Some(publisher) = publisher.clone() {
+            tokio::task::spawn_blocking(move || {
+                publisher.publish(&changes.iter().map(mqtt::Message::change).collect::<Vec<_>>())
+            })
+            .await
+            .expect("MQTT task panicked");
+        }
+        tracing::info!(
+            "Watch scan done in {:.1}s; next in {}",
+            report.stats.elapsed.as_secs_f64(),
+            humantime::format_duration(watch.interval)
+        );
+
+        tokio::select! {
+            _ = tokio::time::sleep(watch.interval) => {}
+            _ = tokio::signal::ctrl_c() => return ExitCode::SUCCESS,
+        }
+    }
+}
+
+/// What `request` scans, for reports: the range, or the hosts file that
+/// replaced it.
+#[cfg(any(feature = "webhook", feature = "sqlite"))]
+fn scanned_range(args: &CliArgs, request: &ScanRequest) -> String {
+    match &args.hosts_file {
+        Some(path) => path.display().to_string(),
+        None => request.range.clone(),
+    }
+}
+
+/// Append the scan of `targets` that started at `started` and ended with
+/// `report` to the scan history, unless `--no-history`. Failures only warn.
+#[cfg(feature = "sqlite")]
+fn record_history(
+    args: &CliArgs,
+    source: &str,
+    targets: &[String],
+    range: &str,
+    started: SystemTime,
+    report: Result<&ScanReport, &RadarError>,
+) {
+    let Some(path) = args.history_path().filter(|_| !args.no_history) else {
+        return;
+    };
+    // Inventories have no target, so finding nothing is no failure.
+    let error = match report {
+        Ok(report) if report.matches.is_empty() && !targets.is_empty() => {
+            // `not_found` folds the first error into the MAC; keep it apart.
+            let err = match report.reason {
+                Some(NotFoundReason::Cancelled) => RadarError::Cancelled,
+                _ => RadarError::MacNotFound(targets.join(", ")),
+            };
+            Some(match report.first_error() {
+                Some(first) => format!("{}; first error: {}", err, first),
+                None => err.to_string(),
+            })
+        }
+        Ok(_) => None,
+        Err(e) => Some(e.to_string()),
+    };
+    let record = sqlite_store::ScanRecord {
+        started_at: started,
+        source,
+        profile: args.profile.as_deref(),
+        targets,
+        range,
+        elapsed: started.elapsed().unwrap_or_default(),
+        report: report.ok(),
+        error: error.as_deref(),
+    };
+    match sqlite_store::record_scan(&path, &record) {
+        Ok(id) => tracing::debug!("Recorded scan {} in {}", id, path.display()),
+        Err(e) => tracing::warn!("Failed to record the scan in {}: {}", path.display(), e),
+    }
+}
+
+/// Print past scans, or the last location of every MAC, from the history.
+#[cfg(feature = "sqlite")]
+fn run_history(args: &CliArgs, history: &HistoryArgs, out: &OutputWriter) -> ExitCode {
+    let Some(path) = args.history_path() else {
+        eprintln!("FAILED   No config directory for the scan history: pass --history-db.");
+        return ExitCode::FAILURE;
+    };
+    let result = if history.last_seen {
+        sqlite_store::last_seen(&path).map(|rows| out.last_seen(&rows))
+    } else {
+        sqlite_store::history(&path, history.target_mac.as_deref(), history.limit)
+            .map(|scans| out.history(&scans))
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            out.failed(&RadarError::Config(path.display().to_string(), e.to_string()));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Configure `scanner` and run `request`, with a progress bar on stderr
+/// when it is a terminal, the output is text and `-v` does not trace it.
+///
+/// The first Ctrl+C cancels the scan, which then reports what it found so
+/// far; a second one exits immediately.
+async fn scan(
+    args: &CliArgs,
+    scanner: Scanner,
+    request: ScanRequest,
+    do_not_scan: Vec<ipnet::IpNet>,
+    out: &OutputWriter,
+) -> Result<ScanReport, RadarError> {
+    let cancel = CancelToken::default();
+    // Ends with the scan, so Ctrl+C reaches a tunnel or login started
+    // afterwards instead.
+    let handler = tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eprintln!("\nCancelling scan; press Ctrl+C again to quit.");
+                cancel.cancel();
+            }
+            let _ = tokio::signal::ctrl_c().await;
+            std::process::exit(130);
+        }
+    });
+
+    let scanner = configure(args, scanner, do_not_scan, out).with_cancel(cancel);
+    let report = if !out.is_text() || args.verbose > 0 || !std::io::stderr().is_terminal() {
+        run_traced(args, scanner, request, out).await
+    } else {
+        let progress = Arc::new(ScanProgress::default());
+        let bar = ProgressBar::start(progress.clone());
+        let report = scanner.with_progress(progress).run(request).await;
+        bar.finish();
+        report
+    };
+    handler.abort();
+    report
+}
+
+/// Run `request`; with `-v`, trace each host on stderr as its probe ends.
+async fn run_traced(
+    args: &CliArgs,
+    scanner: Scanner,
+    request: ScanRequest,
+    out: &OutputWriter,
+) -> Result<ScanReport, RadarError> {
+    if args.verbose == 0 {
+        return scanner.run(request).await;
+    }
+    let mut stream = scanner.scan_stream(request);
+    while let Some(host) = stream.next().await {
+        out.host_trace(&host);
+    }
+    stream.report().await
+}
+
+/// Apply the scan options shared by every sweep to `scanner`.
+fn configure(
+    args: &CliArgs,
+    scanner: Scanner,
+    do_not_scan: Vec<ipnet::IpNet>,
+    out: &OutputWriter,
+) -> Scanner {
+    let mut scanner = scanner
+        .with_jitter(args.jitter())
+        .with_concurrency(args.concurrency())
+        .with_adaptive_concurrency(!args.fixed_concurrency)
+        .with_do_not_scan(do_not_scan)
+        .with_range_parse_budget(args.max_range_parse_time)
+        .with_quiet(!out.is_text());
+    if args.local_arp {
+        scanner = scanner.with_local_arp_shortcut();
+    }
+    if let Some(helper) = &args.arp_shortcut {
+        scanner = scanner.with_arp_shortcut(helper.clone());
+    }
+    if args.reverse_dns {
+        scanner = scanner.with_reverse_dns(rdns::DEFAULT_TIMEOUT);
+    }
+    if let Some(mdns) = args.mdns() {
+        scanner = scanner.with_mdns(mdns);
+    }
+    if let Some(ssdp) = args.ssdp() {
+        scanner = scanner.with_ssdp(ssdp);
+    }
+    #[cfg(feature = "snmp")]
+    if let Some(snmp) = args.snmp_fallback() {
+        scanner = scanner.with_snmp_fallback(snmp);
+    }
+    if let Some(gateway) = &args.gateway {
+        scanner = scanner.with_gateway(gateway.clone());
+    }
+    if let Some(server) = &args.dhcp_server {
+        scanner = scanner.with_dhcp_server(server.clone(), args.lease_file.clone());
+    }
+    scanner = scanner.with_prescan(args.prescan()).with_retry(args.retry());
+    if let Some(percent) = args.sample {
+        scanner = scanner.with_sample(Sample {
+            percent,
+            seed: args.seed,
+        });
+    }
+    scanner
+}
+
+/// Print the stats line and the per-host and inventory summaries the options
+/// ask for.
+fn summarize(args: &CliArgs, report: &ScanReport, out: &OutputWriter) {
+    out.discovered(&report.discovered);
+    out.stats(&report.stats);
+    out.hosts(&report.hosts);
+
+    if let Some(path) = &args.report_file {
+        match csv_export::write_file(path, &report.hosts, args.mac_case) {
+            Ok(()) => {
+                tracing::info!("Wrote {} host row(s) to {}", report.hosts.len(), path.display())
+            }
+            Err(e) => tracing::warn!("Failed to write report {}: {}", path.display(), e),
+        }
+    }
+
+    if args.vendor_summary {
+        let inventory = report.inventory.as_deref().unwrap_or_default();
+        out.vendor_summary(&oui::vendor_counts(inventory));
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let (Some(path), Some(inventory)) = (&args.sqlite, &report.inventory) {
+        match sqlite_store::write_inventory(path, inventory) {
+            Ok(rows) => tracing::info!("Wrote {} inventory row(s) to {}", rows, path.display()),
+            Err(e) => tracing::warn!("Failed to write SQLite inventory {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Run a quick health check on the found device. Only the matched host is
+/// contacted, after the scan has finished.
+async fn verify_uptime(config: SshConfig, ip: String) -> Result<String, RadarError> {
+    tokio::task::spawn_blocking(move || {
+        config.run_command(&ip, "uptime 2>/dev/null || cat /proc/uptime")
+    })
+    .await
+    .expect("verify task panicked")
+}
+
+/// Locate the MAC in a switch forwarding database over SNMP.
+#[cfg(feature = "snmp")]
+async fn run_snmp(
+    args: &CliArgs,
+    mac: &str,
+    spec: &str,
+    out: &OutputWriter,
+    audit: &mut audit::Audit,
+) -> ExitCode {
+    let result = match snmp_client::SnmpTarget::parse(spec) {
+        Ok(target) => {
+            let mac = mac.to_string();
+            let timeout = args.timeout();
+            tokio::task::spawn_blocking(move || snmp_client::locate(&target, &mac, timeout))
+                .await
+                .expect("SNMP task panicked")
+        }
+        Err(e) => Err(e),
+    };
+
+    let switch = spec.rsplit_once('@').map_or(spec, |(_, s)| s);
+    audit.range = format!("snmp:{}", switch);
+    audit.record(Some(mac), result.as_ref().map(|_| None));
+
+    match result {
+        Ok(entry) => {
+            out.located(&entry);
+            Exit::Found.into()
+        }
+        Err(e) => {
+            out.failed(&e);
+            Exit::of(&e).into()
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use radar_ip_core::report::{HostStatus, ScanReport};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, info};
+
+/// How long a scraper may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Counters of every scan `radar-ip watch` has run, served in the
+/// Prometheus text format by [`serve`].
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    scans: u64,
+    hosts_probed: u64,
+    auth_failures: u64,
+    timeouts: u64,
+    /// Watched MAC → when a scan last found it.
+    last_seen: BTreeMap<String, Option<SystemTime>>,
+}
+
+impl Metrics {
+    /// Metrics for `targets`, none of them seen yet.
+    pub fn new(targets: &[String]) -> Arc<Self> {
+        let last_seen = targets.iter().map(|mac| (mac.to_lowercase(), None)).collect();
+        Arc::new(Self {
+            state: Mutex::new(State {
+                last_seen,
+                ..State::default()
+            }),
+        })
+    }
+
+    /// Count the scan `report` describes.
+    pub fn record(&self, report: &ScanReport) {
+        let now = SystemTime::now();
+        let mut state = self.state.lock().unwrap();
+        state.scans += 1;
+        state.hosts_probed += report.stats.hosts_probed as u64;
+        let count = |status| report.hosts.iter().filter(|h| h.status == status).count() as u64;
+        state.auth_failures += count(HostStatus::AuthFailed);
+        state.timeouts += count(HostStatus::Timeout);
+        for m in &report.matches {
+            if let Some(seen) = state.last_seen.get_mut(&m.mac.to_lowercase()) {
+                *seen = Some(now);
+            }
+        }
+    }
+
+    /// The Prometheus text exposition of every metric. A MAC that was
+    /// never found reports a last-seen time of 0.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut text = String::new();
+        let counters = [
+            ("scans_total", "Scans run.", state.scans),
+            ("hosts_probed_total", "Hosts whose probe finished.", state.hosts_probed),
+            ("auth_failures_total", "Probes whose login was refused.", state.auth_failures),
+            ("timeouts_total", "Probes that did not answer in time.", state.timeouts),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(text, "# HELP radar_ip_{} {}", name, help);
+            let _ = writeln!(text, "# TYPE radar_ip_{} counter", name);
+            let _ = writeln!(text, "radar_ip_{} {}", name, value);
+        }
+        let name = "radar_ip_last_seen_timestamp_seconds";
+        let _ = writeln!(text, "# HELP {} When a scan last found the MAC (Unix time).", name);
+        let _ = writeln!(text, "# TYPE {} gauge", name);
+        for (mac, seen) in &state.last_seen {
+            let secs = seen
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            let _ = writeln!(text, "{}{{mac=\"{}\"}} {}", name, mac, secs);
+        }
+        text
+    }
+}
+
+/// Answer `GET /metrics` on `listener` with [`Metrics::render`], and
+/// every other request with 404, until the task is dropped.
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>) {
+    if let Ok(addr) = listener.local_addr() {
+        info!("Serving metrics on http://{}/metrics", addr);
+    }
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                debug!("Metrics listener: {}", e);
+                continue;
+            }
+        };
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            // The request line is all that matters; headers and any body
+            // are ignored.
+            let mut request = [0u8; 1024];
+            let read = tokio::time::timeout(REQUEST_TIMEOUT, stream.read(&mut request)).await;
+            let Ok(Ok(len)) = read else {
+                return debug!("Metrics request from {} failed", peer);
+            };
+            let request = String::from_utf8_lossy(&request[..len]);
+            let mut parts = request.split_whitespace();
+            let (status, body) = match (parts.next(), parts.next()) {
+                (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+                _ => ("404 Not Found", "Not found; try /metrics\n".to_string()),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                debug!("Metrics response to {} failed: {}", peer, e);
+            }
+            let _ = stream.shutdown().await;
+        });
+    }
+}
@@ -0,0 +1,167 @@
+use crate::watch::Change;
+use radar_ip_core::report::ScanReport;
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Upper bound on the whole webhook request.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body POSTed to the webhook. `text` makes it readable as-is by Slack and
+/// Teams incoming webhooks; the remaining fields carry the full report.
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    text: String,
+    target_mac: &'a str,
+    range: &'a str,
+    report: &'a ScanReport,
+}
+
+/// Body POSTed for a `watch` change when there is no [`Template`]; `ip` and
+/// `previous_ip` are set as in the CLI's JSON output.
+#[derive(Debug, Serialize)]
+struct EventPayload<'a> {
+    text: String,
+    event: &'static str,
+    mac: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ip: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_ip: Option<&'a str>,
+    timestamp: String,
+}
+
+/// A `--webhook-template`: the JSON body to POST for a `watch` change, with
+/// `{event}`, `{mac}`, `{ip}`, `{previous_ip}`, `{timestamp}` and `{text}`
+/// replaced by the change's values, escaped for use inside JSON strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(String);
+
+impl Template {
+    /// Take the template text, or read it from the file after a leading
+    /// `@`. It must be valid JSON once filled in.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let text = match s.strip_prefix('@') {
+            Some(path) => {
+                std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?
+            }
+            None => s.to_string(),
+        };
+        let template = Self(text);
+        let sample = template.render(&[
+            ("event", "found"),
+            ("mac", "aa:bb:cc:dd:ee:ff"),
+            ("ip", "192.0.2.1"),
+            ("previous_ip", ""),
+            ("timestamp", "2000-01-01T00:00:00Z"),
+            ("text", "radar-ip"),
+        ]);
+        serde_json::from_str::<serde_json::Value>(&sample)
+            .map_err(|e| format!("not valid JSON once filled in: {}", e))?;
+        Ok(template)
+    }
+
+    fn render(&self, fields: &[(&str, &str)]) -> String {
+        fields.iter().fold(self.0.clone(), |body, (name, value)| {
+            let quoted = serde_json::Value::from(*value).to_string();
+            body.replace(&format!("{{{}}}", name), &quoted[1..quoted.len() - 1])
+        })
+    }
+}
+
+/// POST `report` as JSON to the webhook at `url`.
+///
+/// The payload never contains credentials, and the URL itself (which often
+/// embeds a token) is not logged. Failures are logged as warnings and never
+/// fail the scan. This is a **blocking** call.
+pub fn post(url: &str, target_mac: &str, range: &str, report: &ScanReport) {
+    let payload = Payload {
+        text: summary(target_mac, range, report),
+        target_mac,
+        range,
+        report,
+    };
+    match serde_json::to_string(&payload) {
+        Ok(body) => send(url, &body, "report"),
+        Err(e) => warn!("Failed to encode webhook payload: {}", e),
+    }
+}
+
+/// POST a `watch` change to the webhook at `url`: `template` filled in, or
+/// a JSON object with `text` (the change as a sentence) and its fields.
+///
+/// Same guarantees as [`post`]. This is a **blocking** call.
+pub fn post_change(url: &str, template: Option<&Template>, change: &Change, text: &str) {
+    let (mac, ip, previous_ip) = match change {
+        Change::Found { mac, ip } => (mac, Some(ip.as_str()), None),
+        Change::Moved { mac, from, to } => (mac, Some(to.as_str()), Some(from.as_str())),
+        Change::Lost { mac, ip } => (mac, None, Some(ip.as_str())),
+    };
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let text = format!("radar-ip: {}", text);
+    let body = match template {
+        Some(template) => template.render(&[
+            ("event", change.event()),
+            ("mac", mac),
+            ("ip", ip.unwrap_or_default()),
+            ("previous_ip", previous_ip.unwrap_or_default()),
+            ("timestamp", &timestamp),
+            ("text", &text),
+        ]),
+        None => {
+            let payload = EventPayload {
+                text,
+                event: change.event(),
+                mac,
+                ip,
+                previous_ip,
+                timestamp,
+            };
+            match serde_json::to_string(&payload) {
+                Ok(body) => body,
+                Err(e) => return warn!("Failed to encode webhook payload: {}", e),
+            }
+        }
+    };
+    send(url, &body, change.event());
+}
+
+/// POST the JSON `body`, logging the outcome; `what` names it in the log.
+fn send(url: &str, body: &str, what: &str) {
+    let agent = ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).build();
+    match agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .send_string(body)
+    {
+        Ok(resp) => info!("Webhook accepted the {} (HTTP {})", what, resp.status()),
+        Err(ureq::Error::Status(code, _)) => {
+            warn!("Webhook to {} rejected the {}: HTTP {}", redact(url), what, code)
+        }
+        Err(e) => warn!("Webhook to {} failed: {}", redact(url), e.kind()),
+    }
+}
+
+/// One-line, human-readable outcome for chat clients.
+fn summary(target_mac: &str, range: &str, report: &ScanReport) -> String {
+    let stats = &report.stats;
+    match report.matches.as_slice() {
+        [] => format!(
+            "radar-ip: {} not found in {} ({}/{} host(s) probed)",
+            target_mac, range, stats.hosts_probed, stats.hosts_total
+        ),
+        matches => {
+            let ips: Vec<&str> = matches.iter().map(|m| m.ip.as_str()).collect();
+            format!("radar-ip: {} found at {}", target_mac, ips.join(", "))
+        }
+    }
+}
+
+/// Scheme and host of `url`, dropping the path and query that usually hold
+/// the webhook token.
+fn redact(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let host = rest.split(['/', '?']).next().unwrap_or_default();
+    let host = host.rsplit_once('@').map_or(host, |(_, h)| h);
+    format!("{}://{}/…", scheme, host)
+}
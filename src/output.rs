@@ -0,0 +1,797 @@
+use crate::ansible;
+use crate::csv_export;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_store::{LastSeen, StoredScan};
+use crate::watch::Change;
+use clap::ValueEnum;
+use radar_ip_core::errors::RadarError;
+use radar_ip_core::oui;
+use radar_ip_core::report::{Discovered, HostResult, ScanMatch, ScanStats};
+#[cfg(feature = "snmp")]
+use radar_ip_core::snmp_client::FdbEntry;
+use radar_ip_core::ssh_client::{DeviceDetails, DeviceIdentity, InterfaceAddress};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Letter case used when rendering MAC addresses.
+///
+/// Matching is always case-insensitive; this only affects display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MacCase {
+    #[default]
+    Lower,
+    Upper,
+}
+
+impl MacCase {
+    /// Render `mac` in this case.
+    pub fn apply(self, mac: &str) -> String {
+        match self {
+            MacCase::Lower => mac.to_lowercase(),
+            MacCase::Upper => mac.to_uppercase(),
+        }
+    }
+}
+
+/// How the CLI renders its outcome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable banners and progress lines.
+    #[default]
+    Text,
+    /// A single JSON [`Outcome`] on stdout; progress goes to the logger
+    /// only.
+    Json,
+    /// One CSV row per probed host on stdout; failures go to stderr.
+    Csv,
+    /// An Ansible INI inventory of the found devices (of every probed host
+    /// for `inventory`); failures go to stderr.
+    Ansible,
+    /// The same inventory in YAML.
+    AnsibleYaml,
+}
+
+/// Renders the outcome of one CLI invocation to stdout.
+#[derive(Debug, Clone)]
+pub struct OutputWriter {
+    mac_case: MacCase,
+    format: OutputFormat,
+    target_macs: Vec<String>,
+    range: Option<String>,
+    /// Text mode without banners or progress lines; failures go to stderr.
+    quiet: bool,
+    /// Group, user and key of `--format ansible`.
+    ansible: ansible::Vars,
+    /// Counters of the last sweep, echoed in the JSON outcome.
+    stats: RefCell<Option<ScanStats>>,
+    /// What its mDNS and SSDP passes found, echoed likewise.
+    discovered: RefCell<Vec<Discovered>>,
+}
+
+impl OutputWriter {
+    /// Create a writer for a search for `target_macs` that renders MACs in
+    /// `mac_case`.
+    pub fn new(mac_case: MacCase, format: OutputFormat, target_macs: &[String]) -> Self {
+        Self {
+            mac_case,
+            format,
+            target_macs: target_macs.to_vec(),
+            range: None,
+            quiet: false,
+            ansible: ansible::Vars::default(),
+            stats: RefCell::new(None),
+            discovered: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Name the scanned range in the JSON outcome.
+    pub fn with_range(mut self, range: &str) -> Self {
+        self.range = Some(range.to_string());
+        self
+    }
+
+    /// Write `vars` into `--format ansible` inventories.
+    pub fn with_ansible(mut self, vars: ansible::Vars) -> Self {
+        self.ansible = vars;
+        self
+    }
+
+    /// Keep stdout free of banners and progress lines in text mode, for
+    /// `--quiet`, which prints only found IPs, and `exec`, whose stdout is
+    /// the remote command's.
+    pub fn with_quiet(mut self) -> Self {
+        self.quiet = self.format == OutputFormat::Text;
+        self
+    }
+
+    /// Whether human-readable progress lines should be printed.
+    pub fn is_text(&self) -> bool {
+        self.format == OutputFormat::Text && !self.quiet
+    }
+
+    /// Render a MAC address according to the configured case.
+    pub fn mac(&self, mac: &str) -> String {
+        self.mac_case.apply(mac)
+    }
+
+    /// Show which key will be used, before scanning.
+    pub fn key_fingerprint(&self, fingerprint: &str) {
+        if self.is_text() {
+            println!("Using key {}", fingerprint);
+        }
+    }
+
+    /// Note that a checkpoint already held `resumed` targets, leaving
+    /// `pending` to sweep for.
+    pub fn resumed(&self, resumed: usize, pending: usize) {
+        if !self.is_text() {
+            return;
+        }
+        if pending == 0 {
+            println!("Already located in checkpoint; skipping the sweep.");
+        } else {
+            println!(
+                "{} MAC(s) already located in checkpoint; sweeping for {} more.",
+                resumed, pending
+            );
+        }
+    }
+
+    /// Summarize how many hosts were probed. In JSON mode the counters are
+    /// kept for the outcome instead.
+    pub fn stats(&self, stats: &ScanStats) {
+        if !self.is_text() {
+            self.stats.replace(Some(stats.clone()));
+            return;
+        }
+        println!(
+            "Probed {}/{} host(s) in {:.1}s ({} failed){}{}",
+            stats.hosts_probed,
+            stats.hosts_total,
+            stats.elapsed.as_secs_f64(),
+            stats.hosts_failed,
+            if stats.sampled { " — sampled, non-exhaustive" } else { "" },
+            if stats.cancelled { " — cancelled" } else { "" },
+        );
+    }
+
+    /// List the hosts `--mdns` and `--ssdp` found, one line each. In JSON
+    /// mode they are kept for the outcome instead.
+    pub fn discovered(&self, discovered: &[Discovered]) {
+        if !self.is_text() {
+            self.discovered.replace(discovered.to_vec());
+            return;
+        }
+        for found in discovered {
+            let mut line = format!("  {:<4}  {:<15}  {}", found.via, found.ip, found.id);
+            for extra in [&found.name, &found.location].into_iter().flatten() {
+                line.push_str(&format!("  {}", extra));
+            }
+            if let Some(mac) = &found.mac {
+                line.push_str(&format!("  ({})", self.mac(mac)));
+            }
+            println!("{}", line);
+        }
+    }
+
+    /// Trace one host on stderr for `-v` as its probe ends, e.g.
+    /// `10.8.0.12        auth_failed      0.412s  authentication failed`.
+    pub fn host_trace(&self, host: &HostResult) {
+        let elapsed = match host.elapsed {
+            Some(elapsed) => format!("{:.3}s", elapsed.as_secs_f64()),
+            None => "-".to_string(),
+        };
+        let detail = match &host.error {
+            // Errors start with the IP, which has a column of its own.
+            Some(error) => {
+                error.strip_prefix(&format!("{}: ", host.ip)).unwrap_or(error).to_string()
+            }
+            None => host.macs.iter().map(|m| self.mac(m)).collect::<Vec<_>>().join(", "),
+        };
+        eprintln!("  {:<15}  {:<14}  {:>7}  {}", host.ip, host.status.as_str(), elapsed, detail);
+    }
+
+    /// Print one CSV row per probed host, in CSV mode only.
+    pub fn hosts(&self, hosts: &[HostResult]) {
+        if self.format == OutputFormat::Csv {
+            print!("{}", csv_export::render(hosts, self.mac_case));
+        }
+    }
+
+    /// Show how many distinct MACs each vendor accounts for, e.g.
+    /// `Vendors: 12 Raspberry Pi, 3 Cisco, 5 unknown`.
+    pub fn vendor_summary(&self, counts: &[(&str, usize)]) {
+        if !self.is_text() {
+            return;
+        }
+        let parts: Vec<String> = counts
+            .iter()
+            .map(|(vendor, n)| format!("{} {}", n, vendor))
+            .collect();
+        if parts.is_empty() {
+            println!("Vendors: no MACs observed");
+        } else {
+            println!("Vendors: {}", parts.join(", "));
+        }
+    }
+
+    /// Report a host that owns the target MAC, with the result of the
+    /// optional uptime health check.
+    pub fn found(&self, found: &ScanMatch, uptime: Option<&Result<String, RadarError>>) {
+        if self.is_ansible() {
+            return self.ansible(&[ansible::Host::from_match(found)]);
+        }
+        if self.quiet {
+            return println!("{}", found.ip);
+        }
+        if !self.is_text() {
+            let uptime = uptime.map(|r| match r {
+                Ok(uptime) => uptime.trim().to_string(),
+                Err(e) => format!("unavailable ({})", e),
+            });
+            return self.json(Outcome {
+                status: "found",
+                ip: Some(&found.ip),
+                hostname: found.hostname.as_deref(),
+                dns_name: found.dns_name.as_deref(),
+                interface: found.interface.as_deref(),
+                details: found.details.as_ref(),
+                uptime: uptime.as_deref(),
+                ..self.outcome()
+            });
+        }
+
+        println!();
+        println!("SUCCESS  Device found.");
+        println!("  MAC : {}", self.mac(&found.mac));
+        println!("  IP  : {}", found.ip);
+        if let Some(hostname) = &found.hostname {
+            println!("  HOST: {}", hostname);
+        }
+        if let Some(name) = &found.dns_name {
+            println!("  DNS : {}", name);
+        }
+        if let Some(interface) = &found.interface {
+            println!("  IF  : {}", interface);
+        }
+        let details = found.details.as_ref();
+        if let Some(os) = details.and_then(|d| d.os_release.as_ref()) {
+            println!("  OS  : {}", os);
+        }
+        if let Some(kernel) = details.and_then(|d| d.kernel.as_ref()) {
+            println!("  KERN: {}", kernel);
+        }
+        match (uptime, details.and_then(|d| d.uptime)) {
+            (Some(Ok(uptime)), _) => println!("  UP  : {}", uptime.trim()),
+            (Some(Err(e)), _) => println!("  UP  : unavailable ({})", e),
+            (None, Some(uptime)) => println!("  UP  : {}", since_boot(uptime)),
+            (None, None) => {}
+        }
+        for (i, addr) in details.iter().flat_map(|d| &d.addresses).enumerate() {
+            let label = if i == 0 { "ADDR" } else { "    " };
+            println!("  {}: {}", label, self.address(addr));
+        }
+    }
+
+    /// Report every host that owns the target MAC, one IP per line.
+    pub fn found_all(&self, found: &[ScanMatch]) {
+        if self.is_ansible() {
+            return self.ansible(&found.iter().map(ansible::Host::from_match).collect::<Vec<_>>());
+        }
+        if self.quiet {
+            return found.iter().for_each(|m| println!("{}", m.ip));
+        }
+        if !self.is_text() {
+            return self.json(Outcome {
+                status: "found",
+                ips: found.iter().map(|m| m.ip.as_str()).collect(),
+                ..self.outcome()
+            });
+        }
+
+        println!();
+        println!(
+            "SUCCESS  {} device(s) own {}.",
+            found.len(),
+            self.mac(&self.target_macs.join(", "))
+        );
+        for m in found {
+            let names = [&m.hostname, &m.dns_name];
+            let mut names: Vec<&str> = names.iter().filter_map(|n| n.as_deref()).collect();
+            names.dedup();
+            match names.as_slice() {
+                [] => println!("{}", m.ip),
+                names => println!("{}  {}", m.ip, names.join("  ")),
+            }
+        }
+    }
+
+    /// Report which of several targets were found, in the order they were
+    /// given: one line per found MAC (several for an OUI prefix), or a "not
+    /// found" line. `found` holds one match per found MAC.
+    pub fn found_many(&self, found: &[ScanMatch]) {
+        if self.is_ansible() {
+            return self.ansible(&found.iter().map(ansible::Host::from_match).collect::<Vec<_>>());
+        }
+        let matching = |target: &str| {
+            found
+                .iter()
+                .filter(|m| oui::target_matches(target, &m.mac))
+                .collect::<Vec<_>>()
+        };
+        let missing: Vec<String> = self
+            .target_macs
+            .iter()
+            .filter(|target| matching(target).is_empty())
+            .map(|target| self.mac(target))
+            .collect();
+
+        // Which IP is whose; a missing target shows in the exit code.
+        if self.quiet {
+            return found.iter().for_each(|m| println!("{}  {}", self.mac(&m.mac), m.ip));
+        }
+        if !self.is_text() {
+            return self.json(Outcome {
+                status: if missing.is_empty() { "found" } else { "partial" },
+                found: found.iter().map(|m| (self.mac(&m.mac), m.ip.as_str())).collect(),
+                missing,
+                ..self.outcome()
+            });
+        }
+
+        println!();
+        if missing.is_empty() {
+            println!("SUCCESS  Found {} device(s).", found.len());
+        } else {
+            println!(
+                "PARTIAL  Found {} device(s); {} of {} target(s) not found.",
+                found.len(),
+                missing.len(),
+                self.target_macs.len()
+            );
+        }
+        for target in &self.target_macs {
+            let hits = matching(target);
+            if hits.is_empty() {
+                println!("  {}  not found", self.mac(target));
+            }
+            for m in hits {
+                let mut line = format!("  {}  {}", self.mac(&m.mac), m.ip);
+                if let Some(hostname) = &m.hostname {
+                    line.push_str(&format!("  {}", hostname));
+                }
+                if let Some(name) = &m.dns_name {
+                    line.push_str(&format!("  {}", name));
+                }
+                if let Some(interface) = &m.interface {
+                    line.push_str(&format!(" ({})", interface));
+                }
+                println!("{}", line);
+            }
+        }
+    }
+
+    /// List every reachable host as a table of IP, hostname and MACs, or
+    /// explain why none answered.
+    pub fn inventory(&self, hosts: &[DeviceIdentity], first_error: Option<&str>) {
+        if self.is_ansible() {
+            let hosts: Vec<_> = hosts.iter().filter_map(ansible::Host::from_identity).collect();
+            return self.ansible(&hosts);
+        }
+        if self.quiet && hosts.is_empty() {
+            eprintln!("FAILED   No reachable host in the scanned range");
+            return first_error.into_iter().for_each(|e| eprintln!("  First error: {}", e));
+        }
+        if self.quiet {
+            return hosts.iter().for_each(|host| println!("{}", host.ip));
+        }
+        if !self.is_text() {
+            return self.json(Outcome {
+                status: if hosts.is_empty() { "not_found" } else { "found" },
+                hosts: Some(hosts),
+                first_error: first_error.filter(|_| hosts.is_empty()),
+                ..self.outcome()
+            });
+        }
+
+        println!();
+        if hosts.is_empty() {
+            println!("FAILED   No reachable host in the scanned range");
+            if let Some(e) = first_error {
+                println!("  First error: {}", e);
+            }
+            return;
+        }
+
+        let macs = |host: &DeviceIdentity| {
+            host.interfaces
+                .iter()
+                .map(|iface| format!("{} {}", iface.name, self.mac(&iface.mac)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let hostname = |host: &DeviceIdentity| host.hostname.clone().unwrap_or_else(|| "-".into());
+        let ip_width = hosts.iter().map(|h| h.ip.len()).max().unwrap_or(0).max(2);
+        let name_width = hosts.iter().map(|h| hostname(h).len()).max().unwrap_or(0).max(8);
+
+        println!("{:<ip_width$}  {:<name_width$}  MACS", "IP", "HOSTNAME");
+        for host in hosts {
+            let macs = if host.interfaces.is_empty() {
+                host.mac_list.iter().map(|m| self.mac(m)).collect::<Vec<_>>().join(", ")
+            } else {
+                macs(host)
+            };
+            println!("{:<ip_width$}  {:<name_width$}  {}", host.ip, hostname(host), macs);
+            if let Some(details) = &host.details {
+                let mut summary = Vec::new();
+                summary.extend(details.os_release.clone());
+                summary.extend(details.kernel.as_ref().map(|k| format!("kernel {}", k)));
+                summary.extend(details.uptime.map(|u| format!("up {}", since_boot(u))));
+                if !summary.is_empty() {
+                    println!("    {}", summary.join(", "));
+                }
+                for addr in &details.addresses {
+                    println!("    {}", self.address(addr));
+                }
+            }
+        }
+    }
+
+    fn is_ansible(&self) -> bool {
+        matches!(self.format, OutputFormat::Ansible | OutputFormat::AnsibleYaml)
+    }
+
+    /// Print `hosts` as an inventory in the Ansible format selected, MACs
+    /// in the configured case.
+    fn ansible(&self, hosts: &[ansible::Host]) {
+        let hosts: Vec<ansible::Host> = hosts
+            .iter()
+            .map(|host| ansible::Host {
+                mac: self.mac(&host.mac),
+                ..host.clone()
+            })
+            .collect();
+        if self.format == OutputFormat::AnsibleYaml {
+            print!("{}", ansible::render_yaml(&self.ansible, &hosts));
+        } else {
+            print!("{}", ansible::render_ini(&self.ansible, &hosts));
+        }
+    }
+
+    /// `eth0 192.168.1.20/24 (aa:bb:...)`, the MAC in the configured case.
+    fn address(&self, addr: &InterfaceAddress) -> String {
+        match &addr.mac {
+            Some(mac) => format!("{} {} ({})", addr.name, addr.address, self.mac(mac)),
+            None => format!("{} {}", addr.name, addr.address),
+        }
+    }
+
+    /// List stored scans for `history`, newest first.
+    #[cfg(feature = "sqlite")]
+    pub fn history(&self, scans: &[StoredScan]) {
+        // What `history` lists is its result, so `--quiet` keeps it.
+        if self.format != OutputFormat::Text {
+            return self.json(Outcome {
+                status: "ok",
+                scans: Some(scans),
+                ..self.outcome()
+            });
+        }
+
+        if scans.is_empty() {
+            return println!("No scans recorded yet.");
+        }
+        let source_width = scans.iter().map(|s| s.source.len()).max().unwrap_or(0);
+        for scan in scans {
+            let when = scan.started_at.replace('T', " ").replace('Z', "");
+            let outcome = match scan.matches.as_slice() {
+                [] if scan.source == "inventory" => format!("{} host(s) probed", scan.hosts_probed),
+                [] => "not found".to_string(),
+                matches => matches
+                    .iter()
+                    .map(|m| format!("{} {}", self.mac(&m.mac), m.ip))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            let targets = if scan.targets.is_empty() { "-" } else { &scan.targets };
+            println!(
+                "#{:<5} {}  {:<source_width$}  {}  {}  → {}  ({:.1}s)",
+                scan.id,
+                when,
+                scan.source,
+                self.mac(targets),
+                scan.ip_range,
+                outcome,
+                scan.elapsed_secs
+            );
+        }
+    }
+
+    /// List where each MAC was last located, for `history --last-seen`.
+    #[cfg(feature = "sqlite")]
+    pub fn last_seen(&self, rows: &[LastSeen]) {
+        if self.format != OutputFormat::Text {
+            return self.json(Outcome {
+                status: "ok",
+                last_seen: Some(rows),
+                ..self.outcome()
+            });
+        }
+
+        if rows.is_empty() {
+            return println!("No device located yet.");
+        }
+        let ip_width = rows.iter().map(|r| r.ip.len()).max().unwrap_or(0).max(2);
+        println!("{:<17}  {:<ip_width$}  LAST SEEN", "MAC", "IP");
+        for row in rows {
+            let when = row.seen_at.replace('T', " ").replace('Z', "");
+            println!("{:<17}  {:<ip_width$}  {}", self.mac(&row.mac), row.ip, when);
+        }
+    }
+
+    /// Report a switch port that has learned the target MAC.
+    #[cfg(feature = "snmp")]
+    pub fn located(&self, entry: &FdbEntry) {
+        if self.quiet {
+            return println!("{}", entry);
+        }
+        if !self.is_text() {
+            return self.json(Outcome {
+                status: "found",
+                location: Some(entry),
+                ..self.outcome()
+            });
+        }
+
+        println!();
+        println!("SUCCESS  Device located on switch.");
+        println!("  MAC  : {}", self.mac(&self.target_macs.join(", ")));
+        println!("  PORT : {}", entry);
+    }
+
+    /// Report a change noticed by `watch`: a timestamped line, or one JSON
+    /// object per change.
+    pub fn watch(&self, change: &Change) {
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        // The changes are what `watch` is for, so `--quiet` keeps them.
+        if self.format != OutputFormat::Text {
+            let (mac, ip, previous_ip) = match change {
+                Change::Found { mac, ip } => (mac, Some(ip.as_str()), None),
+                Change::Moved { mac, from, to } => (mac, Some(to.as_str()), Some(from.as_str())),
+                Change::Lost { mac, ip } => (mac, None, Some(ip.as_str())),
+            };
+            return self.json(Outcome {
+                status: change.event(),
+                target_mac: Some(self.mac(mac)),
+                target_macs: Vec::new(),
+                ip,
+                previous_ip,
+                timestamp: Some(timestamp),
+                ..self.outcome()
+            });
+        }
+
+        let label = match change {
+            Change::Found { .. } => "FOUND",
+            Change::Moved { .. } => "MOVED",
+            Change::Lost { .. } => "LOST",
+        };
+        println!("{}  {:<8} {}", timestamp, label, self.describe(change));
+    }
+
+    /// Title of the desktop notification for `change`.
+    pub fn watch_title(&self, change: &Change) -> &'static str {
+        match change {
+            Change::Found { .. } => "Radar-IP: device found",
+            Change::Moved { .. } => "Radar-IP: device changed IP",
+            Change::Lost { .. } => "Radar-IP: device lost",
+        }
+    }
+
+    /// `change` as a sentence, e.g. `aa:bb:cc:dd:ee:ff moved from
+    /// 10.8.0.42 to 10.8.0.57`.
+    pub fn describe(&self, change: &Change) -> String {
+        match change {
+            Change::Found { mac, ip } => format!("{} is at {}", self.mac(mac), ip),
+            Change::Moved { mac, from, to } => {
+                format!("{} moved from {} to {}", self.mac(mac), from, to)
+            }
+            Change::Lost { mac, ip } => {
+                format!("{} no longer answers (last seen at {})", self.mac(mac), ip)
+            }
+        }
+    }
+
+    /// Show the `--ssh-host` block of the found device.
+    pub fn ssh_host(&self, block: &str) {
+        if self.is_text() {
+            println!();
+            print!("{}", block);
+        }
+    }
+
+    /// Show the `--hosts-entry` line of the found device.
+    pub fn hosts_entry(&self, line: &str) {
+        if self.is_text() {
+            println!();
+            println!("{}", line);
+        }
+    }
+
+    /// Note that a file such as `~/.ssh/config` was updated.
+    pub fn wrote(&self, path: &Path) {
+        if self.is_text() {
+            println!("Updated {}", path.display());
+        } else {
+            tracing::info!("Updated {}", path.display());
+        }
+    }
+
+    /// Announce the forwards of `tunnel`, before they are set up.
+    pub fn tunnel(&self, ip: &str, forwards: &[String]) {
+        if self.is_text() {
+            println!("Forwarding {} through {}; press Ctrl+C to stop.", forwards.join(", "), ip);
+        } else {
+            tracing::info!("Forwarding {} through {}", forwards.join(", "), ip);
+        }
+    }
+
+    /// Report a file `push` or `pull` copied to `destination`.
+    pub fn copied(&self, bytes: u64, destination: &str) {
+        if self.is_text() {
+            println!("Copied {} byte(s) to {}", bytes, destination);
+        } else {
+            tracing::info!("Copied {} byte(s) to {}", bytes, destination);
+        }
+    }
+
+    /// Report a failed scan.
+    pub fn failed(&self, err: &RadarError) {
+        if self.format == OutputFormat::Csv || self.is_ansible() || self.quiet {
+            return eprintln!("FAILED   {}", err);
+        }
+        if !self.is_text() {
+            let outcome = match err {
+                RadarError::MacNotFound(detail) => Outcome {
+                    status: "not_found",
+                    first_error: detail.split_once("\n\nFirst error: ").map(|(_, e)| e),
+                    ..self.outcome()
+                },
+                RadarError::NoHostReachable(_, first_error) => Outcome {
+                    status: "not_found",
+                    first_error: Some(first_error),
+                    ..self.outcome()
+                },
+                RadarError::MultipleMatches(_, ips) => Outcome {
+                    status: "multiple_matches",
+                    ips: ips.iter().map(String::as_str).collect(),
+                    ..self.outcome()
+                },
+                _ => Outcome {
+                    status: "error",
+                    error: Some(err.to_string()),
+                    ..self.outcome()
+                },
+            };
+            return self.json(outcome);
+        }
+
+        println!();
+        println!("FAILED   {}", err);
+    }
+
+    /// Fields shared by every JSON outcome.
+    fn outcome(&self) -> Outcome<'_> {
+        let (target_mac, target_macs) = match self.target_macs.as_slice() {
+            [mac] => (Some(self.mac(mac)), Vec::new()),
+            macs => (None, macs.iter().map(|mac| self.mac(mac)).collect()),
+        };
+        Outcome {
+            target_mac,
+            target_macs,
+            scanned_range: self.range.as_deref(),
+            stats: self.stats.borrow().clone(),
+            discovered: self.discovered.borrow().clone(),
+            ..Default::default()
+        }
+    }
+
+    fn json(&self, outcome: Outcome<'_>) {
+        if self.format != OutputFormat::Json {
+            return;
+        }
+        match serde_json::to_string(&outcome) {
+            Ok(line) => println!("{}", line),
+            Err(e) => tracing::error!("Failed to encode JSON output: {}", e),
+        }
+    }
+}
+
+/// `uptime` to the minute, e.g. `3days 4h 12m`.
+fn since_boot(uptime: Duration) -> humantime::FormattedDuration {
+    humantime::format_duration(Duration::from_secs(uptime.as_secs() / 60 * 60))
+}
+
+/// The single JSON object the CLI prints with `--format json`.
+///
+/// Fields that do not apply to an outcome are omitted, never `null`; new
+/// fields may be added, existing ones keep their name and meaning.
+#[derive(Debug, Default, Serialize)]
+pub struct Outcome<'a> {
+    /// `found`, `partial`, `not_found`, `multiple_matches` or `error`;
+    /// `watch` also prints `moved` and `lost`, `history` prints `ok`.
+    pub status: &'static str,
+    /// The target, when there is only one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_mac: Option<String>,
+    /// Every target, when there are several.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub target_macs: Vec<String>,
+    /// The `--range` that was scanned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scanned_range: Option<&'a str>,
+    /// Counters of the sweep, when one ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<ScanStats>,
+    /// Hosts found by `--mdns` and `--ssdp`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub discovered: Vec<Discovered>,
+    /// IP of the host owning the single target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<&'a str>,
+    /// The found host's own name, as it reported it over SSH.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<&'a str>,
+    /// Reverse DNS name of `ip`, with `--reverse-dns`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_name: Option<&'a str>,
+    /// IP the target had before, for `watch`'s `moved` and `lost`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_ip: Option<&'a str>,
+    /// When `watch` noticed the change, RFC 3339.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Interface the single target was found on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interface: Option<&'a str>,
+    /// OS, kernel, uptime and addresses of the found host, with
+    /// `--details`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<&'a DeviceDetails>,
+    /// Every owner, with `--all` or `multiple_matches`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ips: Vec<&'a str>,
+    /// Found target MAC → IP, for several targets.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub found: BTreeMap<String, &'a str>,
+    /// Targets that were not found, for several targets.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// Every reachable host, for `inventory`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts: Option<&'a [DeviceIdentity]>,
+    /// `uptime` of the found host, with `--verify-after-found`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime: Option<&'a str>,
+    /// Stored scans, newest first, for `history`.
+    #[cfg(feature = "sqlite")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scans: Option<&'a [StoredScan]>,
+    /// Latest location of each MAC, for `history --last-seen`.
+    #[cfg(feature = "sqlite")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<&'a [LastSeen]>,
+    /// Switch port that learned the MAC, with `--snmp`.
+    #[cfg(feature = "snmp")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<&'a FdbEntry>,
+    /// First probe failure, when nothing was found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_error: Option<&'a str>,
+    /// Why the run failed, for `error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
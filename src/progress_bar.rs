@@ -0,0 +1,64 @@
+use radar_ip_core::scanner::ScanProgress;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// How often the bar is redrawn.
+const REDRAW_EVERY: Duration = Duration::from_millis(200);
+/// Width of the bar itself, in characters.
+const WIDTH: usize = 30;
+
+/// A one-line progress bar on stderr that follows a [`ScanProgress`]:
+///
+/// ```text
+/// [###########-------------------]  97/254 hosts   1 found   3 errors   12.4s
+/// ```
+pub struct ProgressBar {
+    task: JoinHandle<()>,
+}
+
+impl ProgressBar {
+    /// Start redrawing the bar for `progress` on a background task.
+    pub fn start(progress: Arc<ScanProgress>) -> Self {
+        let started = Instant::now();
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(REDRAW_EVERY);
+            loop {
+                ticks.tick().await;
+                draw(&progress, started.elapsed());
+            }
+        });
+        Self { task }
+    }
+
+    /// Stop redrawing and clear the line.
+    pub fn finish(self) {
+        self.task.abort();
+        eprint!("\r{:width$}\r", "", width = WIDTH + 72);
+        let _ = std::io::stderr().flush();
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn draw(progress: &ScanProgress, elapsed: Duration) {
+    let (done, total) = (progress.done(), progress.total());
+    let filled = (done * WIDTH).checked_div(total).unwrap_or(0).min(WIDTH);
+    eprint!(
+        "\r[{}{}] {:>w$}/{} hosts  {:>3} found  {:>3} errors  {:>6.1}s",
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled),
+        done,
+        total,
+        progress.found(),
+        progress.failed(),
+        elapsed.as_secs_f64(),
+        w = total.to_string().len(),
+    );
+    let _ = std::io::stderr().flush();
+}
@@ -0,0 +1,335 @@
+use crate::config::HistoryEntry;
+use radar_ip_core::report::ScanReport;
+use radar_ip_core::ssh_client::DeviceIdentity;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Schema for the host inventory. One row per (ip, mac, interface); each
+/// run refreshes `seen_at` so the table accumulates history over time.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS hosts (
+        ip        TEXT NOT NULL,
+        mac       TEXT NOT NULL,
+        interface TEXT NOT NULL,
+        seen_at   TEXT NOT NULL,
+        PRIMARY KEY (ip, mac, interface)
+    )";
+
+/// Upsert every interface of every host in `inventory` into the SQLite
+/// database at `path`, creating the schema if absent. Hosts known only by
+/// their MACs, as the ARP, neighbor and lease backends report them, get a
+/// row per MAC with an empty interface.
+///
+/// Returns the number of rows written.
+pub fn write_inventory(path: &Path, inventory: &[DeviceIdentity]) -> rusqlite::Result<usize> {
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let seen_at = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let tx = conn.transaction()?;
+    let mut rows = 0;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO hosts (ip, mac, interface, seen_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (ip, mac, interface) DO UPDATE SET seen_at = excluded.seen_at",
+        )?;
+        for host in inventory {
+            if host.interfaces.is_empty() {
+                for mac in &host.mac_list {
+                    rows += stmt.execute(params![host.ip, mac, "", seen_at])?;
+                }
+            }
+            for iface in &host.interfaces {
+                rows += stmt.execute(params![host.ip, iface.mac, iface.name, seen_at])?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    Ok(rows)
+}
+
+/// The scan history, in the config directory unless `--history-db` says
+/// otherwise.
+pub const HISTORY_FILE: &str = "history.db";
+
+/// Schema of the scan history: one `scans` row per scan, with the outcome
+/// of every probed host and every match.
+const HISTORY_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS scans (
+        id           INTEGER PRIMARY KEY,
+        started_at   TEXT NOT NULL,
+        source       TEXT NOT NULL,
+        profile      TEXT,
+        targets      TEXT NOT NULL,
+        ip_range     TEXT NOT NULL,
+        elapsed_secs REAL NOT NULL,
+        hosts_total  INTEGER NOT NULL,
+        hosts_probed INTEGER NOT NULL,
+        hosts_failed INTEGER NOT NULL,
+        error        TEXT
+    );
+    CREATE TABLE IF NOT EXISTS scan_hosts (
+        scan_id INTEGER NOT NULL REFERENCES scans (id),
+        ip      TEXT NOT NULL,
+        status  TEXT NOT NULL,
+        macs    TEXT NOT NULL,
+        error   TEXT
+    );
+    CREATE TABLE IF NOT EXISTS scan_matches (
+        scan_id   INTEGER NOT NULL REFERENCES scans (id),
+        mac       TEXT NOT NULL,
+        ip        TEXT NOT NULL,
+        interface TEXT,
+        hostname  TEXT
+    );
+    CREATE INDEX IF NOT EXISTS scan_matches_mac ON scan_matches (mac)";
+
+/// A finished scan, as [`record_scan`] stores it.
+#[derive(Debug)]
+pub struct ScanRecord<'a> {
+    pub started_at: SystemTime,
+    /// What ran it: `scan`, `inventory`, `watch` or `gui`.
+    pub source: &'a str,
+    /// GUI profile or `--profile`, if any.
+    pub profile: Option<&'a str>,
+    pub targets: &'a [String],
+    /// The range, or the hosts file that replaced it.
+    pub range: &'a str,
+    pub elapsed: Duration,
+    /// The report, unless the scan failed before producing one.
+    pub report: Option<&'a ScanReport>,
+    /// Why the scan failed or found nothing.
+    pub error: Option<&'a str>,
+}
+
+/// A stored scan, as [`history`] returns it.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredScan {
+    pub id: i64,
+    /// RFC 3339.
+    pub started_at: String,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Comma-separated target MACs.
+    pub targets: String,
+    pub ip_range: String,
+    pub elapsed_secs: f64,
+    pub hosts_total: i64,
+    pub hosts_probed: i64,
+    pub matches: Vec<StoredMatch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<StoredScan> for HistoryEntry {
+    fn from(scan: StoredScan) -> Self {
+        let ips: Vec<String> = scan.matches.into_iter().map(|m| m.ip).collect();
+        HistoryEntry {
+            timestamp: scan.started_at,
+            mac: scan.targets,
+            ip_range: scan.ip_range,
+            profile: scan.profile.unwrap_or_default(),
+            ip: (!ips.is_empty()).then(|| ips.join(", ")),
+            error: scan.error,
+            duration_secs: scan.elapsed_secs,
+        }
+    }
+}
+
+/// A MAC located by a stored scan.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredMatch {
+    pub mac: String,
+    pub ip: String,
+}
+
+/// Where and when a MAC was last located, as [`last_seen`] returns it.
+#[derive(Debug, Clone, Serialize)]
+pub struct LastSeen {
+    pub mac: String,
+    pub ip: String,
+    /// RFC 3339 start of the scan that found it.
+    pub seen_at: String,
+}
+
+/// Open the history at `path`, creating it (and its directory) if needed.
+fn open_history(path: &Path) -> rusqlite::Result<Connection> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        // `open` reports a missing directory that cannot be created.
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let conn = Connection::open(path)?;
+    conn.execute_batch(HISTORY_SCHEMA)?;
+    Ok(conn)
+}
+
+/// Append `scan` to the history at `path`, and return its id.
+pub fn record_scan(path: &Path, scan: &ScanRecord) -> rusqlite::Result<i64> {
+    let mut conn = open_history(path)?;
+    let started_at = humantime::format_rfc3339_seconds(scan.started_at).to_string();
+    let stats = scan.report.map(|r| r.stats.clone()).unwrap_or_default();
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO scans (started_at, source, profile, targets, ip_range, elapsed_secs,
+                            hosts_total, hosts_probed, hosts_failed, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            started_at,
+            scan.source,
+            scan.profile,
+            scan.targets.join(", "),
+            scan.range,
+            scan.elapsed.as_secs_f64(),
+            stats.hosts_total as i64,
+            stats.hosts_probed as i64,
+            stats.hosts_failed as i64,
+            scan.error,
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+    if let Some(report) = scan.report {
+        let mut host = tx.prepare(
+            "INSERT INTO scan_hosts (scan_id, ip, status, macs, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for h in &report.hosts {
+            host.execute(params![id, h.ip, h.status.as_str(), h.macs.join(" "), h.error])?;
+        }
+        let mut found = tx.prepare(
+            "INSERT INTO scan_matches (scan_id, mac, ip, interface, hostname)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for m in &report.matches {
+            found.execute(params![id, m.mac, m.ip, m.interface, m.hostname])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(id)
+}
+
+/// The newest `limit` scans at `path`, newest first; with `mac`, only those
+/// that searched for or found it.
+pub fn history(
+    path: &Path,
+    mac: Option<&str>,
+    limit: usize,
+) -> rusqlite::Result<Vec<StoredScan>> {
+    let conn = open_history(path)?;
+    let mac = mac.map(str::to_lowercase);
+    let mut stmt = conn.prepare(
+        "SELECT id, started_at, source, profile, targets, ip_range, elapsed_secs,
+                hosts_total, hosts_probed, error
+         FROM scans
+         WHERE ?1 IS NULL
+            OR instr(lower(targets), ?1) > 0
+            OR id IN (SELECT scan_id FROM scan_matches WHERE mac = ?1)
+         ORDER BY id DESC
+         LIMIT ?2",
+    )?;
+    let mut scans = stmt
+        .query_map(params![mac, limit as i64], |row| {
+            Ok(StoredScan {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                source: row.get(2)?,
+                profile: row.get(3)?,
+                targets: row.get(4)?,
+                ip_range: row.get(5)?,
+                elapsed_secs: row.get(6)?,
+                hosts_total: row.get(7)?,
+                hosts_probed: row.get(8)?,
+                matches: Vec::new(),
+                error: row.get(9)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut found =
+        conn.prepare("SELECT mac, ip FROM scan_matches WHERE scan_id = ?1 ORDER BY rowid")?;
+    for scan in &mut scans {
+        scan.matches = found
+            .query_map([scan.id], |row| {
+                Ok(StoredMatch {
+                    mac: row.get(0)?,
+                    ip: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+    }
+    Ok(scans)
+}
+
+/// The latest location of every MAC any scan at `path` found, by MAC.
+pub fn last_seen(path: &Path) -> rusqlite::Result<Vec<LastSeen>> {
+    let conn = open_history(path)?;
+    let mut stmt = conn.prepare(
+        "SELECT m.mac, m.ip, s.started_at
+         FROM scan_matches m JOIN scans s ON s.id = m.scan_id
+         WHERE m.rowid IN (SELECT max(rowid) FROM scan_matches GROUP BY mac)
+         ORDER BY m.mac",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(LastSeen {
+            mac: row.get(0)?,
+            ip: row.get(1)?,
+            seen_at: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use radar_ip_core::ssh_client::InterfaceMac;
+
+    fn host(ip: &str, mac_list: &[&str], interfaces: &[(&str, &str)]) -> DeviceIdentity {
+        DeviceIdentity {
+            ip: ip.into(),
+            hostname: None,
+            mac_list: mac_list.iter().map(|mac| mac.to_string()).collect(),
+            interfaces: interfaces
+                .iter()
+                .map(|(name, mac)| InterfaceMac {
+                    name: name.to_string(),
+                    mac: mac.to_string(),
+                })
+                .collect(),
+            mac_command: None,
+            details: None,
+        }
+    }
+
+    fn rows(path: &Path) -> Vec<(String, String, String)> {
+        let conn = Connection::open(path).unwrap();
+        let mut stmt = conn.prepare("SELECT ip, mac, interface FROM hosts ORDER BY ip").unwrap();
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)));
+        rows.unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn hosts_without_interfaces_are_written_by_mac() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("inventory.db");
+        let inventory = [
+            host("10.0.0.5", &["b8:27:eb:00:00:05"], &[]),
+            host("10.0.0.6", &["b8:27:eb:00:00:06"], &[("eth0", "b8:27:eb:00:00:06")]),
+        ];
+        assert_eq!(write_inventory(&path, &inventory).unwrap(), 2);
+        assert_eq!(
+            rows(&path),
+            [
+                ("10.0.0.5".into(), "b8:27:eb:00:00:05".into(), String::new()),
+                ("10.0.0.6".into(), "b8:27:eb:00:00:06".into(), "eth0".into()),
+            ]
+        );
+        // A second run refreshes the same rows.
+        assert_eq!(write_inventory(&path, &inventory).unwrap(), 2);
+        assert_eq!(rows(&path).len(), 2);
+    }
+}
@@ -0,0 +1,193 @@
+use radar_ip_core::ssh_client::JumpHost;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A `Host` block of an OpenSSH client config that reaches a found device,
+/// so that `ssh NAME` logs in without a scan. Passwords are never written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostEntry {
+    pub name: String,
+    pub ip: String,
+    pub user: String,
+    pub port: u16,
+    /// The `--key`, made absolute.
+    pub key: Option<PathBuf>,
+    pub via: Option<JumpHost>,
+    /// The MAC it was found by, noted in a comment.
+    pub mac: String,
+}
+
+impl HostEntry {
+    /// The block, indented the way `ssh_config(5)` examples are.
+    pub fn render(&self) -> String {
+        let mut block = format!("Host {}\n", self.name);
+        block.push_str(&format!("    # radar-ip: {}\n", self.mac));
+        block.push_str(&format!("    HostName {}\n", self.ip));
+        block.push_str(&format!("    User {}\n", self.user));
+        if self.port != 22 {
+            block.push_str(&format!("    Port {}\n", self.port));
+        }
+        if let Some(key) = &self.key {
+            block.push_str(&format!("    IdentityFile \"{}\"\n", key.display()));
+            block.push_str("    IdentitiesOnly yes\n");
+        }
+        if let Some(jump) = &self.via {
+            let user = jump.user.as_deref().unwrap_or(&self.user);
+            let host = if jump.host.contains(':') {
+                format!("[{}]", jump.host)
+            } else {
+                jump.host.clone()
+            };
+            block.push_str(&format!("    ProxyJump {}@{}:{}\n", user, host, jump.port));
+        }
+        block
+    }
+}
+
+/// `~/.ssh/config` (`%USERPROFILE%\.ssh\config` on Windows).
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".ssh").join("config"))
+}
+
+/// Write `entry` into the config at `path`: in place of a block already
+/// named `Host <name>` (up to the next `Host` or `Match` line), else before
+/// a `Host *` block, whose settings would otherwise win, or at the end.
+/// The file and its directory are created if missing.
+pub fn write(path: &Path, entry: &HostEntry) -> io::Result<()> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let updated = replace_block(&text, &entry.name, &entry.render());
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        create_private_dir(dir)?;
+    }
+    std::fs::write(path, updated)
+}
+
+/// `text` with the block `Host <name>` replaced by `block`, or `block`
+/// added as a new one.
+fn replace_block(text: &str, name: &str, block: &str) -> String {
+    let mut out = String::new();
+    let mut replaced = false;
+    let mut skipping = false;
+    let mut catch_all = None;
+    for line in text.lines() {
+        let keyword = line.split_whitespace().next().unwrap_or_default().to_lowercase();
+        if keyword == "host" || keyword == "match" {
+            let names: Vec<&str> = line.split_whitespace().skip(1).collect();
+            if keyword == "host" && names == ["*"] {
+                catch_all.get_or_insert(out.len());
+            }
+            let was_skipping = skipping;
+            skipping = keyword == "host" && names == [name];
+            if was_skipping && !skipping && !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            if skipping && !replaced {
+                out.push_str(block);
+                replaced = true;
+            }
+        }
+        if !skipping {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    match catch_all {
+        _ if replaced => {}
+        Some(at) => out.insert_str(at, &format!("{}\n", block)),
+        None => {
+            if !out.trim().is_empty() {
+                out.push('\n');
+            }
+            out.push_str(block);
+        }
+    }
+    out
+}
+
+/// Create `dir` (and its parents) readable by the owner only, as `ssh`
+/// expects of `~/.ssh`.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "Host dev\n    HostName 10.0.0.2\n";
+
+    fn entry(ip: &str) -> HostEntry {
+        HostEntry {
+            name: "dev".into(),
+            ip: ip.into(),
+            user: "pi".into(),
+            port: 2222,
+            key: None,
+            via: None,
+            mac: "aa:bb:cc:dd:ee:ff".into(),
+        }
+    }
+
+    #[test]
+    fn existing_block_is_replaced_in_place() {
+        let text = "Host a\n    HostName 1\n\nHost dev\n    HostName 10.0.0.1\n    User old\n\n\
+                    Host b\n    HostName 2\n";
+        assert_eq!(
+            replace_block(text, "dev", BLOCK),
+            "Host a\n    HostName 1\n\nHost dev\n    HostName 10.0.0.2\n\nHost b\n    HostName 2\n"
+        );
+    }
+
+    #[test]
+    fn new_block_goes_before_the_catch_all() {
+        let text = "Host a\n    User x\n\nHost *\n    User root\n";
+        assert_eq!(
+            replace_block(text, "dev", BLOCK),
+            "Host a\n    User x\n\nHost dev\n    HostName 10.0.0.2\n\nHost *\n    User root\n"
+        );
+        assert_eq!(replace_block("", "dev", BLOCK), BLOCK);
+        assert_eq!(replace_block("Host a\n", "dev", BLOCK), format!("Host a\n\n{}", BLOCK));
+    }
+
+    #[test]
+    fn match_blocks_are_left_alone() {
+        // The replaced block ends at the Match line, which stays.
+        let text = "Host dev\n    HostName old\nMatch host dev exec \"true\"\n    User matched\n";
+        assert_eq!(
+            replace_block(text, "dev", BLOCK),
+            "Host dev\n    HostName 10.0.0.2\n\nMatch host dev exec \"true\"\n    User matched\n"
+        );
+        // A Match naming the host is not its block.
+        let text = "Match host dev\n    User m\n";
+        assert_eq!(replace_block(text, "dev", BLOCK), format!("{}\n{}", text, BLOCK));
+    }
+
+    #[test]
+    fn write_creates_a_missing_file_then_replaces_its_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".ssh").join("config");
+        write(&path, &entry("10.0.0.1")).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), entry("10.0.0.1").render());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(path.parent().unwrap()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        write(&path, &entry("10.0.0.2")).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, entry("10.0.0.2").render());
+        assert!(text.contains("    Port 2222\n"));
+    }
+}
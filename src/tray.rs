@@ -0,0 +1,372 @@
+use eframe::egui;
+use std::sync::mpsc::Receiver;
+
+/// What was picked in the tray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// "Scan now": start a scan, or the next round of a watch early.
+    ScanNow,
+    /// "Show window", or a click on the icon.
+    Show,
+    Quit,
+}
+
+/// The GUI's icon in the system tray, while it lives.
+///
+/// On Linux and the BSDs it is a StatusNotifierItem on the D-Bus session
+/// bus, which KDE, Xfce, most other desktops and GNOME with the
+/// AppIndicator extension show; no tray crate is part of the build, so
+/// other platforms have no tray yet.
+pub struct Tray {
+    events: Receiver<TrayEvent>,
+    #[cfg(all(unix, not(target_os = "macos")))]
+    item: sni::Item,
+}
+
+impl Tray {
+    /// Put the icon in the tray. `None`, after logging why, when there is
+    /// no tray to put it in.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn start(ctx: &egui::Context) -> Option<Self> {
+        let (sender, events) = std::sync::mpsc::channel();
+        match sni::Item::register(ctx.clone(), sender) {
+            Ok(item) => Some(Self { events, item }),
+            Err(e) => {
+                tracing::info!("No system tray icon: {}", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    pub fn start(_ctx: &egui::Context) -> Option<Self> {
+        tracing::info!("No system tray icon on this platform");
+        None
+    }
+
+    /// The next pick not handled yet.
+    pub fn event(&self) -> Option<TrayEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Show `ip` as the last IP found, in the menu and the tooltip.
+    pub fn set_last_found(&self, ip: Option<&str>) {
+        #[cfg(all(unix, not(target_os = "macos")))]
+        self.item.set_last_found(ip);
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        let _ = ip;
+    }
+}
+
+/// The StatusNotifierItem and its com.canonical.dbusmenu menu.
+#[cfg(all(unix, not(target_os = "macos")))]
+mod sni {
+    use super::TrayEvent;
+    use eframe::egui;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::sync::{Arc, Mutex};
+    use zbus::blocking::connection::Builder;
+    use zbus::blocking::Connection;
+    use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+    const ITEM_PATH: &str = "/StatusNotifierItem";
+    const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+    const MENU_PATH: &str = "/MenuBar";
+    const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+
+    /// Ids of the menu entries; the root is 0.
+    const SCAN_NOW: i32 = 1;
+    const LAST_FOUND: i32 = 2;
+    const SEPARATOR: i32 = 3;
+    const SHOW: i32 = 4;
+    const QUIT: i32 = 5;
+    const ENTRIES: [i32; 5] = [SCAN_NOW, LAST_FOUND, SEPARATOR, SHOW, QUIT];
+
+    /// An icon as the spec wants it: width, height and ARGB32 pixels in
+    /// network byte order.
+    type Pixmap = (i32, i32, Vec<u8>);
+
+    /// State shared by both D-Bus objects.
+    struct Shared {
+        ctx: egui::Context,
+        events: Mutex<Sender<TrayEvent>>,
+        last_found: Mutex<Option<String>>,
+        /// Bumped whenever the menu changes.
+        revision: AtomicU32,
+    }
+
+    impl Shared {
+        fn send(&self, event: TrayEvent) {
+            let _ = self.events.lock().unwrap().send(event);
+            self.ctx.request_repaint();
+        }
+
+        fn tool_tip(&self) -> String {
+            match &*self.last_found.lock().unwrap() {
+                Some(ip) => format!("Last found at {}", ip),
+                None => "No device found yet".into(),
+            }
+        }
+    }
+
+    /// Registered with the tray host until dropped, which closes the
+    /// connection and so removes the icon.
+    pub struct Item {
+        connection: Connection,
+        shared: Arc<Shared>,
+    }
+
+    impl Item {
+        /// Serve the item and its menu, then announce them to the
+        /// StatusNotifierWatcher of the session.
+        pub fn register(ctx: egui::Context, events: Sender<TrayEvent>) -> zbus::Result<Self> {
+            let shared = Arc::new(Shared {
+                ctx,
+                events: Mutex::new(events),
+                last_found: Mutex::new(None),
+                revision: AtomicU32::new(1),
+            });
+            let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+            let connection = Builder::session()?
+                .name(name.as_str())?
+                .serve_at(ITEM_PATH, StatusNotifierItem(shared.clone()))?
+                .serve_at(MENU_PATH, DbusMenu(shared.clone()))?
+                .build()?;
+            connection.call_method(
+                Some("org.kde.StatusNotifierWatcher"),
+                "/StatusNotifierWatcher",
+                Some("org.kde.StatusNotifierWatcher"),
+                "RegisterStatusNotifierItem",
+                &(name.as_str(),),
+            )?;
+            Ok(Self { connection, shared })
+        }
+
+        pub fn set_last_found(&self, ip: Option<&str>) {
+            *self.shared.last_found.lock().unwrap() = ip.map(str::to_string);
+            let revision = self.shared.revision.fetch_add(1, Ordering::SeqCst) + 1;
+            let sent = self
+                .connection
+                .emit_signal(
+                    None::<&str>,
+                    MENU_PATH,
+                    MENU_INTERFACE,
+                    "LayoutUpdated",
+                    &(revision, 0),
+                )
+                .and_then(|()| {
+                    self.connection.emit_signal(
+                        None::<&str>,
+                        ITEM_PATH,
+                        ITEM_INTERFACE,
+                        "NewToolTip",
+                        &(),
+                    )
+                });
+            if let Err(e) = sent {
+                tracing::debug!("Cannot update the tray menu: {}", e);
+            }
+        }
+    }
+
+    struct StatusNotifierItem(Arc<Shared>);
+
+    #[zbus::interface(name = "org.kde.StatusNotifierItem")]
+    impl StatusNotifierItem {
+        fn activate(&self, _x: i32, _y: i32) {
+            self.0.send(TrayEvent::Show);
+        }
+
+        fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+        fn context_menu(&self, _x: i32, _y: i32) {}
+
+        fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+        #[zbus(property)]
+        fn category(&self) -> &str {
+            "ApplicationStatus"
+        }
+
+        #[zbus(property)]
+        fn id(&self) -> &str {
+            "radar-ip"
+        }
+
+        #[zbus(property)]
+        fn title(&self) -> &str {
+            "Radar-IP Scanner"
+        }
+
+        #[zbus(property)]
+        fn status(&self) -> &str {
+            "Active"
+        }
+
+        #[zbus(property)]
+        fn window_id(&self) -> i32 {
+            0
+        }
+
+        #[zbus(property)]
+        fn icon_name(&self) -> &str {
+            "network-wireless"
+        }
+
+        #[zbus(property)]
+        fn icon_pixmap(&self) -> Vec<Pixmap> {
+            vec![icon()]
+        }
+
+        #[zbus(property)]
+        fn tool_tip(&self) -> (&str, Vec<Pixmap>, &str, String) {
+            ("", Vec::new(), "Radar-IP Scanner", self.0.tool_tip())
+        }
+
+        #[zbus(property)]
+        fn item_is_menu(&self) -> bool {
+            false
+        }
+
+        #[zbus(property)]
+        fn menu(&self) -> ObjectPath<'_> {
+            ObjectPath::from_static_str_unchecked(MENU_PATH)
+        }
+    }
+
+    /// One menu entry with its children, `(ia{sv}av)`.
+    type Layout = (i32, HashMap<&'static str, Value<'static>>, Vec<Value<'static>>);
+
+    struct DbusMenu(Arc<Shared>);
+
+    impl DbusMenu {
+        fn properties(&self, id: i32) -> HashMap<&'static str, Value<'static>> {
+            let mut properties = HashMap::new();
+            let label = match id {
+                0 => {
+                    properties.insert("children-display", Value::from("submenu"));
+                    return properties;
+                }
+                SCAN_NOW => "Scan now".to_string(),
+                LAST_FOUND => {
+                    properties.insert("enabled", Value::from(false));
+                    match &*self.0.last_found.lock().unwrap() {
+                        Some(ip) => format!("Last found: {}", ip),
+                        None => "No device found yet".into(),
+                    }
+                }
+                SEPARATOR => {
+                    properties.insert("type", Value::from("separator"));
+                    return properties;
+                }
+                SHOW => "Show window".into(),
+                _ => "Quit".into(),
+            };
+            properties.insert("label", Value::from(label));
+            properties
+        }
+
+        fn layout(&self, id: i32) -> Layout {
+            let children = match id {
+                0 => ENTRIES.iter().map(|&entry| Value::from(self.layout(entry))).collect(),
+                _ => Vec::new(),
+            };
+            (id, self.properties(id), children)
+        }
+
+        fn clicked(&self, id: i32, event_id: &str) {
+            let event = match id {
+                SCAN_NOW => TrayEvent::ScanNow,
+                SHOW => TrayEvent::Show,
+                QUIT => TrayEvent::Quit,
+                _ => return,
+            };
+            if event_id == "clicked" {
+                self.0.send(event);
+            }
+        }
+    }
+
+    #[zbus::interface(name = "com.canonical.dbusmenu")]
+    impl DbusMenu {
+        fn get_layout(
+            &self,
+            parent_id: i32,
+            _recursion_depth: i32,
+            _property_names: Vec<String>,
+        ) -> (u32, Layout) {
+            (self.0.revision.load(Ordering::SeqCst), self.layout(parent_id))
+        }
+
+        fn get_group_properties(
+            &self,
+            ids: Vec<i32>,
+            _property_names: Vec<String>,
+        ) -> Vec<(i32, HashMap<&'static str, Value<'static>>)> {
+            ids.into_iter().map(|id| (id, self.properties(id))).collect()
+        }
+
+        fn get_property(&self, id: i32, name: &str) -> zbus::fdo::Result<Value<'static>> {
+            self.properties(id)
+                .remove(name)
+                .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("No {} on {}", name, id)))
+        }
+
+        fn event(&self, id: i32, event_id: &str, _data: OwnedValue, _timestamp: u32) {
+            self.clicked(id, event_id);
+        }
+
+        fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+            for (id, event_id, _, _) in &events {
+                self.clicked(*id, event_id);
+            }
+            Vec::new()
+        }
+
+        fn about_to_show(&self, _id: i32) -> bool {
+            false
+        }
+
+        fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+            (Vec::new(), Vec::new())
+        }
+
+        #[zbus(property)]
+        fn version(&self) -> u32 {
+            3
+        }
+
+        #[zbus(property)]
+        fn text_direction(&self) -> &str {
+            "ltr"
+        }
+
+        #[zbus(property)]
+        fn status(&self) -> &str {
+            "normal"
+        }
+
+        #[zbus(property)]
+        fn icon_theme_path(&self) -> Vec<String> {
+            Vec::new()
+        }
+    }
+
+    /// A 32×32 radar in the GUI's accent colour, for trays without the
+    /// themed icon: two rings around a dot.
+    fn icon() -> Pixmap {
+        const SIZE: i32 = 32;
+        let mut argb = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let (dx, dy) = (x as f32 - 15.5, y as f32 - 15.5);
+                let r = (dx * dx + dy * dy).sqrt();
+                let on = r < 3.0 || (8.0..10.5).contains(&r) || (13.0..15.5).contains(&r);
+                argb.extend_from_slice(&[if on { 0xff } else { 0 }, 30, 120, 200]);
+            }
+        }
+        (SIZE, SIZE, argb)
+    }
+}
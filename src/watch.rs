@@ -0,0 +1,93 @@
+use crate::checkpoint::Checkpoint;
+use radar_ip_core::report::ScanMatch;
+use std::collections::BTreeMap;
+
+/// What one scan of `radar-ip watch` changed about a watched MAC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Seen for the first time, or again at its last known IP.
+    Found { mac: String, ip: String },
+    /// Seen at another IP than last time.
+    Moved { mac: String, from: String, to: String },
+    /// Not seen by this scan; `ip` is where it was last.
+    Lost { mac: String, ip: String },
+}
+
+impl Change {
+    /// `found`, `moved` or `lost`.
+    pub fn event(&self) -> &'static str {
+        match self {
+            Change::Found { .. } => "found",
+            Change::Moved { .. } => "moved",
+            Change::Lost { .. } => "lost",
+        }
+    }
+
+    /// The MAC and the IP it has now, unless it was lost.
+    pub fn located(&self) -> Option<(&str, &str)> {
+        match self {
+            Change::Found { mac, ip } | Change::Moved { mac, to: ip, .. } => Some((mac, ip)),
+            Change::Lost { .. } => None,
+        }
+    }
+}
+
+/// Last known IP of each watched MAC, and whether the latest scan saw it.
+#[derive(Debug)]
+pub struct Watcher {
+    devices: BTreeMap<String, Device>,
+}
+
+#[derive(Debug)]
+struct Device {
+    ip: Option<String>,
+    present: bool,
+}
+
+impl Watcher {
+    /// Watch `targets`, starting from the IPs in `checkpoint`.
+    pub fn new(targets: &[String], checkpoint: Option<&Checkpoint>) -> Self {
+        let devices = targets
+            .iter()
+            .map(|mac| {
+                let ip = checkpoint.and_then(|c| c.get(mac)).map(str::to_string);
+                let device = Device { ip, present: false };
+                (mac.to_lowercase(), device)
+            })
+            .collect();
+        Self { devices }
+    }
+
+    /// Compare the `matches` of a new scan with the previous one.
+    pub fn update(&mut self, matches: &[ScanMatch]) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (mac, device) in &mut self.devices {
+            // Matches are sorted by address: the lowest IP stands for a MAC
+            // seen on several.
+            let seen = matches.iter().find(|m| m.mac.eq_ignore_ascii_case(mac));
+            let change = match (seen, &device.ip) {
+                (Some(m), Some(last)) if *last != m.ip => Some(Change::Moved {
+                    mac: mac.clone(),
+                    from: last.clone(),
+                    to: m.ip.clone(),
+                }),
+                (Some(_), _) if device.present => None,
+                (Some(m), _) => Some(Change::Found {
+                    mac: mac.clone(),
+                    ip: m.ip.clone(),
+                }),
+                (None, Some(last)) if device.present => Some(Change::Lost {
+                    mac: mac.clone(),
+                    ip: last.clone(),
+                }),
+                (None, _) => None,
+            };
+            if let Some(m) = seen {
+                device.ip = Some(m.ip.clone());
+            }
+            device.present = seen.is_some();
+            changes.extend(change);
+        }
+        changes
+    }
+}